@@ -1,27 +1,56 @@
-use std::io::{self};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
 use std::ops::Add;
-use std::sync::mpsc::Receiver;
-use std::time::Duration;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::vec;
 
-use bluer::{AdapterEvent, DeviceEvent, SessionEvent};
-use crossterm::event::{self};
+use bluer::{AdapterEvent, Address, AddressType, DeviceEvent, DiscoveryTransport, SessionEvent};
+use chrono::Local;
+use crossterm::event::EventStream;
 use futures::StreamExt;
+use itertools::Itertools;
 use ratatui::crossterm::event::Event;
 use ratatui::crossterm::{self};
-use ratatui::layout::Position;
-use ratatui::widgets::TableState;
+use ratatui::layout::{Alignment, Position, Rect};
+use ratatui::widgets::{Paragraph, TableState};
+use ratatui::Frame;
 use ratatui_helpers::keymap::KeyMap;
 use ratatui_helpers::status_line::StatusId;
+use ratatui_helpers::view::View;
 use ratatui_helpers::view_controller::ViewController;
 use tokio::sync::oneshot::error::TryRecvError;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
 
-use crate::bt_manager::{BtManager, TaskStatus};
-use crate::helpers::{try_init_term, try_release_term};
+use crate::bt_manager::{monitor_device, scan_events, BtManager, TaskStatus};
+use crate::cli::DeepLink;
+use crate::config::{Config, ScanType, Setup};
+use crate::errors::friendly_message;
+use crate::filter::address_matches;
+use crate::globals::{CONFIG, EVENT_QUEUE};
+use crate::helpers::{copy_to_clipboard, on_ac_power, try_init_term, try_release_term};
+use crate::hexdump::{parse_hex, Endian};
+use crate::i18n::tr;
 use crate::keymaps::{AppCommand, AppKeyMap};
-use crate::models::{Adapter, AdapterAction, AdapterId, DeviceAction, DeviceId};
+use crate::metrics;
+use crate::models::{
+    Adapter, AdapterAction, AdapterCapabilities, AdapterId, DeviceAction, DeviceId, GattRow,
+    PairingChoice, Popup, PrivacyChoice, PropertyRow, ProximityState, QuitChoice, RetryChoice,
+    ServiceChoice, Severity, StartupChoice, UnblockChoice, WriteType,
+};
+use crate::mqtt::Publisher;
+use crate::rfkill::{rfkill_state, unblock, Rfkill};
+use crate::service::{service_state, start_service, SERVICE};
+use crate::state::{Snippet, State};
+use crate::theme::{Icons, StyledWidget};
+use crate::ticker::{ticks, until_next_tick, Timers};
 use crate::views::{
-    AdapterActionsView, AdapterView, DeviceActionsView, DeviceView, HelpView, PopupView,
+    AdapterActionsView, AdapterView, AddressView, BatchView, BatteryView, CheatsheetView,
+    ConfirmView, DeviceActionsView, DeviceInfoView, DeviceView, GattView, HelpView, HistoryView,
+    MonitorView, PinView, PopupView, QuickView, SearchView, SummaryView, WriteView,
 };
 
 #[derive(PartialEq)]
@@ -34,6 +63,26 @@ pub enum ViewKind {
     NotificationView,
     HelpView,
     StatusView,
+    RetryView,
+    MonitorView,
+    GattView,
+    DeviceInfoView,
+    BatteryView,
+    SummaryView,
+    HistoryView,
+    QuickView,
+    CheatsheetView,
+    InputView,
+    BatchView,
+    WelcomeView,
+    ServiceView,
+    UnblockView,
+    StartupView,
+    PrivacyView,
+    PinView,
+    PairingPromptView,
+    PairElsewhereView,
+    QuitView,
 }
 
 #[derive(Clone, Default, Debug)]
@@ -42,18 +91,101 @@ pub enum AppRequest {
     None,
     RefreshViews,
     CloseView,
+    Quit,
+    QuitWhenIdle,
+    GoBack,
+    GoForward,
     OpenHelpView,
-    OpenPopupView(String),
+    OpenPopupView(Popup),
     OpenAdaptersView,
     OpenAdapterActionsViewAt(Adapter, Position),
     ExecAdapterAction(Adapter, AdapterAction),
     OpenDevicesView(Adapter),
+    FocusDevice(DeviceId),
     OpenDeviceActionsViewAt(Adapter, DeviceId, Position),
     ExecDeviceAction(AdapterId, DeviceId, DeviceAction),
     MonitorDevice(AdapterId, DeviceId),
+    OpenBatteryView(DeviceId),
+    OpenSummaryView(AdapterId),
+    OpenCheatsheet,
+    OpenHistoryView,
+    OpenQuickView,
+    ExportActions,
+    OpenDeviceInfoView(AdapterId, DeviceId),
+    OpenGattView(AdapterId, DeviceId),
+    ReadCharacteristic((u16, u16)),
+    ToggleGattPoll((u16, u16)),
+    OpenWriteView(GattRow),
+    WriteCharacteristic((u16, u16), String, WriteType),
+    SendSnippet((u16, u16), Snippet),
+    ToggleQuickDevice(String),
+    CopyToClipboard(String),
+    CancelPairing,
+    AnswerPairing(bool),
+    RepeatAdapterAction(Adapter),
+    RepeatDeviceAction(AdapterId, DeviceId),
+    PromptRetry(AdapterId, DeviceId, DeviceAction, String),
+    BatchPair(AdapterId, Vec<DeviceId>),
+    OpenWelcomeView,
+    SaveSetup(Setup),
+    PowerAllAdapters(bool),
+    OpenServiceView,
+    StartService,
+    OpenUnblockView(Adapter),
+    UnblockAdapter(Adapter),
+    OpenStartupView(Vec<String>),
+    SetPrivacy(Adapter, bool),
+    MuteStartupChecks(Vec<String>),
+    ExpireInRange(DeviceId),
+    ShowStatus(String),
+    OpenAddressView(AdapterId),
+    ConnectAddress(AdapterId, String),
+    OpenSearchView,
+    OpenPairElsewhereView(AdapterId, DeviceId),
+    PairOnAdapter(AdapterId, DeviceId),
+    ExpirePairElsewhere(DeviceId),
+    KeepPowered,
+    BackgroundScan,
+    SaveState,
+    ToggleScanType,
+    AcknowledgeNew(AdapterId),
+    AcknowledgeDevice(DeviceId),
     Chain(Vec<AppRequest>),
 }
 impl AppRequest {
+    fn is_page(&self) -> bool {
+        matches!(
+            self,
+            AppRequest::OpenHelpView
+                | AppRequest::OpenAdaptersView
+                | AppRequest::OpenDevicesView(_)
+                | AppRequest::FocusDevice(_)
+                | AppRequest::MonitorDevice(_, _)
+                | AppRequest::OpenBatteryView(_)
+                | AppRequest::OpenSummaryView(_)
+                | AppRequest::OpenHistoryView
+                | AppRequest::OpenQuickView
+        )
+    }
+    /// The adapter whose state the view opened by this request shows
+    fn bound_adapter(&self, bt: &BtManager) -> Option<AdapterId> {
+        match self {
+            AppRequest::OpenDevicesView(adapter)
+            | AppRequest::OpenAdapterActionsViewAt(adapter, _)
+            | AppRequest::OpenDeviceActionsViewAt(adapter, _, _)
+            | AppRequest::OpenUnblockView(adapter) => Some(adapter.id),
+            AppRequest::MonitorDevice(adapter_id, _)
+            | AppRequest::OpenDeviceInfoView(adapter_id, _)
+            | AppRequest::OpenGattView(adapter_id, _)
+            | AppRequest::OpenSummaryView(adapter_id)
+            | AppRequest::OpenAddressView(adapter_id)
+            | AppRequest::PromptRetry(adapter_id, _, _, _)
+            | AppRequest::BatchPair(adapter_id, _)
+            | AppRequest::OpenPairElsewhereView(adapter_id, _) => Some(*adapter_id),
+            AppRequest::FocusDevice(device_id) => bt.get_device_adapter(device_id).map(|a| a.id),
+            _ => None,
+        }
+    }
     fn or_else<T: FnOnce() -> Self>(self, other: T) -> Self {
         if let AppRequest::None = self {
             return other();
@@ -83,70 +215,492 @@ impl Add for AppRequest {
     }
 }
 
+/// Low priority messages are dropped for this long after an important one
+const STATUS_QUIET: Duration = Duration::from_secs(3);
+/// Low priority messages are shown at most this often
+const STATUS_INTERVAL: Duration = Duration::from_millis(500);
+/// How long the offer to connect a device that came into range stays valid
+const IN_RANGE_OFFER: Duration = Duration::from_secs(30);
+/// How long before an idle adapter is powered off the user is warned
+const IDLE_WARNING: Duration = Duration::from_secs(30);
+/// How long to scan for a device that should be paired on another adapter
+const PAIR_ELSEWHERE_WAIT: Duration = Duration::from_secs(60);
+/// Delay between attempts to restart an event stream that ended
+const MONITOR_RETRY: Duration = Duration::from_secs(5);
+/// How often the state is saved, so a crash or a kill loses little of it
+const SAVE_STATE_EVERY: Duration = Duration::from_secs(60);
+/// Adapter additions and removals queued for the interface, the monitor waits past it
+const SESSION_QUEUE: usize = 64;
+
+/// Takes the queued events, counting the ones the channel dropped to make room
+fn drain<T: Clone>(rx: &mut broadcast::Receiver<T>, dropped: &mut u64) -> Vec<T> {
+    let mut events = vec![];
+    loop {
+        match rx.try_recv() {
+            Ok(ev) => events.push(ev),
+            Err(broadcast::error::TryRecvError::Lagged(n)) => *dropped += n,
+            Err(_) => return events,
+        }
+    }
+}
+
+/// The status line text of a discovery event
+fn describe_adapter_event(ev: &AdapterEvent) -> String {
+    use bluer::AdapterProperty as P;
+    let on_off = |on: &bool| match on {
+        true => "on",
+        false => "off",
+    };
+    match ev {
+        AdapterEvent::DeviceAdded(addr) => format!("Found {}", DeviceId(*addr)),
+        AdapterEvent::DeviceRemoved(addr) => format!("Lost {}", DeviceId(*addr)),
+        AdapterEvent::PropertyChanged(prop) => match prop {
+            P::Powered(on) => format!("Adapter powered {}", on_off(on)),
+            P::Pairable(on) => format!("Adapter pairable {}", on_off(on)),
+            P::Discoverable(on) => format!("Adapter discoverable {}", on_off(on)),
+            P::Discovering(true) => "Scan started".to_string(),
+            P::Discovering(false) => "Scan stopped".to_string(),
+            P::DiscoverableTimeout(secs) => format!("Discoverable timeout set to {secs}s"),
+            P::Alias(alias) => format!("Adapter renamed to {alias}"),
+            _ => "Adapter properties changed".to_string(),
+        },
+    }
+}
+
+/// Waits for the next event of the receiver, forever while there is none
+async fn recv<T>(rx: &mut Option<mpsc::Receiver<T>>) -> Option<T> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+async fn recv_unbounded<T>(rx: &mut Option<mpsc::UnboundedReceiver<T>>) -> Option<T> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+/// Waits for the next event of the receiver along with the number it dropped before it
+async fn recv_broadcast<T: Clone>(rx: &mut Option<broadcast::Receiver<T>>) -> Option<(T, u64)> {
+    let Some(rx) = rx else {
+        return std::future::pending().await;
+    };
+    let mut lagged = 0;
+    loop {
+        match rx.recv().await {
+            Ok(ev) => return Some((ev, lagged)),
+            Err(broadcast::error::RecvError::Lagged(n)) => lagged += n,
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// Sits in front of the status line, collapsing repeated messages into a
+/// counter and keeping chatty low priority events from hiding results.
+#[derive(Default)]
+struct StatusGate {
+    last: Option<(String, usize)>,
+    last_shown: Option<Instant>,
+    important_at: Option<Instant>,
+}
+impl StatusGate {
+    fn show(&mut self, vc: &mut AppViewController, msg: String) {
+        if let Some(msg) = self.admit(msg, false) {
+            vc.show_status(msg);
+        }
+    }
+    fn show_low(&mut self, vc: &mut AppViewController, msg: String) {
+        if let Some(msg) = self.admit(msg, true) {
+            vc.show_status(msg);
+        }
+    }
+    fn admit(&mut self, msg: String, low_priority: bool) -> Option<String> {
+        let now = Instant::now();
+        let count = match &mut self.last {
+            Some((last, count)) if *last == msg => {
+                *count += 1;
+                *count
+            }
+            _ => {
+                self.last = Some((msg.clone(), 1));
+                1
+            }
+        };
+        let since = |t: Option<Instant>| t.map_or(Duration::MAX, |t| now - t);
+        if low_priority {
+            if since(self.important_at) < STATUS_QUIET || since(self.last_shown) < STATUS_INTERVAL {
+                return None;
+            }
+        } else {
+            self.important_at = Some(now);
+        }
+        self.last_shown = Some(now);
+        Some(match count {
+            1 => msg,
+            n => format!("{msg} (x{n})"),
+        })
+    }
+}
+
+type AppViewController = ViewController<BtManager, AppRequest, ViewKind>;
+
 pub struct App {
     bt: BtManager,
-    vc: ViewController<BtManager, AppRequest, ViewKind>,
+    vc: AppViewController,
     keymap: AppKeyMap,
+    history: Vec<AppRequest>,
+    forward_history: Vec<AppRequest>,
+    quit_when_idle: bool,
+    status: StatusGate,
+    in_range: Option<(AdapterId, DeviceId)>,
+    pair_elsewhere: Option<(AdapterId, DeviceId)>,
+    /// Idle adapters whose power off was announced
+    idle_warned: HashSet<AdapterId>,
+    timers: Timers<AppRequest>,
+    added_adapter: Option<String>,
+    last_adapter_action: Option<AdapterAction>,
+    scan_adapter: Option<AdapterId>,
+    pair_status: Option<(StatusId, u64)>,
+    /// Status entry of each running action, by its index in the action history
+    task_status: HashMap<usize, StatusId>,
+    scan_type: ScanType,
+    /// Page served on the metrics endpoint, when it is listening
+    metrics: Option<Arc<Mutex<String>>>,
+    mqtt: Option<Publisher>,
 
-    session_event_rx: Option<Receiver<SessionEvent>>,
-    adapter_event_rx: Option<Receiver<AdapterEvent>>,
+    session_event_rx: Option<mpsc::Receiver<SessionEvent>>,
+    session_event_task: Option<JoinHandle<()>>,
+    /// Status entry warning about the event streams that ended, and its text
+    monitor_badge: Option<(StatusId, String)>,
+    monitor_retry: Option<Instant>,
+    /// Devices found and lost by the scan, never dropped so that no row is missed
+    device_list_rx: Option<mpsc::UnboundedReceiver<AdapterEvent>>,
+    adapter_event_rx: Option<broadcast::Receiver<AdapterEvent>>,
     stop_adapter_event_sx: Option<tokio::sync::oneshot::Sender<()>>,
-    device_event_rx: Option<Receiver<DeviceEvent>>,
+    adapter_event_task: Option<JoinHandle<()>>,
+    device_event_rx: Option<broadcast::Receiver<DeviceEvent>>,
     stop_device_event_sx: Option<tokio::sync::oneshot::Sender<()>>,
+    service_prompted: bool,
+    service_start: Option<tokio::sync::oneshot::Receiver<Result<(), String>>>,
+    /// Events dropped since startup because the interface fell behind
+    dropped_events: u64,
 }
 impl App {
-    pub async fn new() -> Self {
-        Self {
-            bt: BtManager::new().await,
+    pub async fn new() -> bluer::Result<Self> {
+        Ok(Self {
+            bt: BtManager::new().await?,
             vc: ViewController::new(Duration::from_secs(3)),
             keymap: KeyMap::default(),
+            history: Vec::new(),
+            forward_history: Vec::new(),
+            quit_when_idle: false,
+            status: StatusGate::default(),
+            in_range: None,
+            pair_elsewhere: None,
+            idle_warned: HashSet::new(),
+            timers: Timers::default(),
+            added_adapter: None,
+            last_adapter_action: None,
+            scan_adapter: None,
+            pair_status: None,
+            task_status: HashMap::new(),
+            scan_type: CONFIG.scan_type,
+            metrics: None,
+            mqtt: None,
             session_event_rx: Default::default(),
+            session_event_task: None,
+            monitor_badge: None,
+            monitor_retry: None,
+            device_list_rx: Default::default(),
             adapter_event_rx: Default::default(),
             stop_adapter_event_sx: Default::default(),
+            adapter_event_task: Default::default(),
             device_event_rx: Default::default(),
             stop_device_event_sx: Default::default(),
-        }
+            service_prompted: false,
+            service_start: None,
+            dropped_events: 0,
+        })
     }
-    pub async fn init(mut self) -> Self {
+    pub async fn init(mut self, link: Option<DeepLink>) -> Self {
         self.monitor_session();
-        self.handle_request(AppRequest::RefreshViews).await;
+        if self.bt.get_adapters(&Adapter::BY_ADDRESS).is_empty() {
+            self.handle_request(AppRequest::RefreshViews).await;
+        }
 
-        let req = match self.bt.get_adapters(&Adapter::BY_CONNECTIONS).first() {
+        let adapters = self.bt.get_adapters(&Adapter::BY_CONNECTIONS);
+        let preferred = CONFIG.default_adapter.as_ref().and_then(|name| {
+            adapters
+                .iter()
+                .find(|a| a.name == *name || a.id.to_string().eq_ignore_ascii_case(name))
+        });
+        let req = match preferred.or(adapters.first()) {
+            Some(a) if CONFIG.scan_on_startup && !a.is_scanning => {
+                AppRequest::OpenDevicesView(a.clone())
+                    + AppRequest::ExecAdapterAction(a.clone(), AdapterAction::SetScanning(true))
+            }
             Some(a) => AppRequest::OpenDevicesView(a.clone()),
             _ => AppRequest::OpenAdaptersView,
         };
+        let req = match CONFIG.resume_scan {
+            true => match self.bt.resume_scan().await {
+                Some(Ok(a)) if !a.is_scanning => {
+                    AppRequest::OpenDevicesView(a.clone())
+                        + AppRequest::ExecAdapterAction(a, AdapterAction::SetScanning(true))
+                }
+                Some(Err(e)) => {
+                    self.status
+                        .show(&mut self.vc, format!("Could not resume the scan: {e}"));
+                    req
+                }
+                _ => req,
+            },
+            false => req,
+        };
+        let req = match link {
+            Some(link) => match self.bt.get_device_adapter(&link.device) {
+                Some(adapter) if link.connect => {
+                    AppRequest::FocusDevice(link.device)
+                        + AppRequest::ExecDeviceAction(
+                            adapter.id,
+                            link.device,
+                            DeviceAction::SetConnected(true),
+                        )
+                }
+                Some(_) => AppRequest::FocusDevice(link.device),
+                None => {
+                    self.status
+                        .show(&mut self.vc, format!("Device {} not found", link.device));
+                    req
+                }
+            },
+            None => req,
+        };
+        // first run, nothing configured or remembered yet
+        let req = match State::exists() || Config::path().is_none_or(|p| p.exists()) {
+            true => req,
+            false => req + AppRequest::OpenWelcomeView,
+        };
+        let req = match CONFIG.errors.is_empty() {
+            true => req,
+            false => {
+                req + AppRequest::OpenPopupView(Popup::new(
+                    Severity::Warning,
+                    "Invalid configuration",
+                    CONFIG.errors.join("\n"),
+                ))
+            }
+        };
+        if let Some(e) = self.bt.identities_error.clone() {
+            self.status.show(&mut self.vc, e);
+        }
+        if let Some(e) = self.bt.agent_error.clone() {
+            let msg = format!("Can't register the pairing agent: {e}");
+            self.status.show(&mut self.vc, msg);
+        }
+        let problems = self.bt.health_check().await;
+        let problems = problems
+            .into_iter()
+            .filter(|p| !self.bt.state.muted_checks.contains(p))
+            .collect_vec();
+        let req = match problems.is_empty() {
+            true => req,
+            false => req + AppRequest::OpenStartupView(problems),
+        };
 
         self.handle_request(req).await;
         self
     }
-    pub async fn run(mut self) -> Result<(), Box<io::Error>> {
-        let mut term = try_init_term()?;
+    pub async fn run(mut self, inline: bool, summary: bool) -> Result<(), Box<io::Error>> {
+        let mut term = try_init_term(inline)?;
         self.vc.curr().set_title();
+        let _ = term.draw(|f| self.draw(f))?;
+        self.serve_metrics().await;
+        self.mqtt = Publisher::start(&CONFIG.mqtt);
+        if let Some(every) = CONFIG.background_scan() {
+            self.timers.after(every, AppRequest::BackgroundScan);
+        }
+        self.timers.after(SAVE_STATE_EVERY, AppRequest::SaveState);
+        self.handle_request(AppRequest::RefreshViews).await;
+        if !CONFIG.adapters.is_empty() {
+            for e in self.bt.apply_adapter_sections(None).await {
+                self.status.show(&mut self.vc, e);
+            }
+            self.handle_request(AppRequest::RefreshViews).await;
+        }
+        self.bt.watch_connections().await;
+        self.bt.auto_connect_all();
+        if let Some(device) = &CONFIG.proximity.device
+            && let Ok(addr) = Address::from_str(device)
+        {
+            self.bt.watch_proximity(&DeviceId(addr)).await;
+        }
+
+        let mut input = EventStream::new();
         while self.vc.is_running() {
-            let _ = term.draw(|f| self.vc.draw(f, f.area()))?;
+            let _ = term.draw(|f| self.draw(f))?;
 
-            let req = self.handle_view_event()
-                + self.poll_session().await
-                + self.poll_adapter().await
-                + self.poll_device().await
-                + self.poll_pending_tasks().await;
+            // input and bluez events wake the loop right away, tasks and timers on the next tick
+            let (mut ev, mut session_ev, mut adapter_ev, mut device_ev) = (None, None, None, None);
+            let mut lagged = 0;
+            tokio::select! {
+                e = input.next() => ev = e.and_then(Result::ok),
+                Some(e) = recv(&mut self.session_event_rx) => session_ev = Some(e),
+                Some(e) = recv_unbounded(&mut self.device_list_rx) => adapter_ev = Some(e),
+                Some((e, n)) = recv_broadcast(&mut self.adapter_event_rx) => {
+                    adapter_ev = Some(e);
+                    lagged = n;
+                }
+                Some((e, n)) = recv_broadcast(&mut self.device_event_rx) => {
+                    device_ev = Some(e);
+                    lagged = n;
+                }
+                _ = tokio::time::sleep(until_next_tick()) => {}
+            };
+            self.dropped_events += lagged;
+            let req = self.handle_view_event(ev.as_ref())
+                + self.poll_session(session_ev).await
+                + self.poll_adapter(adapter_ev).await
+                + self.poll_device(device_ev).await
+                + self.poll_pending_tasks().await
+                + self
+                    .timers
+                    .take_due()
+                    .into_iter()
+                    .fold(AppRequest::None, Add::add);
 
             self.vc.update_status_line();
             self.handle_request(req).await;
         }
-        try_release_term(term)
+        let scanning = self
+            .scan_adapter
+            .filter(|_| self.adapter_event_task.is_some());
+        self.bt.remember_scan(scanning).await;
+        self.stop_scan().await;
+        self.bt.save_state();
+        try_release_term(term, inline)?;
+        if summary {
+            println!("{}", self.bt.session_summary());
+        }
+        Ok(())
     }
 
-    fn handle_view_event(&mut self) -> AppRequest {
-        if let Ok(true) = event::poll(Duration::from_millis(200)) {
-            let ev = &event::read().unwrap();
+    async fn serve_metrics(&mut self) {
+        let Some(addr) = CONFIG.metrics_listen else {
+            return;
+        };
+        let page = Arc::new(Mutex::new(String::new()));
+        match metrics::serve(addr, page.clone()).await {
+            Ok(()) => {
+                self.metrics = Some(page);
+                // scrapes before the first refresh get the cached adapters
+                self.render_metrics();
+            }
+            Err(e) => self.status.show(
+                &mut self.vc,
+                format!("Could not serve metrics on {addr}: {e}"),
+            ),
+        }
+    }
+
+    fn render_metrics(&self) {
+        if let Some(page) = &self.metrics {
+            *page.lock().unwrap() = metrics::render(
+                &self.bt.get_adapters(&Adapter::BY_ADDRESS),
+                self.bt.connect_failures,
+                self.dropped_events,
+            );
+        }
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>) {
+        let area = f.area();
+        self.vc.draw(f, area);
+        let status_line = Rect {
+            y: area.bottom().saturating_sub(1),
+            height: 1.min(area.height),
+            ..area
+        };
+        let mut indicators = vec![];
+        let spinner = Icons::spinner(ticks());
+        if let Some(started) = self.bt.running_actions().map(|(_, r)| r.started).min() {
+            let secs = (Local::now() - started).num_seconds();
+            indicators.push(format!("{spinner} {secs}s"));
+        } else if self.bt.has_pending_tasks() {
+            indicators.push(spinner.to_string());
+        }
+        if let Some(device) = &CONFIG.proximity.device
+            && self.bt.proximity != ProximityState::Unknown
+        {
+            indicators.push(format!("{device} {}", self.bt.proximity));
+        }
+        if !indicators.is_empty() {
+            let indicator =
+                Paragraph::new(format!("{} ", indicators.join(" "))).alignment(Alignment::Right);
+            f.render_widget(indicator, status_line);
+        }
+        if let Some(style) = StyledWidget::status_style() {
+            f.buffer_mut().set_style(status_line, style);
+        }
+    }
+
+    fn handle_view_event(&mut self, ev: Option<&Event>) -> AppRequest {
+        if let Some(ev) = ev {
+            // text input and the cheatsheet get every key, including the global shortcuts
+            if matches!(
+                self.vc.curr().kind(),
+                ViewKind::InputView | ViewKind::CheatsheetView
+            ) {
+                return self.vc.curr_mut().update(ev);
+            }
             return match ev {
                 Event::Key(ev) => match self.keymap.get_command(ev) {
                     None => AppRequest::None,
                     Some(cmd) => match cmd {
                         AppCommand::CloseView => AppRequest::CloseView,
+                        AppCommand::Back => AppRequest::GoBack,
+                        AppCommand::Forward => AppRequest::GoForward,
                         AppCommand::OpenHelpView => AppRequest::OpenHelpView,
                         AppCommand::RefreshView => AppRequest::RefreshViews,
+                        AppCommand::Cheatsheet => AppRequest::OpenCheatsheet,
+                        AppCommand::ActionHistory => AppRequest::OpenHistoryView,
+                        AppCommand::CancelPairing => AppRequest::CancelPairing,
+                        AppCommand::ConnectInRange => match self.in_range.take() {
+                            Some((adapter_id, device_id)) => AppRequest::ExecDeviceAction(
+                                adapter_id,
+                                device_id,
+                                DeviceAction::SetConnected(true),
+                            ),
+                            None => AppRequest::None,
+                        },
+                        AppCommand::QuickList => AppRequest::OpenQuickView,
+                        AppCommand::Search => AppRequest::OpenSearchView,
+                        AppCommand::KeepPowered => AppRequest::KeepPowered,
+                        // the gatt view sends its snippets with the number keys
+                        AppCommand::QuickDevice(_)
+                            if self.vc.curr().kind() == ViewKind::GattView =>
+                        {
+                            AppRequest::None
+                        }
+                        AppCommand::QuickDevice(device) => {
+                            AppRequest::ToggleQuickDevice(device.clone())
+                        }
+                        AppCommand::PowerAll => {
+                            let any_on = self
+                                .bt
+                                .get_adapters(&Adapter::BY_ADDRESS)
+                                .iter()
+                                .any(|a| a.is_on);
+                            AppRequest::PowerAllAdapters(!any_on)
+                        }
+                        AppCommand::OpenNewAdapter => match self
+                            .added_adapter
+                            .take()
+                            .and_then(|name| self.bt.get_adapter_by_name(&name).cloned())
+                        {
+                            Some(adapter) => AppRequest::OpenDevicesView(adapter),
+                            None => AppRequest::None,
+                        },
                     },
                 },
                 _ => AppRequest::None,
@@ -157,121 +711,701 @@ impl App {
     }
 
     fn monitor_session(&mut self) {
-        let session = self.bt.session.clone();
-        let (sx, rx) = std::sync::mpsc::channel();
+        let Ok(session) = self.bt.session().cloned() else {
+            return;
+        };
+        let (sx, rx) = mpsc::channel(SESSION_QUEUE);
         self.session_event_rx = Some(rx);
-        let _ = tokio::spawn(async move {
-            let mut events = Box::pin(session.events().await.unwrap());
+        self.session_event_task = Some(tokio::spawn(async move {
+            let Ok(events) = session.events().await else {
+                return;
+            };
+            let mut events = Box::pin(events);
             while let Some(ev) = events.next().await {
-                sx.send(ev).unwrap();
+                if sx.send(ev).await.is_err() {
+                    return;
+                }
             }
-        });
+        }));
     }
-    async fn poll_session(&mut self) -> AppRequest {
-        self.session_event_rx
-            .as_ref()
-            .map_or(AppRequest::None, |rx| match rx.try_recv() {
-                Ok(ev) => {
-                    match ev {
-                        SessionEvent::AdapterAdded(_) => {}
-                        SessionEvent::AdapterRemoved(_) => {}
-                    };
-                    self.vc.show_status(format!("{:?}", ev));
-                    AppRequest::RefreshViews
+    /// Restarts the event streams that ended without being stopped, which would leave the
+    /// views frozen, keeping a warning in the status line until they run again
+    async fn check_monitors(&mut self) -> AppRequest {
+        let ended = |task: &Option<JoinHandle<()>>| task.as_ref().is_some_and(|t| t.is_finished());
+        let streams = [
+            (ended(&self.session_event_task), "adapter list"),
+            (ended(&self.adapter_event_task), "scan"),
+        ]
+        .into_iter()
+        .filter(|(ended, _)| *ended)
+        .map(|(_, name)| name)
+        .join(" and ");
+        let msg = format!("! Lost the {streams} events, restarting");
+        if self.monitor_badge.as_ref().map(|(_, m)| m) != Some(&msg) {
+            if let Some((id, _)) = self.monitor_badge.take() {
+                self.vc.status().lock().unwrap().remove(id);
+            }
+            if !streams.is_empty() {
+                self.monitor_badge = Some((self.vc.show_status_always(msg.clone()), msg));
+            }
+        }
+        if streams.is_empty() {
+            self.monitor_retry = None;
+            return AppRequest::None;
+        }
+        if self.monitor_retry.is_some_and(|at| at > Instant::now()) {
+            return AppRequest::None;
+        }
+        self.monitor_retry = Some(Instant::now() + MONITOR_RETRY);
+        if ended(&self.session_event_task) {
+            self.monitor_session();
+        }
+        // a failed restart leaves the ended task in place, to be retried
+        if ended(&self.adapter_event_task)
+            && let Some(adapter_id) = self.scan_adapter
+            && let Some(adapter) = self.bt.get_actual_adapter(&adapter_id).await
+        {
+            let _ = self.monitor_adapter(adapter).await;
+        }
+        AppRequest::RefreshViews
+    }
+    async fn poll_session(&mut self, ev: Option<SessionEvent>) -> AppRequest {
+        let Some(ev) = ev else {
+            return AppRequest::None;
+        };
+        match ev {
+            SessionEvent::AdapterAdded(name) => {
+                self.bt.update_adapters().await;
+                for e in self.bt.apply_adapter_sections(Some(&name)).await {
+                    self.status.show(&mut self.vc, e);
                 }
-                _ => AppRequest::None,
-            })
+                let msg = format!("Adapter {name} added, press O to open its devices");
+                self.status.show(&mut self.vc, msg);
+                self.added_adapter = Some(name);
+            }
+            SessionEvent::AdapterRemoved(name) => {
+                if self.added_adapter.as_ref() == Some(&name) {
+                    self.added_adapter = None;
+                }
+                if let Some(adapter_id) = self.bt.get_adapter_by_name(&name).map(|a| a.id) {
+                    self.close_adapter_views(&adapter_id).await;
+                }
+                self.status
+                    .show(&mut self.vc, format!("Adapter {name} removed"));
+            }
+        };
+        AppRequest::RefreshViews
+    }
+    /// Closes every view showing the adapter and stops the monitors running on it
+    async fn close_adapter_views(&mut self, adapter_id: &AdapterId) {
+        if self.scan_adapter == Some(*adapter_id) {
+            self.stop_scan().await;
+            self.scan_adapter = None;
+        }
+        if self.in_range.is_some_and(|(id, _)| id == *adapter_id) {
+            self.in_range = None;
+        }
+        if self.pair_elsewhere.is_some_and(|(id, _)| id == *adapter_id) {
+            self.pair_elsewhere = None;
+        }
+        let bt = &self.bt;
+        self.forward_history
+            .retain(|req| req.bound_adapter(bt) != Some(*adapter_id));
+        let Some(first) = self
+            .history
+            .iter()
+            .position(|req| req.bound_adapter(bt) == Some(*adapter_id))
+        else {
+            return;
+        };
+        while self.history.len() > first {
+            if let Some(AppRequest::MonitorDevice(..)) = self.pop_view()
+                && let Some(sx) = self.stop_device_event_sx.take()
+            {
+                let _ = sx.send(());
+            }
+        }
+        if self.history.is_empty() {
+            Box::pin(self.handle_request(AppRequest::OpenAdaptersView)).await;
+        }
     }
 
-    fn monitor_adapter(&mut self, adapter: bluer::Adapter) {
-        let (sx, rx) = std::sync::mpsc::channel();
+    async fn monitor_adapter(&mut self, adapter: bluer::Adapter) -> bluer::Result<()> {
+        let mut events = scan_events(&adapter, self.scan_type).await?;
+        let (sx, rx) = broadcast::channel(EVENT_QUEUE);
         self.adapter_event_rx = Some(rx);
+        let (list_sx, list_rx) = mpsc::unbounded_channel();
+        self.device_list_rx = Some(list_rx);
         let (stop_sx, mut stop_rx) = tokio::sync::oneshot::channel();
         self.stop_adapter_event_sx = Some(stop_sx);
 
-        let _ = tokio::spawn(async move {
-            let mut events = Box::pin(adapter.discover_devices().await.unwrap());
-            while let Some(ev) = events.next().await {
-                match stop_rx.try_recv() {
-                    Ok(_) | Err(TryRecvError::Closed) => return,
-                    Err(TryRecvError::Empty) => sx.send(ev).unwrap(),
+        // the discovery session lives as long as the stream, dropping it lets
+        // bluez restore the adapter to its previous discovery state
+        self.adapter_event_task = Some(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = &mut stop_rx => return,
+                    ev = events.next() => {
+                        // only property changes may be dropped when the interface lags
+                        let sent = match ev {
+                            Some(ev @ AdapterEvent::PropertyChanged(_)) => sx.send(ev).is_ok(),
+                            Some(ev) => list_sx.send(ev).is_ok(),
+                            None => false,
+                        };
+                        if !sent {
+                            return;
+                        }
+                    }
                 }
             }
-        });
+        }));
+        Ok(())
     }
-    async fn poll_adapter(&mut self) -> AppRequest {
-        self.adapter_event_rx
-            .as_ref()
-            .map_or(AppRequest::None, |rx| match rx.try_recv() {
-                Ok(ev) => {
-                    match ev {
-                        AdapterEvent::DeviceAdded(device_id) => {
-                            self.bt.mark_new_device(&DeviceId(device_id));
-                        }
-                        AdapterEvent::DeviceRemoved(_) => {}
-                        AdapterEvent::PropertyChanged(_) => {}
+    async fn stop_scan(&mut self) {
+        if let Some(sx) = self.stop_adapter_event_sx.take() {
+            let _ = sx.send(());
+        }
+        if let Some(task) = self.adapter_event_task.take() {
+            let _ = task.await;
+        }
+    }
+    /// Handles the event that woke the loop along with the ones queued behind it
+    async fn poll_adapter(&mut self, first: Option<AdapterEvent>) -> AppRequest {
+        let mut events = Vec::from_iter(first);
+        if let Some(rx) = &mut self.device_list_rx {
+            while let Ok(ev) = rx.try_recv() {
+                events.push(ev);
+            }
+        }
+        events.extend(
+            self.adapter_event_rx
+                .as_mut()
+                .map(|rx| drain(rx, &mut self.dropped_events))
+                .unwrap_or_default(),
+        );
+        let mut refresh = false;
+        for ev in events {
+            refresh |= self.on_adapter_event(ev).await;
+        }
+        if refresh {
+            return AppRequest::RefreshViews;
+        }
+        // only the rows of the devices that advertised again change
+        if self.bt.flush_advertisements().await {
+            self.vc.refresh(&self.bt);
+        }
+        AppRequest::None
+    }
+    /// Handles a discovery event, returning whether the devices need a full refresh
+    async fn on_adapter_event(&mut self, ev: AdapterEvent) -> bool {
+        let refresh = match &ev {
+            AdapterEvent::DeviceAdded(addr) => {
+                let device_id = DeviceId(*addr);
+                // with duplicate_data every advertisement is reported as an addition
+                if self.bt.found.contains(&device_id)
+                    && let Some(adapter_id) = self.scan_adapter
+                    && self.bt.note_advertisement(&adapter_id, &device_id)
+                {
+                    return false;
+                }
+                if !self.bt.found.contains(&device_id) {
+                    self.alert("new_device", &device_id.to_string());
+                }
+                self.bt.mark_new_device(&device_id);
+                if let Some(adapter_id) = self.scan_adapter
+                    && CONFIG
+                        .auto_block
+                        .iter()
+                        .any(|p| address_matches(p, &addr.to_string()))
+                {
+                    let msg = match self.bt.auto_block(&adapter_id, &device_id).await {
+                        TaskStatus::Error(e) => format!("Failed to auto block {device_id}: {e}"),
+                        _ => format!("Blocked {device_id}, it matches auto_block"),
                     };
-                    self.vc.show_status(format!("{:?}", ev));
-                    AppRequest::RefreshViews
+                    self.status.show(&mut self.vc, msg);
+                    return true;
                 }
-                _ => AppRequest::None,
-            })
+                true
+            }
+            AdapterEvent::DeviceRemoved(_) => true,
+            AdapterEvent::PropertyChanged(prop) => {
+                // applied in place, the views only need redrawing
+                let applied = self
+                    .scan_adapter
+                    .is_some_and(|adapter_id| self.bt.apply_adapter_property(&adapter_id, prop));
+                if applied {
+                    self.vc.refresh(&self.bt);
+                }
+                !applied
+            }
+        };
+        self.status
+            .show_low(&mut self.vc, describe_adapter_event(&ev));
+        refresh
     }
 
-    fn monitor_device(&mut self, device: bluer::Device) {
-        let (sx, rx) = std::sync::mpsc::channel();
-        self.device_event_rx = Some(rx);
-        let (stop_sx, mut stop_rx) = tokio::sync::oneshot::channel();
-        self.stop_device_event_sx = Some(stop_sx);
-
-        let _ = tokio::spawn(async move {
-            let mut events = Box::pin(device.events().await.unwrap());
-            while let Some(ev) = events.next().await {
-                match stop_rx.try_recv() {
-                    Ok(_) | Err(TryRecvError::Closed) => return,
-                    Err(TryRecvError::Empty) => sx.send(ev).unwrap(),
-                }
+    async fn monitor_device(&mut self, device: bluer::Device) {
+        let device_id = DeviceId(device.address());
+        match monitor_device(device, EVENT_QUEUE).await {
+            Ok((rx, stop_sx)) => {
+                self.device_event_rx = Some(rx);
+                self.stop_device_event_sx = Some(stop_sx);
             }
-        });
+            Err(e) => {
+                let msg = format!("Can't monitor {device_id}: {}", friendly_message(&e));
+                self.status.show(&mut self.vc, msg);
+            }
+        }
     }
-    async fn poll_device(&mut self) -> AppRequest {
-        self.device_event_rx
-            .as_ref()
-            .map_or(AppRequest::None, |rx| match rx.try_recv() {
-                Ok(DeviceEvent::PropertyChanged(prop)) => {
-                    self.vc.show_status(format!("{:?}", prop));
-                    AppRequest::RefreshViews
-                }
-                _ => AppRequest::None,
-            })
+    async fn poll_device(&mut self, first: Option<DeviceEvent>) -> AppRequest {
+        let mut events = Vec::from_iter(first);
+        events.extend(
+            self.device_event_rx
+                .as_mut()
+                .map(|rx| drain(rx, &mut self.dropped_events))
+                .unwrap_or_default(),
+        );
+        let mut req = AppRequest::None;
+        for DeviceEvent::PropertyChanged(prop) in events {
+            self.bt.update_monitored(&prop);
+            req = AppRequest::RefreshViews;
+        }
+        req
     }
 
+    /// Shows the code bluez asked for in a popup, closed once the pairing ends
+    fn show_pin_code(&mut self) -> AppRequest {
+        let open = self.vc.curr().kind() == ViewKind::PinView;
+        if self.bt.poll_pin_code() {
+            if !open {
+                let view = PinView::new(&self.bt);
+                self.push_view(Box::new(view), AppRequest::None);
+            }
+            return AppRequest::RefreshViews;
+        }
+        if open && self.bt.pin_code.is_none() {
+            let _ = self.pop_view();
+        }
+        AppRequest::None
+    }
+    /// Asks the user about the pairing requests bluez forwards. A prompt stays pending under
+    /// other views until it is answered, closed or given up by bluez
+    fn show_pairing_prompt(&mut self) -> AppRequest {
+        let open = self.vc.curr().kind() == ViewKind::PairingPromptView;
+        let changed = self.bt.poll_pairing_prompt();
+        if open && (changed || self.bt.pairing_prompt.is_none()) {
+            let _ = self.pop_view();
+        }
+        if !changed {
+            return AppRequest::None;
+        }
+        // pairings started from bluerat are already in front of the user
+        if let Some(prompt) = &self.bt.pairing_prompt
+            && !self.bt.started_pairing(&prompt.device)
+        {
+            let device = prompt.device.to_string();
+            self.alert("pairing", &device);
+        }
+        if let Some(prompt) = &self.bt.pairing_prompt {
+            let alias = self
+                .bt
+                .get_device_adapter(&prompt.device)
+                .and_then(|a| a.get_device(&prompt.device))
+                .map_or(prompt.device.to_string(), |d| d.alias.clone());
+            let msg = match prompt.passkey {
+                Some(passkey) => format!("{alias} asks to pair, accept if it shows {passkey:06}"),
+                None => format!("{alias} asks to pair"),
+            };
+            let choices = vec![PairingChoice::Accept, PairingChoice::Reject];
+            let view = ConfirmView::new("Pairing request", msg, choices, ());
+            self.push_view(Box::new(view), AppRequest::None);
+        }
+        AppRequest::RefreshViews
+    }
+    /// Keeps a status entry with the seconds left for the running pairing
+    fn show_pair_countdown(&mut self) {
+        let pairing = self
+            .bt
+            .pairing()
+            .map(|(alias, left)| (alias, left.as_secs()));
+        if pairing.map(|(_, secs)| secs) == self.pair_status.as_ref().map(|(_, secs)| *secs) {
+            return;
+        }
+        if let Some((id, _)) = self.pair_status.take() {
+            self.vc.status().lock().unwrap().remove(id);
+        }
+        if let Some((alias, secs)) = pairing {
+            let msg = format!("Pairing with {alias} ({secs}s, X to cancel)");
+            self.pair_status = Some((self.vc.show_status_always(msg), secs));
+        }
+    }
+    /// Keeps one status entry per running action until it finishes, the spinner and the time
+    /// taken being drawn with the indicators
+    fn show_task_progress(&mut self) {
+        let running: HashMap<usize, String> = self
+            .bt
+            .running_actions()
+            .map(|(i, record)| (i, format!("{} {}", record.action(), record.target)))
+            .collect();
+        let status = self.vc.status().clone();
+        self.task_status.retain(|i, id| {
+            let current = running.contains_key(i);
+            if !current {
+                status.lock().unwrap().remove(*id);
+            }
+            current
+        });
+        for (i, label) in running {
+            if !self.task_status.contains_key(&i) {
+                let id = self.vc.show_status_always(label);
+                let _ = self.task_status.insert(i, id);
+            }
+        }
+    }
     async fn poll_pending_tasks(&mut self) -> AppRequest {
         let r1 = match self.bt.poll_exec_adapter_action().await {
-            TaskStatus::Done(_) => AppRequest::RefreshViews,
+            TaskStatus::Done((name, action)) => {
+                if let Some(msg) = action.done(&name) {
+                    self.status.show(&mut self.vc, msg);
+                }
+                AppRequest::RefreshViews
+            }
             TaskStatus::Error(e) => {
-                self.vc.show_status(e);
+                self.status.show(&mut self.vc, e);
                 AppRequest::None
             }
             _ => AppRequest::None,
         };
         let r2 = match self.bt.poll_exec_device_action().await {
-            TaskStatus::Done(_) => AppRequest::RefreshViews,
-            TaskStatus::Error(e) => {
-                self.vc.show_status(e);
+            TaskStatus::Done((alias, action)) => {
+                let mut msg = action.done(&alias);
+                if CONFIG.stop_scan_on_connect
+                    && self.adapter_event_task.is_some()
+                    && let DeviceAction::SetConnected(true) = action
+                {
+                    self.stop_scan().await;
+                    msg = msg.map(|msg| msg + tr(", stopped scanning"));
+                }
+                if let Some(msg) = msg {
+                    self.status.show(&mut self.vc, msg);
+                }
+                // trust may have changed
+                self.bt.watch_connections().await;
+                AppRequest::RefreshViews
+            }
+            TaskStatus::Error(e) => match self.bt.last_device_action() {
+                Some((
+                    adapter_id,
+                    device_id,
+                    action @ (DeviceAction::SetConnected(true) | DeviceAction::SetPaired(true)),
+                )) if !self.bt.pairing_cancelled() => {
+                    AppRequest::PromptRetry(adapter_id, device_id, action, e)
+                }
+                _ => {
+                    self.status.show(&mut self.vc, e);
+                    AppRequest::None
+                }
+            },
+            _ => AppRequest::None,
+        };
+        self.show_pair_countdown();
+        self.show_task_progress();
+        let r3 = match self.bt.poll_batch() {
+            true => AppRequest::RefreshViews,
+            false => AppRequest::None,
+        };
+        let r10 = self.show_pin_code() + self.show_pairing_prompt();
+        let r11 = self.check_monitors().await;
+        // the polls only run while a GATT view is open
+        if self.bt.is_polling_gatt()
+            && !self
+                .history
+                .iter()
+                .any(|req| matches!(req, AppRequest::OpenGattView(..)))
+        {
+            self.bt.stop_gatt();
+        }
+        let (changed, msg) = self.bt.poll_gatt();
+        if let Some(msg) = msg {
+            self.status.show(&mut self.vc, msg);
+        }
+        let r12 = match changed {
+            true => AppRequest::RefreshViews,
+            false => AppRequest::None,
+        };
+        let r4 = match self.quit_when_idle && !self.bt.has_pending_tasks() {
+            true => AppRequest::Quit,
+            false => AppRequest::None,
+        };
+        let mut r5 = AppRequest::None;
+        for (adapter_id, device_id) in self.bt.poll_disconnects() {
+            let alias = self
+                .bt
+                .get_adapter(&adapter_id)
+                .and_then(|a| a.get_device(&device_id))
+                .map_or(device_id.to_string(), |d| d.alias.clone());
+            self.alert("connection_lost", &alias);
+            r5 = r5
+                + match self.bt.auto_connects(&device_id) {
+                    true => {
+                        let delay = Duration::from_secs(CONFIG.reconnect_delay.into());
+                        self.bt.schedule_reconnect(adapter_id, device_id, delay);
+                        self.status.show(
+                            &mut self.vc,
+                            format!(
+                                "Lost connection to {alias}, reconnecting in {}s",
+                                CONFIG.reconnect_delay
+                            ),
+                        );
+                        AppRequest::RefreshViews
+                    }
+                    false => AppRequest::PromptRetry(
+                        adapter_id,
+                        device_id,
+                        DeviceAction::SetConnected(true),
+                        format!("Lost connection to {alias}"),
+                    ),
+                };
+        }
+        let r6 = match self.bt.due_reconnect() {
+            Some((adapter_id, device_id)) => AppRequest::ExecDeviceAction(
+                adapter_id,
+                device_id,
+                DeviceAction::SetConnected(true),
+            ),
+            None => AppRequest::None,
+        };
+        let proximity = &CONFIG.proximity;
+        match self.bt.poll_proximity(
+            proximity.leave_rssi,
+            proximity.return_rssi,
+            Duration::from_secs(proximity.leave_after.into()),
+        ) {
+            Some(ProximityState::Away) => self.run_hook("left range", &proximity.on_leave),
+            Some(ProximityState::Near) => self.run_hook("returned", &proximity.on_return),
+            _ => {}
+        }
+        let r8 = match self.service_start.as_mut().map(|rx| rx.try_recv()) {
+            Some(Ok(res)) => {
+                self.service_start = None;
+                match res {
+                    Ok(_) => {
+                        self.status.show(&mut self.vc, format!("Started {SERVICE}"));
+                        self.monitor_session();
+                        AppRequest::RefreshViews
+                    }
+                    Err(e) => {
+                        let msg = format!("Failed to start {SERVICE}: {e}");
+                        self.status.show(&mut self.vc, msg);
+                        AppRequest::None
+                    }
+                }
+            }
+            Some(Err(TryRecvError::Closed)) => {
+                self.service_start = None;
                 AppRequest::None
             }
             _ => AppRequest::None,
         };
-        r1 + r2
+        // reloading also picks up bluez turning discoverable off at the end
+        let r7 = match self.bt.discoverable_ticked() {
+            true => AppRequest::RefreshViews,
+            false => AppRequest::None,
+        };
+        let r9 = self.power_off_idle();
+        if let Some(e) = self.mqtt.as_mut().and_then(Publisher::poll_error) {
+            self.status.show(&mut self.vc, e);
+        }
+        r1 + r2 + r3 + r4 + r5 + r6 + r7 + r8 + r9 + r10 + r11 + r12
+    }
+    fn power_off_idle(&mut self) -> AppRequest {
+        let idle = self.bt.idle_power_off();
+        self.idle_warned
+            .retain(|id| idle.iter().any(|(a, _)| a.id == *id));
+        // the adapter may be busy with the operation
+        if self.bt.has_pending_tasks() {
+            return AppRequest::None;
+        }
+        let mut req = AppRequest::None;
+        for (adapter, left) in idle {
+            if left.is_zero() {
+                let _ = self.idle_warned.remove(&adapter.id);
+                // the adapter shows as idle until the next refresh
+                self.bt.keep_powered(&adapter.id);
+                req =
+                    req + AppRequest::ExecAdapterAction(adapter, AdapterAction::SetPowered(false));
+            } else if left <= IDLE_WARNING && self.idle_warned.insert(adapter.id) {
+                let msg = format!(
+                    "{} is idle and powers off in {}s, press K to keep it on",
+                    adapter.name,
+                    left.as_secs()
+                );
+                self.status.show(&mut self.vc, msg);
+            }
+        }
+        req
+    }
+
+    // scanning doesn't go through the action tasks, it settles right away
+    fn record_scan(&mut self, adapter_id: &AdapterId, action: AdapterAction) {
+        let i = self
+            .bt
+            .record_action(self.bt.adapter_name(adapter_id), action.label());
+        let _ = self.bt.finish_action(i, &TaskStatus::Done(()));
+    }
+    /// Rings the bell and runs the alert command if the event is enabled
+    fn alert(&mut self, event: &str, detail: &str) {
+        if !CONFIG.alerts.wants(event) {
+            return;
+        }
+        if CONFIG.alerts.bell {
+            let mut out = io::stdout();
+            let _ = out.write_all(b"\x07").and_then(|_| out.flush());
+        }
+        let Some(cmd) = &CONFIG.alerts.command else {
+            return;
+        };
+        let env = [("BLUERAT_EVENT", event), ("BLUERAT_DETAIL", detail)];
+        let _ = self.spawn_shell("Alert command", cmd, &env);
+    }
+    fn run_hook(&mut self, event: &str, cmd: &Option<String>) {
+        let Some(cmd) = cmd else {
+            self.status
+                .show(&mut self.vc, format!("Proximity device {event}"));
+            return;
+        };
+        if self.spawn_shell("Proximity hook", cmd, &[]) {
+            self.status.show(
+                &mut self.vc,
+                format!("Proximity device {event}, ran {cmd:?}"),
+            );
+        }
+    }
+    /// Runs the command through `sh` in the background, reporting on the status line when it
+    /// can't be started
+    fn spawn_shell(&mut self, what: &str, cmd: &str, env: &[(&str, &str)]) -> bool {
+        let child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(cmd)
+            .envs(env.iter().copied())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match child {
+            Ok(mut child) => {
+                let _ = tokio::spawn(async move { child.wait().await });
+                true
+            }
+            Err(e) => {
+                let msg = format!("{what} {cmd:?} failed: {e}");
+                self.status.show(&mut self.vc, msg);
+                false
+            }
+        }
+    }
+
+    fn push_view(
+        &mut self,
+        view: Box<dyn View<Model = BtManager, Signal = AppRequest, Kind = ViewKind>>,
+        req: AppRequest,
+    ) {
+        if req.is_page() {
+            self.forward_history.clear();
+        }
+        self.vc.push(view);
+        self.history.push(req);
+    }
+    fn pop_view(&mut self) -> Option<AppRequest> {
+        self.vc.pop();
+        self.history.pop()
     }
 
     async fn handle_request(&mut self, req: AppRequest) {
         match req {
             AppRequest::None => {}
-            AppRequest::CloseView => self.vc.pop(),
+            AppRequest::CloseView => {
+                // closing the prompt rejects the request, choosing answers it first
+                if self.vc.curr().kind() == ViewKind::PairingPromptView {
+                    self.bt.answer_pairing(false);
+                }
+                if self.history.len() <= 1 && self.bt.has_pending_tasks() {
+                    let view = ConfirmView::new(
+                        "Quit",
+                        "An operation is still running, quit anyway?".to_string(),
+                        vec![QuitChoice::Wait, QuitChoice::Quit],
+                        (),
+                    );
+                    self.push_view(Box::new(view), AppRequest::CloseView);
+                } else {
+                    let _ = self.pop_view();
+                }
+            }
+            AppRequest::Quit => while self.pop_view().is_some() {},
+            AppRequest::QuitWhenIdle => {
+                self.quit_when_idle = true;
+                self.status.show(
+                    &mut self.vc,
+                    "Quitting once the running operation finishes".to_string(),
+                );
+            }
+            AppRequest::GoBack => {
+                if self.history.len() > 1
+                    && let Some(req) = self.pop_view()
+                    && req.is_page()
+                {
+                    self.forward_history.push(req);
+                }
+            }
+            AppRequest::GoForward => {
+                if let Some(req) = self.forward_history.pop() {
+                    let forward_history = std::mem::take(&mut self.forward_history);
+                    Box::pin(self.handle_request(req)).await;
+                    self.forward_history = forward_history;
+                    self.vc.curr_mut().refresh(&self.bt);
+                }
+            }
             AppRequest::RefreshViews => {
                 self.bt.update_adapters().await;
+                if self.vc.curr().kind() == ViewKind::DeviceInfoView {
+                    self.bt.reload_ad_data().await;
+                }
+                self.render_metrics();
+                if let Some(mqtt) = &mut self.mqtt {
+                    let adapters = self.bt.get_adapters(&Adapter::BY_ADDRESS);
+                    mqtt.publish_changes(&CONFIG.mqtt, &adapters);
+                }
+                if self.bt.bluez_running {
+                    self.service_prompted = false;
+                } else if !self.service_prompted && !self.history.is_empty() {
+                    self.service_prompted = true;
+                    Box::pin(self.handle_request(AppRequest::OpenServiceView)).await;
+                }
+                for (adapter_id, device_id) in self.bt.take_arrivals() {
+                    if let Some(device) = self
+                        .bt
+                        .get_adapter(&adapter_id)
+                        .and_then(|a| a.get_device(&device_id))
+                    {
+                        self.status.show(
+                            &mut self.vc,
+                            format!("{} is in range, press C to connect", device.alias),
+                        );
+                        self.in_range = Some((adapter_id, device_id));
+                        self.timers
+                            .after(IN_RANGE_OFFER, AppRequest::ExpireInRange(device_id));
+                    }
+                }
+                if let Some((adapter_id, device_id)) = self.pair_elsewhere
+                    && self
+                        .bt
+                        .get_adapter(&adapter_id)
+                        .is_some_and(|a| a.get_device(&device_id).is_some())
+                {
+                    self.pair_elsewhere = None;
+                    let req = AppRequest::PairOnAdapter(adapter_id, device_id);
+                    Box::pin(self.handle_request(req)).await;
+                }
                 self.vc.refresh(&self.bt);
             }
             AppRequest::Chain(reqs) => {
@@ -280,36 +1414,65 @@ impl App {
                 }
             }
 
-            AppRequest::OpenHelpView => self.vc.push(Box::new(HelpView::new())),
-            AppRequest::OpenPopupView(msg) => self.vc.push(Box::new(PopupView::new(msg))),
+            AppRequest::OpenHelpView => {
+                self.push_view(Box::new(HelpView::new()), AppRequest::OpenHelpView)
+            }
+            AppRequest::OpenPopupView(popup) => self.push_view(
+                Box::new(PopupView::new(&popup)),
+                AppRequest::OpenPopupView(popup),
+            ),
 
             AppRequest::OpenAdaptersView => {
-                self.vc.push(Box::new(AdapterView::new(
-                    &self.bt,
-                    TableState::new().with_selected(0),
-                )));
+                self.push_view(
+                    Box::new(AdapterView::new(
+                        &self.bt,
+                        TableState::new().with_selected(0),
+                    )),
+                    AppRequest::OpenAdaptersView,
+                );
             }
             AppRequest::OpenDevicesView(adapter) => {
-                self.vc.push(Box::new(DeviceView::new(
-                    adapter.clone(),
-                    TableState::new().with_selected(0),
-                )));
+                self.push_view(
+                    Box::new(DeviceView::new(
+                        adapter.clone(),
+                        TableState::new().with_selected(0),
+                    )),
+                    AppRequest::OpenDevicesView(adapter),
+                );
+            }
+
+            AppRequest::FocusDevice(device_id) => {
+                if let Some(adapter) = self.bt.get_device_adapter(&device_id).cloned() {
+                    let view = DeviceView::new(adapter, TableState::new().with_selected(0))
+                        .with_focus(&device_id);
+                    self.push_view(Box::new(view), AppRequest::FocusDevice(device_id));
+                }
             }
 
             AppRequest::OpenAdapterActionsViewAt(adapter, pos) => {
-                let actions = vec![
+                let mut actions = vec![
                     AdapterAction::SetPowered(!adapter.is_on),
                     AdapterAction::SetDiscoverable(!adapter.is_discoverable),
                     AdapterAction::SetScanning(!adapter.is_scanning),
                     AdapterAction::SetPairable(!adapter.is_pairable),
-                    AdapterAction::Info,
                 ];
-                self.vc.push(Box::new(AdapterActionsView::new(
-                    adapter,
-                    actions,
-                    TableState::new().with_selected(0),
-                    pos,
-                )));
+                if !adapter.is_scanning {
+                    actions.insert(3, AdapterAction::ScanOnly(DiscoveryTransport::Le));
+                    actions.insert(4, AdapterAction::ScanOnly(DiscoveryTransport::BrEdr));
+                }
+                if let Some(on) = self.bt.privacy(&adapter).await {
+                    actions.push(AdapterAction::SetPrivacy(!on));
+                }
+                actions.push(AdapterAction::Info);
+                self.push_view(
+                    Box::new(AdapterActionsView::new(
+                        adapter.clone(),
+                        actions,
+                        TableState::new().with_selected(0),
+                        pos,
+                    )),
+                    AppRequest::OpenAdapterActionsViewAt(adapter, pos),
+                );
             }
             AppRequest::OpenDeviceActionsViewAt(adapter, device_id, pos) => {
                 if let Some(device) = adapter.get_device(&device_id) {
@@ -318,90 +1481,709 @@ impl App {
                         DeviceAction::SetTrusted(!device.is_trusted),
                         DeviceAction::SetBlocked(!device.is_blocked),
                         DeviceAction::SetPaired(!device.is_paired),
+                        DeviceAction::SetAutoConnect(!self.bt.auto_connects(&device_id)),
                         DeviceAction::Info,
                     ];
-                    self.vc.push(Box::new(DeviceActionsView::new(
-                        adapter,
-                        device_id,
-                        actions,
-                        TableState::new().with_selected(0),
-                        pos,
-                    )));
+                    self.push_view(
+                        Box::new(DeviceActionsView::new(
+                            adapter.clone(),
+                            device_id,
+                            actions,
+                            TableState::new().with_selected(0),
+                            pos,
+                        )),
+                        AppRequest::OpenDeviceActionsViewAt(adapter, device_id, pos),
+                    );
                 }
             }
 
+            AppRequest::RepeatAdapterAction(adapter) => match self.last_adapter_action {
+                Some(action) => {
+                    let req = AppRequest::ExecAdapterAction(adapter, action);
+                    Box::pin(self.handle_request(req)).await;
+                }
+                None => self
+                    .status
+                    .show(&mut self.vc, "No adapter action to repeat".to_string()),
+            },
+            AppRequest::RepeatDeviceAction(adapter_id, device_id) => {
+                match self.bt.last_device_action() {
+                    Some((_, _, action)) => {
+                        let req = AppRequest::ExecDeviceAction(adapter_id, device_id, action);
+                        Box::pin(self.handle_request(req)).await;
+                    }
+                    None => self
+                        .status
+                        .show(&mut self.vc, "No device action to repeat".to_string()),
+                }
+            }
             AppRequest::ExecAdapterAction(adapter, action) => {
+                if !matches!(action, AdapterAction::Info) {
+                    self.last_adapter_action = Some(action);
+                }
                 match action {
                     AdapterAction::Info => {
-                        todo!()
+                        if let Some(actual) = self.bt.get_actual_adapter(&adapter.id).await {
+                            let capabilities = AdapterCapabilities::read(&actual).await;
+                            let popup = Popup::info(&adapter.name, adapter.get_info(&capabilities));
+                            let req = AppRequest::OpenPopupView(popup);
+                            Box::pin(self.handle_request(req)).await;
+                        }
                     }
                     AdapterAction::SetScanning(true) => {
-                        self.vc.show_status(action.to_string());
+                        self.record_scan(&adapter.id, action);
+                        self.status.show(&mut self.vc, action.to_string());
+                        self.stop_scan().await;
+                        self.scan_adapter = Some(adapter.id);
                         let adapter = self.bt.get_actual_adapter(&adapter.id).await.unwrap();
-                        self.monitor_adapter(adapter);
+                        if let Err(e) = self.monitor_adapter(adapter).await {
+                            self.scan_adapter = None;
+                            self.status.show(&mut self.vc, friendly_message(&e));
+                        }
                     }
                     AdapterAction::SetScanning(false) => {
-                        if let Some(rx) = std::mem::replace(&mut self.stop_adapter_event_sx, None) {
-                            rx.send(()).unwrap();
+                        self.record_scan(&adapter.id, action);
+                        self.stop_scan().await;
+                        self.status.show(&mut self.vc, action.to_string());
+                        // a transport picked for the last scan would stick to the next ones
+                        let transport = CONFIG
+                            .adapter(&adapter.name, &adapter.id.to_string())
+                            .scan_filter
+                            .map_or(DiscoveryTransport::Auto, |f| f.transport);
+                        let _ = self.bt.set_scan_transport(&adapter.id, transport).await;
+                    }
+                    AdapterAction::ScanOnly(transport) => {
+                        match self.bt.set_scan_transport(&adapter.id, transport).await {
+                            Ok(()) => {
+                                let req = AppRequest::ExecAdapterAction(
+                                    adapter,
+                                    AdapterAction::SetScanning(true),
+                                );
+                                Box::pin(self.handle_request(req)).await;
+                                self.status.show(&mut self.vc, action.to_string());
+                            }
+                            Err(e) => self.status.show(&mut self.vc, e),
                         }
-                        self.vc.show_status(action.to_string());
                     }
-                    _ => {
-                        let id = self.vc.show_status_always(action.to_string());
-                        let on_complete = {
-                            let status = self.vc.status().clone();
-                            move || status.lock().unwrap().remove(id)
+                    // bluez only answers a blocked adapter with a generic failure
+                    AdapterAction::SetPowered(true)
+                        if rfkill_state(&adapter.name) == Rfkill::HardBlocked =>
+                    {
+                        let msg = format!("{} is blocked by a hardware switch", adapter.name);
+                        self.status.show(&mut self.vc, msg);
+                    }
+                    AdapterAction::SetPowered(true)
+                        if rfkill_state(&adapter.name) == Rfkill::SoftBlocked =>
+                    {
+                        let req = AppRequest::OpenUnblockView(adapter);
+                        Box::pin(self.handle_request(req)).await;
+                    }
+                    // the kernel only changes privacy on a powered off adapter
+                    AdapterAction::SetPrivacy(on) if adapter.is_on => {
+                        let msg = match adapter.connections {
+                            0 => format!("{} will be powered off and on again", adapter.name),
+                            n => format!(
+                                "{} will be powered off and on again, dropping {n} connection(s)",
+                                adapter.name
+                            ),
                         };
-                        let _ = self
+                        let choices = vec![PrivacyChoice::Apply, PrivacyChoice::Cancel];
+                        let context = (adapter.clone(), on);
+                        let view = ConfirmView::new("LE privacy", msg, choices, context);
+                        self.push_view(
+                            Box::new(view),
+                            AppRequest::ExecAdapterAction(adapter, action),
+                        );
+                    }
+                    _ => {
+                        if self
                             .bt
-                            .exec_adapter_action(&adapter.id, action, on_complete)
-                            .await;
+                            .exec_adapter_action(&adapter.id, action)
+                            .await
+                            .is_none()
+                        {
+                            let msg = format!("Adapter {} not found", adapter.name);
+                            self.status.show(&mut self.vc, msg);
+                        }
                     }
                 };
             }
             AppRequest::ExecDeviceAction(adapter_id, device_id, action) => {
-                let mut id = StatusId::default();
+                if !device_id.is_allowed() {
+                    let msg = format!("{device_id} is not in allowed_devices");
+                    self.status.show(&mut self.vc, msg);
+                    return;
+                }
 
                 if let DeviceAction::Info = action {
-                    todo!();
-                }
-                if let TaskStatus::Running = self.bt.poll_exec_device_action().await {
-                    self.vc
-                        .show_status("Another device operation is running".into());
+                    let req = AppRequest::OpenDeviceInfoView(adapter_id, device_id);
+                    Box::pin(self.handle_request(req)).await;
                     return;
                 }
-                if let DeviceAction::SetConnected(val) = action {
-                    let device = self
+                if let DeviceAction::SetAutoConnect(enabled) = action {
+                    let alias = self
                         .bt
                         .get_adapter(&adapter_id)
                         .and_then(|a| a.get_device(&device_id))
-                        .expect("Failed to get device");
-                    let msg = match val {
-                        true => "Connecting to",
-                        _ => "Disconnecting from",
+                        .map_or(device_id.to_string(), |d| d.alias.clone());
+                    let done = action.done(&alias).unwrap_or_default();
+                    let msg = match self.bt.set_auto_connect(&device_id, enabled).await {
+                        Ok(()) => done,
+                        Err(e) => format!("{done}, not saved: {e}"),
                     };
-                    id = self
-                        .vc
-                        .show_status_always(format!("{} {}", msg, device.alias));
+                    self.status.show(&mut self.vc, msg);
+                    return;
+                }
+                if let TaskStatus::Running = self.bt.poll_exec_device_action().await {
+                    self.status
+                        .show(&mut self.vc, "Another device operation is running".into());
+                    return;
+                }
+                if self
+                    .bt
+                    .exec_device_action(&adapter_id, &device_id, action)
+                    .await
+                    .is_none()
+                {
+                    let msg = format!("Device {device_id} not found");
+                    self.status.show(&mut self.vc, msg);
                 }
-                let finally = {
-                    let status = self.vc.status().clone();
-                    move || status.lock().unwrap().remove(id)
+            }
+
+            AppRequest::PromptRetry(adapter_id, device_id, action, msg) => {
+                let title = format!("{action} failed");
+                let choices = vec![RetryChoice::Retry, RetryChoice::Info, RetryChoice::Dismiss];
+                let context = (adapter_id, device_id, action);
+                let view = ConfirmView::new(&title, msg.clone(), choices, context);
+                self.push_view(
+                    Box::new(view),
+                    AppRequest::PromptRetry(adapter_id, device_id, action, msg),
+                );
+            }
+
+            AppRequest::BatchPair(adapter_id, devices) => {
+                if self
+                    .bt
+                    .exec_batch_pair(&adapter_id, devices.clone())
+                    .await
+                    .is_none()
+                {
+                    let msg = format!("Adapter {adapter_id} not found");
+                    self.status.show(&mut self.vc, msg);
+                    return;
+                }
+                self.push_view(
+                    Box::new(BatchView::new(self.bt.batch.clone())),
+                    AppRequest::BatchPair(adapter_id, devices),
+                );
+            }
+            AppRequest::ShowStatus(msg) => self.status.show(&mut self.vc, msg),
+
+            AppRequest::OpenSearchView => {
+                let view = SearchView::new(&self.bt);
+                self.push_view(Box::new(view), AppRequest::OpenSearchView);
+            }
+            AppRequest::OpenAddressView(adapter_id) => {
+                let view = AddressView::new(adapter_id);
+                self.push_view(Box::new(view), AppRequest::OpenAddressView(adapter_id));
+            }
+            AppRequest::ConnectAddress(adapter_id, text) => {
+                let mut words = text.split_whitespace();
+                let Some(Ok(address)) = words.next().map(str::parse::<Address>) else {
+                    let msg = format!("{text:?} is not an address, e.g. AA:BB:CC:DD:EE:FF");
+                    self.status.show(&mut self.vc, msg);
+                    return;
                 };
-                let _ = self
+                let kind = match words.next() {
+                    None => None,
+                    Some("bredr") => Some(AddressType::BrEdr),
+                    Some("le-public") => Some(AddressType::LePublic),
+                    Some("le-random") => Some(AddressType::LeRandom),
+                    Some(other) => {
+                        let msg = format!(
+                            "{other:?} is not an address type, use bredr, le-public or le-random"
+                        );
+                        self.status.show(&mut self.vc, msg);
+                        return;
+                    }
+                };
+                let device_id = DeviceId(address);
+                if !device_id.is_allowed() {
+                    let msg = format!("{device_id} is not in allowed_devices");
+                    self.status.show(&mut self.vc, msg);
+                    return;
+                }
+                if let TaskStatus::Running = self.bt.poll_exec_device_action().await {
+                    self.status
+                        .show(&mut self.vc, "Another device operation is running".into());
+                    return;
+                }
+                if self
                     .bt
-                    .exec_device_action(&adapter_id, &device_id, action, finally)
-                    .await;
+                    .connect_address(&adapter_id, &device_id, kind)
+                    .await
+                    .is_some()
+                {
+                    self.status
+                        .show(&mut self.vc, format!("Connecting to {device_id}"));
+                }
             }
 
             AppRequest::MonitorDevice(adapter_id, device_id) => {
-                self.vc.show_status(format!("{:?}", req));
                 let device = self
                     .bt
                     .get_actual_device(&adapter_id, &device_id)
                     .await
                     .unwrap();
-                self.monitor_device(device);
+                self.bt.monitored_device = Some((adapter_id, device_id));
+                self.bt.monitored = device
+                    .all_properties()
+                    .await
+                    .unwrap_or_default()
+                    .iter()
+                    .map(PropertyRow::from)
+                    .collect();
+                self.push_view(
+                    Box::new(MonitorView::new(
+                        device_id,
+                        self.bt.monitored.clone(),
+                        TableState::new().with_selected(0),
+                    )),
+                    AppRequest::MonitorDevice(adapter_id, device_id),
+                );
+                self.monitor_device(device).await;
+            }
+            AppRequest::OpenBatteryView(device_id) => {
+                let alias = self
+                    .bt
+                    .get_device_adapter(&device_id)
+                    .and_then(|a| a.get_device(&device_id))
+                    .map_or(device_id.to_string(), |d| d.alias.clone());
+                self.push_view(
+                    Box::new(BatteryView::new(
+                        device_id,
+                        alias,
+                        self.bt.state.battery_history(&device_id),
+                    )),
+                    AppRequest::OpenBatteryView(device_id),
+                );
+            }
+            AppRequest::OpenCheatsheet => {
+                let view = CheatsheetView::new(self.vc.curr().kind());
+                self.push_view(Box::new(view), AppRequest::OpenCheatsheet);
+            }
+            AppRequest::OpenHistoryView => {
+                let view = HistoryView::new(&self.bt.actions, TableState::new().with_selected(0));
+                self.push_view(Box::new(view), AppRequest::OpenHistoryView);
+            }
+            AppRequest::ExpireInRange(device_id) => {
+                if self.in_range.is_some_and(|(_, id)| id == device_id) {
+                    self.in_range = None;
+                }
+            }
+            AppRequest::OpenPairElsewhereView(adapter_id, device_id) => {
+                let Some(alias) = self
+                    .bt
+                    .get_adapter(&adapter_id)
+                    .and_then(|a| a.get_device(&device_id))
+                    .map(|d| d.alias.clone())
+                else {
+                    return;
+                };
+                let adapters = self
+                    .bt
+                    .get_adapters(&Adapter::BY_ADDRESS)
+                    .into_iter()
+                    .filter(|a| a.id != adapter_id)
+                    .collect_vec();
+                if adapters.is_empty() {
+                    let msg = format!("No other adapter to pair {alias} on");
+                    self.status.show(&mut self.vc, msg);
+                    return;
+                }
+                let msg = format!("Pair {alias} on which adapter?");
+                let view = ConfirmView::new("Pair on other adapter", msg, adapters, device_id);
+                self.push_view(
+                    Box::new(view),
+                    AppRequest::OpenPairElsewhereView(adapter_id, device_id),
+                );
+            }
+            AppRequest::PairOnAdapter(adapter_id, device_id) => {
+                let Some(adapter) = self.bt.get_adapter(&adapter_id).cloned() else {
+                    return;
+                };
+                let alias = self
+                    .bt
+                    .get_device_adapter(&device_id)
+                    .and_then(|a| a.get_device(&device_id))
+                    .map_or(device_id.to_string(), |d| d.alias.clone());
+                if adapter.get_device(&device_id).is_some() {
+                    let msg = format!("Pairing {alias} on {}", adapter.name);
+                    self.status.show(&mut self.vc, msg);
+                    let req = AppRequest::ExecDeviceAction(
+                        adapter_id,
+                        device_id,
+                        DeviceAction::SetPaired(true),
+                    );
+                    Box::pin(self.handle_request(req)).await;
+                    return;
+                }
+                // bluez only knows the devices an adapter has discovered
+                self.pair_elsewhere = Some((adapter_id, device_id));
+                self.timers.after(
+                    PAIR_ELSEWHERE_WAIT,
+                    AppRequest::ExpirePairElsewhere(device_id),
+                );
+                if !adapter.is_scanning {
+                    let req = AppRequest::ExecAdapterAction(
+                        adapter.clone(),
+                        AdapterAction::SetScanning(true),
+                    );
+                    Box::pin(self.handle_request(req)).await;
+                }
+                let msg = format!(
+                    "Put {alias} in pairing mode, looking for it on {}",
+                    adapter.name
+                );
+                self.status.show(&mut self.vc, msg);
+            }
+            AppRequest::ExpirePairElsewhere(device_id) => {
+                if let Some((adapter_id, id)) = self.pair_elsewhere
+                    && id == device_id
+                {
+                    self.pair_elsewhere = None;
+                    let name = self
+                        .bt
+                        .get_adapter(&adapter_id)
+                        .map_or(adapter_id.to_string(), |a| a.name.clone());
+                    let msg = format!("{device_id} was not found by {name}");
+                    self.status.show(&mut self.vc, msg);
+                }
+            }
+            AppRequest::OpenServiceView => {
+                let (msg, choices) = match service_state().await {
+                    Some(state) => (
+                        format!("{SERVICE} is {state}, start it?"),
+                        vec![ServiceChoice::Start, ServiceChoice::Dismiss],
+                    ),
+                    None => (
+                        "bluetoothd is not running, start it with your init system".to_string(),
+                        vec![ServiceChoice::Dismiss],
+                    ),
+                };
+                let view = ConfirmView::new("Bluetooth unavailable", msg, choices, ());
+                self.push_view(Box::new(view), AppRequest::OpenServiceView);
+            }
+            AppRequest::OpenUnblockView(adapter) => {
+                let msg = format!("{} is blocked by rfkill, unblock it?", adapter.name);
+                let choices = vec![UnblockChoice::Unblock, UnblockChoice::Dismiss];
+                let view = ConfirmView::new("Radio blocked", msg, choices, adapter.clone());
+                self.push_view(Box::new(view), AppRequest::OpenUnblockView(adapter));
+            }
+            AppRequest::OpenStartupView(problems) => {
+                let msg = problems.join("\n");
+                let choices = vec![StartupChoice::Dismiss, StartupChoice::Mute];
+                let view = ConfirmView::new("Startup checks", msg, choices, problems.clone());
+                self.push_view(Box::new(view), AppRequest::OpenStartupView(problems));
+            }
+            AppRequest::SetPrivacy(adapter, on) => {
+                let action = AdapterAction::SetPrivacy(on);
+                if self
+                    .bt
+                    .exec_adapter_action(&adapter.id, action)
+                    .await
+                    .is_none()
+                {
+                    let msg = format!("Adapter {} not found", adapter.name);
+                    self.status.show(&mut self.vc, msg);
+                }
+            }
+            AppRequest::MuteStartupChecks(problems) => {
+                if let Err(e) = self.bt.mute_startup_checks(problems) {
+                    self.status
+                        .show(&mut self.vc, format!("Can't save the state: {e}"));
+                }
+            }
+            AppRequest::UnblockAdapter(adapter) => match unblock(&adapter.name).await {
+                Ok(()) => {
+                    let req =
+                        AppRequest::ExecAdapterAction(adapter, AdapterAction::SetPowered(true));
+                    Box::pin(self.handle_request(req)).await;
+                }
+                Err(e) => {
+                    let msg = format!("Failed to unblock {}: {e}", adapter.name);
+                    self.status.show(&mut self.vc, msg);
+                }
+            },
+            AppRequest::StartService => {
+                let (sx, rx) = tokio::sync::oneshot::channel();
+                self.service_start = Some(rx);
+                let _ = tokio::spawn(async move {
+                    let _ = sx.send(start_service().await);
+                });
+                self.status
+                    .show(&mut self.vc, format!("Starting {SERVICE}"));
+            }
+            AppRequest::PowerAllAdapters(on) => {
+                let state = match on {
+                    true => "on",
+                    false => "off",
+                };
+                let msg = self
+                    .bt
+                    .power_all_adapters(on)
+                    .await
+                    .into_iter()
+                    .map(|(name, status)| match status {
+                        TaskStatus::Error(e) => format!("{name} failed: {e}"),
+                        _ => format!("{name} {state}"),
+                    })
+                    .join(", ");
+                self.status
+                    .show(&mut self.vc, format!("Power {state}: {msg}"));
+                self.vc.refresh(&self.bt);
+            }
+            AppRequest::OpenWelcomeView => {
+                let view = ConfirmView::welcome(&self.bt);
+                self.push_view(Box::new(view), AppRequest::OpenWelcomeView);
+            }
+            AppRequest::SaveSetup(setup) => {
+                let path = match setup.save() {
+                    Ok(path) => path,
+                    Err(e) => {
+                        self.status
+                            .show(&mut self.vc, format!("Failed to save the config: {e}"));
+                        return;
+                    }
+                };
+                self.status.show(
+                    &mut self.vc,
+                    format!(
+                        "Saved {}, the theme applies on the next start",
+                        path.display()
+                    ),
+                );
+                let adapter = setup
+                    .default_adapter
+                    .and_then(|name| self.bt.get_adapter_by_name(&name).cloned());
+                if let Some(adapter) = adapter {
+                    let mut req = match self.history.last() {
+                        Some(AppRequest::OpenDevicesView(a)) if a.id == adapter.id => {
+                            AppRequest::None
+                        }
+                        _ => AppRequest::OpenDevicesView(adapter.clone()),
+                    };
+                    if setup.scan_on_startup && !adapter.is_scanning {
+                        req = req
+                            + AppRequest::ExecAdapterAction(
+                                adapter,
+                                AdapterAction::SetScanning(true),
+                            );
+                    }
+                    Box::pin(self.handle_request(req)).await;
+                }
+            }
+            AppRequest::AcknowledgeNew(adapter_id) => {
+                self.bt.acknowledge_new(&adapter_id);
+                self.vc.refresh(&self.bt);
+            }
+            AppRequest::AcknowledgeDevice(device_id) => {
+                self.bt.acknowledge_device(&device_id);
+                self.vc.refresh(&self.bt);
+            }
+            AppRequest::ToggleQuickDevice(name) => {
+                let adapters = self.bt.get_adapters(&Adapter::BY_ADDRESS);
+                let found = adapters
+                    .iter()
+                    .filter(|a| a.is_on && !a.is_cached)
+                    .flat_map(|a| a.devices.iter().map(move |d| (a.id, d)))
+                    .find(|(_, d)| d.alias == name || d.id.to_string().eq_ignore_ascii_case(&name))
+                    .map(|(adapter_id, d)| (adapter_id, d.id, d.is_connected));
+                match found {
+                    Some((adapter_id, device_id, connected)) => {
+                        let action = DeviceAction::SetConnected(!connected);
+                        let req = AppRequest::ExecDeviceAction(adapter_id, device_id, action);
+                        Box::pin(self.handle_request(req)).await;
+                    }
+                    None => self.status.show(
+                        &mut self.vc,
+                        format!("{name} is not known to any powered adapter"),
+                    ),
+                }
+            }
+            AppRequest::KeepPowered => {
+                for id in std::mem::take(&mut self.idle_warned) {
+                    self.bt.keep_powered(&id);
+                    let msg = format!("Keeping {} on", self.bt.adapter_name(&id));
+                    self.status.show(&mut self.vc, msg);
+                }
+            }
+            AppRequest::ToggleScanType => {
+                self.scan_type = self.scan_type.toggled();
+                let saved = match Config::persist("scan_type", self.scan_type.to_string().into()) {
+                    Ok(_) => "saved to the config".to_string(),
+                    Err(e) => format!("not saved: {e}"),
+                };
+                self.status.show(
+                    &mut self.vc,
+                    format!("Scanning is {} from now on, {saved}", self.scan_type),
+                );
+                // a running scan switches right away
+                if let Some(adapter_id) = self.scan_adapter
+                    && self.adapter_event_task.is_some()
+                {
+                    let adapter = self.bt.get_adapter(&adapter_id).cloned();
+                    if let Some(adapter) = adapter {
+                        let req = AppRequest::ExecAdapterAction(
+                            adapter,
+                            AdapterAction::SetScanning(true),
+                        );
+                        Box::pin(self.handle_request(req)).await;
+                    }
+                }
+            }
+            AppRequest::SaveState => {
+                self.timers.after(SAVE_STATE_EVERY, AppRequest::SaveState);
+                self.bt.save_state();
+            }
+            AppRequest::BackgroundScan => {
+                if let Some(every) = CONFIG.background_scan() {
+                    self.timers.after(every, AppRequest::BackgroundScan);
+                }
+                // a running scan or operation already has the adapters busy
+                if self.adapter_event_task.is_some()
+                    || self.bt.has_pending_tasks()
+                    || !on_ac_power()
+                {
+                    return;
+                }
+                let duration = Duration::from_secs(CONFIG.scan_for.into());
+                if self.bt.background_scan(duration).await > 0 {
+                    self.timers.after(duration, AppRequest::RefreshViews);
+                }
+            }
+            AppRequest::AnswerPairing(accept) => self.bt.answer_pairing(accept),
+            AppRequest::CancelPairing => {
+                if self.bt.cancel_pairing().await {
+                    self.status
+                        .show(&mut self.vc, "Cancelling pairing".to_string());
+                }
+            }
+            AppRequest::OpenDeviceInfoView(adapter_id, device_id) => {
+                let device = self
+                    .bt
+                    .get_adapter(&adapter_id)
+                    .and_then(|a| a.get_device(&device_id))
+                    .cloned();
+                if let Some(device) = device {
+                    self.bt.load_ad_data(&adapter_id, &device_id).await;
+                    let ad_data = self.bt.ad_data(&device_id);
+                    let att_mtu = self.bt.att_mtu(&adapter_id, &device_id).await;
+                    let state = TableState::new().with_selected(0);
+                    self.push_view(
+                        Box::new(DeviceInfoView::new(
+                            adapter_id, device, ad_data, att_mtu, state,
+                        )),
+                        AppRequest::OpenDeviceInfoView(adapter_id, device_id),
+                    );
+                }
+            }
+            AppRequest::OpenGattView(adapter_id, device_id) => {
+                let alias = self
+                    .bt
+                    .get_adapter(&adapter_id)
+                    .and_then(|a| a.get_device(&device_id))
+                    .map_or(device_id.to_string(), |d| d.alias.clone());
+                match self.bt.load_gatt(&adapter_id, &device_id).await {
+                    Ok(()) if self.bt.gatt.is_empty() => self.status.show(
+                        &mut self.vc,
+                        format!("{alias} has no characteristics, is it connected?"),
+                    ),
+                    Ok(()) => {
+                        let state = TableState::new().with_selected(0);
+                        self.push_view(
+                            Box::new(GattView::new(
+                                alias,
+                                self.bt.gatt.clone(),
+                                self.bt.gatt_snippets(),
+                                state,
+                                Endian::default(),
+                            )),
+                            AppRequest::OpenGattView(adapter_id, device_id),
+                        );
+                    }
+                    Err(e) => self.status.show(&mut self.vc, e),
+                }
+            }
+            AppRequest::ReadCharacteristic(key) => self.bt.read_characteristic(key),
+            AppRequest::ToggleGattPoll(key) => {
+                let polled = self.bt.toggle_gatt_poll(key);
+                self.vc.refresh(&self.bt);
+                if !polled {
+                    self.status
+                        .show(&mut self.vc, "Stopped polling".to_string());
+                }
+            }
+            AppRequest::OpenWriteView(row) => match row.write_types().is_empty() {
+                true => {
+                    let msg = format!("{} is not writable", row.name());
+                    self.status.show(&mut self.vc, msg);
+                }
+                false => self.push_view(
+                    Box::new(WriteView::new(row.clone())),
+                    AppRequest::OpenWriteView(row),
+                ),
+            },
+            AppRequest::WriteCharacteristic(key, text, write_type) => {
+                // `name = hex` saves the payload instead of writing it
+                if let Some((name, value)) = text.split_once('=') {
+                    let msg = self
+                        .bt
+                        .save_snippet(key, name.trim(), value.trim(), write_type)
+                        .unwrap_or_else(|e| e);
+                    self.vc.refresh(&self.bt);
+                    self.status.show(&mut self.vc, msg);
+                    return;
+                }
+                match parse_hex(&text) {
+                    Ok(value) => self.bt.write_characteristic(key, value, write_type),
+                    Err(e) => self.status.show(&mut self.vc, e),
+                }
+            }
+            AppRequest::SendSnippet(key, snippet) => match parse_hex(&snippet.value) {
+                Ok(value) => self.bt.write_characteristic(key, value, snippet.write_type),
+                Err(e) => self
+                    .status
+                    .show(&mut self.vc, format!("{}: {e}", snippet.name)),
+            },
+            AppRequest::CopyToClipboard(text) => {
+                let msg = match copy_to_clipboard(&text) {
+                    Ok(()) => format!("Copied {text}"),
+                    Err(e) => format!("Copy failed: {e}"),
+                };
+                self.status.show(&mut self.vc, msg);
+            }
+            AppRequest::ExportActions => {
+                let lines = self.bt.actions.iter().map(|a| a.to_line()).collect_vec();
+                match State::export_actions(&lines) {
+                    Ok(path) => self.status.show(
+                        &mut self.vc,
+                        format!("Exported {} actions to {}", lines.len(), path.display()),
+                    ),
+                    Err(e) => self
+                        .status
+                        .show(&mut self.vc, format!("Export failed: {e}")),
+                }
+            }
+            AppRequest::OpenQuickView => {
+                let view = QuickView::new(&self.bt, TableState::new().with_selected(0));
+                self.push_view(Box::new(view), AppRequest::OpenQuickView);
+            }
+            AppRequest::OpenSummaryView(adapter_id) => {
+                if let Some(adapter) = self.bt.get_adapter(&adapter_id) {
+                    let view = SummaryView::new(adapter, &self.bt.found);
+                    self.push_view(Box::new(view), AppRequest::OpenSummaryView(adapter_id));
+                }
             }
         }
     }
@@ -1,11 +1,42 @@
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::time::{Duration, Instant};
 
+use bluer::agent::{Agent, AgentHandle, ReqError, ReqResult};
+use bluer::gatt::remote::{Characteristic, CharacteristicWriteRequest};
+use bluer::monitor::{Monitor, MonitorEvent, Pattern};
+use bluer::{
+    AdapterEvent, Address, AddressType, DeviceEvent, DeviceProperty, DiscoveryFilter,
+    DiscoveryTransport,
+};
+use chrono::{Local, Utc};
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
 use itertools::Itertools;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tokio::sync::oneshot::error::TryRecvError;
-use tokio::sync::oneshot::Receiver;
+use tokio::sync::oneshot::{self, Receiver};
 use tokio::task::JoinHandle;
 
-use crate::models::{Adapter, AdapterAction, AdapterId, Device, DeviceAction, DeviceId};
+use crate::config::{ScanType, StaleAction};
+use crate::errors::{bluez_experimental, experimental_error, friendly_message};
+use crate::globals::CONFIG;
+use crate::hexdump::parse_hex;
+use crate::identity::{bluez_identities, merge_identities};
+use crate::models::{
+    ActionOutcome, ActionRecord, Adapter, AdapterAction, AdapterId, BatchRow, BatchStatus, Device,
+    DeviceAction, DeviceCategory, DeviceId, DeviceOrder, GattRow, PairingPrompt, PinCode,
+    PropertyRow, ProximityState, WriteType,
+};
+use crate::privacy;
+use crate::rfkill::{rfkill_state, Rfkill};
+use crate::state::{SavedScan, Snippet, State};
+use crate::uuids::uuid_name;
+
+/// How often repeated advertisements of listed devices are applied to their rows
+const ADVERTISEMENT_FLUSH: Duration = Duration::from_millis(500);
 
 pub enum TaskStatus<T> {
     None,
@@ -13,63 +44,726 @@ pub enum TaskStatus<T> {
     Error(String),
     Done(T),
 }
+/// The adapter of an action that succeeded, with the name of its target
+type Finished<A> = (AdapterId, String, A);
+/// Labelled manufacturer and service data of an advertisement
+pub type AdData = Vec<(String, Vec<u8>)>;
+/// Outcome of an operation on a characteristic, keyed by its service and own id
+enum GattResult {
+    Read((u16, u16), Result<Vec<u8>, String>),
+    Written((u16, u16), Result<usize, String>),
+}
+struct Pairing {
+    device: bluer::Device,
+    alias: String,
+    deadline: Instant,
+    cancelled: bool,
+}
 pub struct BtManager {
-    pub session: bluer::Session,
+    /// Missing in the mock the view tests draw from
+    session: Option<bluer::Session>,
+    pub state: State,
+    /// The state as last written, so an unchanged one isn't written again
+    saved_state: String,
     adapters: Vec<Adapter>,
-    adapter_actions_ch: Option<Receiver<Result<AdapterId, bluer::Error>>>,
-    device_actions_ch: Option<Receiver<Result<AdapterId, bluer::Error>>>,
+    adapter_actions_ch: Option<Receiver<Result<Finished<AdapterAction>, bluer::Error>>>,
+    device_actions_ch: Option<Receiver<Result<Finished<DeviceAction>, bluer::Error>>>,
+    last_device_action: Option<(AdapterId, DeviceId, DeviceAction)>,
+    pairing: Option<Pairing>,
+    pub monitored: Vec<PropertyRow>,
+    /// The device whose properties are listed in `monitored`
+    pub monitored_device: Option<(AdapterId, DeviceId)>,
+    pub batch: Vec<BatchRow>,
+    batch_ch: Option<UnboundedReceiver<(DeviceId, BatchStatus)>>,
+    user_disconnects: HashSet<DeviceId>,
+    reconnects: Vec<(AdapterId, DeviceId, Instant)>,
+    disconnect_ch: Option<UnboundedReceiver<(AdapterId, DeviceId)>>,
+    watchdog: Option<JoinHandle<()>>,
+    arrivals: Vec<(AdapterId, DeviceId)>,
+    pub actions: Vec<ActionRecord>,
+    adapter_record: Option<usize>,
+    device_record: Option<usize>,
+    /// Devices discovered since startup
+    pub found: HashSet<DeviceId>,
+    /// Found devices still flagged as new, until acknowledged
+    unseen: HashSet<DeviceId>,
+    pub proximity: ProximityState,
+    proximity_ch: Option<UnboundedReceiver<DeviceProperty>>,
+    proximity_device: Option<(AdapterId, DeviceId)>,
+    // last signal or connection change of the proximity device
+    proximity_seen: Instant,
+    /// Whether bluetoothd exports its experimental interfaces, if it could be told
+    pub experimental: Option<bool>,
+    discoverable_secs: Vec<u64>,
+    /// When each powered adapter last had a connection or a scan running
+    idle_since: HashMap<AdapterId, Instant>,
+    /// LE privacy of the adapters btmgmt was asked about
+    privacy: HashMap<AdapterId, bool>,
+    /// Cleared when bluetoothd stops answering, the cached adapters are kept meanwhile
+    pub bluez_running: bool,
+    /// Connection attempts that failed since startup
+    pub connect_failures: u64,
+    /// Connections made from bluerat since startup
+    pub connections: u64,
+    /// Resolving keys of the paired devices, when bluez lets them be read
+    identities: Vec<(DeviceId, [u8; 16])>,
+    /// Why the resolving keys could not be read
+    pub identities_error: Option<String>,
+    /// Why bluez refused the pairing agent
+    pub agent_error: Option<String>,
+    /// Listed devices that advertised again since the last flush
+    advertised: HashSet<(AdapterId, DeviceId)>,
+    last_flush: Instant,
+    /// Kept for bluez to ask the pairings started here for codes to show
+    agent: Option<AgentHandle>,
+    pin_ch: UnboundedReceiver<PinCode>,
+    /// The code to type on the device being paired
+    pub pin_code: Option<PinCode>,
+    prompt_ch: UnboundedReceiver<PairingPrompt>,
+    /// The request being asked to the user, the next ones wait in the channel
+    pub pairing_prompt: Option<PairingPrompt>,
+    /// Advertising data of the device opened in the info view, re-read on every refresh
+    ad_data: Option<(AdapterId, DeviceId, AdData)>,
+    /// Characteristics of the device opened in the GATT view
+    pub gatt: Vec<GattRow>,
+    /// The device whose characteristics are in `gatt`
+    gatt_device: Option<DeviceId>,
+    gatt_chars: HashMap<(u16, u16), Characteristic>,
+    gatt_polls: HashMap<(u16, u16), JoinHandle<()>>,
+    gatt_tx: UnboundedSender<GattResult>,
+    gatt_ch: UnboundedReceiver<GattResult>,
 }
 impl BtManager {
-    pub async fn new() -> Self {
+    pub async fn new() -> bluer::Result<Self> {
+        let session = bluer::Session::new().await?;
+        let (agent, pin_ch, prompt_ch) = register_agent(&session).await;
+        let identities = bluez_identities();
+        Ok(Self {
+            session: Some(session),
+            experimental: bluez_experimental(),
+            identities_error: identities.as_ref().err().cloned(),
+            identities: identities.unwrap_or_default(),
+            agent_error: agent.as_ref().err().map(friendly_message),
+            agent: agent.ok(),
+            ..Self::offline(State::load(), pin_ch, prompt_ch)
+        })
+    }
+    /// A manager showing the adapters cached in the state, without a connection to bluez
+    fn offline(
+        state: State,
+        pin_ch: UnboundedReceiver<PinCode>,
+        prompt_ch: UnboundedReceiver<PairingPrompt>,
+    ) -> Self {
+        let (gatt_tx, gatt_ch) = mpsc::unbounded_channel();
         Self {
-            session: bluer::Session::new().await.unwrap(),
-            adapters: Vec::new(),
+            session: None,
+            adapters: state.cached_adapters(),
+            state,
+            saved_state: String::new(),
             adapter_actions_ch: None,
             device_actions_ch: None,
+            last_device_action: None,
+            pairing: None,
+            monitored: Vec::new(),
+            monitored_device: None,
+            batch: Vec::new(),
+            batch_ch: None,
+            user_disconnects: HashSet::new(),
+            reconnects: Vec::new(),
+            disconnect_ch: None,
+            watchdog: None,
+            arrivals: Vec::new(),
+            actions: Vec::new(),
+            adapter_record: None,
+            device_record: None,
+            found: HashSet::new(),
+            unseen: HashSet::new(),
+            proximity: ProximityState::Unknown,
+            proximity_ch: None,
+            proximity_device: None,
+            proximity_seen: Instant::now(),
+            experimental: None,
+            discoverable_secs: Vec::new(),
+            idle_since: HashMap::new(),
+            privacy: HashMap::new(),
+            bluez_running: true,
+            connect_failures: 0,
+            connections: 0,
+            identities_error: None,
+            agent_error: None,
+            identities: Vec::new(),
+            advertised: HashSet::new(),
+            last_flush: Instant::now(),
+            agent: None,
+            pin_ch,
+            pin_code: None,
+            prompt_ch,
+            pairing_prompt: None,
+            ad_data: None,
+            gatt: Vec::new(),
+            gatt_device: None,
+            gatt_chars: HashMap::new(),
+            gatt_polls: HashMap::new(),
+            gatt_tx,
+            gatt_ch,
+        }
+    }
+    /// A manager listing the given adapters, for the view tests
+    #[cfg(test)]
+    pub fn mock(adapters: Vec<Adapter>) -> Self {
+        let (_, pin_ch) = mpsc::unbounded_channel();
+        let (_, prompt_ch) = mpsc::unbounded_channel();
+        Self {
+            adapters,
+            ..Self::offline(State::default(), pin_ch, prompt_ch)
+        }
+    }
+    /// The connection to bluez, failing in the mock
+    pub fn session(&self) -> bluer::Result<&bluer::Session> {
+        self.session.as_ref().ok_or_else(|| bluer::Error {
+            kind: bluer::ErrorKind::Failed,
+            message: "Not connected to bluetoothd".to_string(),
+        })
+    }
+    async fn actual_adapter_names(&self) -> bluer::Result<Vec<String>> {
+        self.session()?.adapter_names().await
+    }
+    /// What stands between bluerat and the devices, checked once on startup
+    pub async fn health_check(&self) -> Vec<String> {
+        let names = match self.actual_adapter_names().await {
+            Ok(names) => names,
+            Err(e) => {
+                return vec![format!(
+                    "bluetoothd is not reachable: {}",
+                    friendly_message(&e)
+                )]
+            }
+        };
+        let mut problems = vec![];
+        let mut powered = false;
+        for name in &names {
+            let Ok(adapter) = self.session().and_then(|s| s.adapter(name)) else {
+                continue;
+            };
+            powered |= adapter.is_powered().await.unwrap_or(false);
+            match rfkill_state(name) {
+                Rfkill::Unblocked => {}
+                state => problems.push(format!(
+                    "{name} is {} by rfkill",
+                    state.to_string().to_lowercase()
+                )),
+            }
+        }
+        if names.is_empty() {
+            problems.push("No adapter found, is the controller plugged in?".to_string());
+        } else if !powered {
+            problems.push("No adapter is powered on".to_string());
         }
+        if self.agent.is_none() {
+            problems.push(
+                "The pairing agent could not be registered, pairing may need another agent"
+                    .to_string(),
+            );
+        }
+        problems
+    }
+    pub fn mute_startup_checks(&mut self, problems: Vec<String>) -> io::Result<()> {
+        for p in problems {
+            if !self.state.muted_checks.contains(&p) {
+                self.state.muted_checks.push(p);
+            }
+        }
+        self.state.save()
     }
     pub async fn update_adapters(&mut self) {
-        self.adapters.clear();
-        let adapters = self
-            .session
-            .adapter_names()
-            .await
-            .unwrap()
+        let Ok(names) = self.actual_adapter_names().await else {
+            self.bluez_running = false;
+            return;
+        };
+        self.bluez_running = true;
+        self.advertised.clear();
+        let cached = std::mem::take(&mut self.adapters);
+        let adapters = names
             .into_iter()
-            .map(|a| self.session.adapter(&a).unwrap())
+            .filter_map(|a| self.session().and_then(|s| s.adapter(&a)).ok())
             .collect_vec();
         for a in adapters {
             self.adapters.push(Adapter::from(a).await);
         }
+        self.note_arrivals(&cached);
+        self.track_discoverable(&cached);
+        self.track_idle();
+        self.flag_new();
+        self.merge_identities();
+        for d in self.adapters.iter().flat_map(|a| a.devices.iter()) {
+            if d.is_connected
+                && let Some(level) = d.battery
+            {
+                self.state.record_battery(d.id, level);
+            }
+        }
+        self.age_devices();
+        for a in cached {
+            if a.is_cached && self.get_adapter(&a.id).is_none() {
+                self.adapters.push(a);
+            }
+        }
         self.sort_adapters();
     }
+    /// Forgets when the devices were seen once an adapter that is present no longer
+    /// knows them, then ages the devices of every adapter
+    fn age_devices(&mut self) {
+        let present = self
+            .adapters
+            .iter()
+            .filter(|a| !a.is_cached)
+            .map(|a| a.id)
+            .collect::<HashSet<_>>();
+        let known: HashSet<DeviceId> = self
+            .adapters
+            .iter()
+            .filter(|a| !a.is_cached)
+            .flat_map(|a| a.devices.iter().map(|d| d.id))
+            .collect();
+        if !present.is_empty() {
+            self.state.seen.retain(|s| {
+                known.contains(&s.device) || s.adapter.is_some_and(|a| !present.contains(&a))
+            });
+        }
+        for id in present {
+            self.age_adapter(&id);
+        }
+    }
+    /// Records the devices of the adapter in range and drops the unpaired ones unseen for
+    /// `stale_after`, removing them from bluez too when `stale_action` says so. A device met
+    /// for the first time starts its clock then.
+    fn age_adapter(&mut self, adapter_id: &AdapterId) {
+        let now = Utc::now().timestamp();
+        let Some(adapter) = self.adapters.iter_mut().find(|a| a.id == *adapter_id) else {
+            return;
+        };
+        for d in &adapter.devices {
+            if d.is_connected || d.rssi.is_some() || self.state.last_seen(&d.id).is_none() {
+                self.state.record_seen(adapter.id, d.id, now);
+            }
+        }
+        let Some(limit) = CONFIG.stale_after() else {
+            return;
+        };
+        let limit = limit.as_secs() as i64;
+        let (stale, kept) = std::mem::take(&mut adapter.devices)
+            .into_iter()
+            .partition::<Vec<_>, _>(|d| {
+                !d.is_paired
+                    && !d.is_trusted
+                    && !d.is_connected
+                    && self
+                        .state
+                        .last_seen(&d.id)
+                        .is_some_and(|at| now - at > limit)
+            });
+        adapter.devices = kept;
+        if CONFIG.stale_action != StaleAction::Remove || stale.is_empty() {
+            return;
+        }
+        let Some(Ok(actual_adapter)) = self.session.as_ref().map(|s| s.adapter(&adapter.name))
+        else {
+            return;
+        };
+        let _ = tokio::spawn(async move {
+            for d in stale {
+                let _ = actual_adapter.remove_device(d.id.0).await;
+            }
+        });
+    }
+    // bluez does not report the time left, so it is counted from the moment
+    // the adapter was seen turning discoverable
+    fn track_discoverable(&mut self, before: &[Adapter]) {
+        for adapter in &mut self.adapters {
+            let Some(old) = before.iter().find(|a| a.id == adapter.id) else {
+                continue;
+            };
+            adapter.discoverable_until = match old {
+                _ if !adapter.is_discoverable || adapter.discoverable_timeout == 0 => None,
+                old if old.is_discoverable => old.discoverable_until,
+                old if !old.is_cached => {
+                    Some(Instant::now() + Duration::from_secs(adapter.discoverable_timeout.into()))
+                }
+                _ => None,
+            };
+        }
+    }
+    fn track_idle(&mut self) {
+        let now = Instant::now();
+        let idle = self
+            .adapters
+            .iter()
+            .filter(|a| a.is_on && !a.is_cached && !a.is_scanning && a.connections == 0)
+            .map(|a| a.id)
+            .collect_vec();
+        self.idle_since.retain(|id, _| idle.contains(id));
+        for id in idle {
+            let _ = self.idle_since.entry(id).or_insert(now);
+        }
+    }
+    /// Time left before each idle adapter with a power off policy is turned off
+    pub fn idle_power_off(&self) -> Vec<(Adapter, Duration)> {
+        self.adapters
+            .iter()
+            .filter_map(|a| {
+                let since = self.idle_since.get(&a.id)?;
+                let limit = CONFIG.idle_power_off(&a.name, &a.id.to_string())?;
+                Some((a.clone(), limit.saturating_sub(since.elapsed())))
+            })
+            .collect()
+    }
+    /// Runs a discovery for `duration` on the powered adapters not scanning already,
+    /// returning how many it started on
+    pub async fn background_scan(&self, duration: Duration) -> usize {
+        let mut started = 0;
+        for a in self
+            .adapters
+            .iter()
+            .filter(|a| a.is_on && !a.is_cached && !a.is_scanning)
+        {
+            let Some(adapter) = self.get_actual_adapter(&a.id).await else {
+                continue;
+            };
+            let _ = tokio::spawn(async move {
+                // discovery lasts as long as its stream is kept
+                if let Ok(events) = adapter.discover_devices().await {
+                    tokio::time::sleep(duration).await;
+                    drop(events);
+                }
+            });
+            started += 1;
+        }
+        started
+    }
+    /// Restarts the idle countdown of the adapter
+    pub fn keep_powered(&mut self, adapter_id: &AdapterId) {
+        if let Some(since) = self.idle_since.get_mut(adapter_id) {
+            *since = Instant::now();
+        }
+    }
+    /// Whether a discoverable countdown changed since the last call
+    pub fn discoverable_ticked(&mut self) -> bool {
+        let secs = self
+            .adapters
+            .iter()
+            .filter_map(|a| a.discoverable_left())
+            .map(|left| left.as_secs())
+            .collect_vec();
+        let ticked = secs != self.discoverable_secs;
+        self.discoverable_secs = secs;
+        ticked
+    }
+    /// Takes the paired devices that came into range since the last call
+    pub fn take_arrivals(&mut self) -> Vec<(AdapterId, DeviceId)> {
+        std::mem::take(&mut self.arrivals)
+    }
+    // bluez only reports an rssi for devices seen by the running discovery
+    fn note_arrivals(&mut self, before: &[Adapter]) {
+        for adapter in &self.adapters {
+            let Some(old) = before.iter().find(|a| a.id == adapter.id) else {
+                continue;
+            };
+            for device in &adapter.devices {
+                if device.is_paired
+                    && !device.is_connected
+                    && device.rssi.is_some()
+                    && old.get_device(&device.id).is_some_and(|d| d.rssi.is_none())
+                {
+                    self.arrivals.push((adapter.id, device.id));
+                }
+            }
+        }
+    }
+    /// Remembers the running discovery and its filter, so the next start can resume it
+    pub async fn remember_scan(&mut self, adapter_id: Option<AdapterId>) {
+        self.state.scans.clear();
+        let Some(adapter_id) = adapter_id else {
+            return;
+        };
+        if let Some(adapter) = self.get_actual_adapter(&adapter_id).await {
+            let filter = adapter.discovery_filter().await;
+            self.state.scans.push(SavedScan::new(adapter_id, &filter));
+        }
+    }
+    /// Applies the config section of the adapters, all of them or the named one, returning
+    /// what failed
+    pub async fn apply_adapter_sections(&self, only: Option<&str>) -> Vec<String> {
+        let mut errors = vec![];
+        for adapter in self.adapters.iter().filter(|a| !a.is_cached) {
+            if only.is_some_and(|name| name != adapter.name) {
+                continue;
+            }
+            let section = CONFIG.adapter(&adapter.name, &adapter.id.to_string());
+            let Some(actual) = self.get_actual_adapter(&adapter.id).await else {
+                continue;
+            };
+            let mut results = vec![];
+            if section.auto_power && !adapter.is_on {
+                results.push(("power it on", actual.set_powered(true).await));
+            }
+            if let Some(secs) = section.discoverable_timeout {
+                let res = actual.set_discoverable_timeout(secs).await;
+                results.push(("set its discoverable timeout", res));
+            }
+            if let Some(filter) = section.scan_filter {
+                results.push((
+                    "set its scan filter",
+                    actual.set_discovery_filter(filter).await,
+                ));
+            }
+            for (what, res) in results {
+                if let Err(e) = res {
+                    errors.push(format!(
+                        "Could not {what} for {}: {}",
+                        adapter.name,
+                        friendly_message(&e)
+                    ));
+                }
+            }
+        }
+        errors
+    }
+    /// Restricts the next discoveries of the adapter to a transport, keeping the rest of the filter
+    pub async fn set_scan_transport(
+        &self,
+        adapter_id: &AdapterId,
+        transport: DiscoveryTransport,
+    ) -> Result<(), String> {
+        let adapter = self
+            .get_actual_adapter(adapter_id)
+            .await
+            .ok_or(format!("Adapter {adapter_id} not found"))?;
+        let filter = DiscoveryFilter {
+            transport,
+            ..adapter.discovery_filter().await
+        };
+        adapter
+            .set_discovery_filter(filter)
+            .await
+            .map_err(|e| friendly_message(&e))
+    }
+    /// Restores the filter of the discovery running on the last quit, returning its adapter
+    pub async fn resume_scan(&mut self) -> Option<Result<Adapter, String>> {
+        let scan = self.state.scans.first()?.clone();
+        let adapter = self.get_actual_adapter(&scan.adapter).await?;
+        if let Err(e) = adapter.set_discovery_filter(scan.filter()).await {
+            return Some(Err(friendly_message(&e)));
+        }
+        self.get_adapter(&scan.adapter).cloned().map(Ok)
+    }
+    pub fn save_state(&mut self) {
+        self.state.adapters = self
+            .adapters
+            .iter()
+            .filter(|a| !a.is_cached)
+            .cloned()
+            .map(|mut a| {
+                a.devices.retain(|d| !d.is_cached);
+                a
+            })
+            .collect();
+        let _ = self.state.save_changed(&mut self.saved_state);
+    }
     pub async fn update_adapter(&mut self, adapter_id: &AdapterId) {
-        self.adapters.retain(|a| a.id != *adapter_id);
+        let (old, rest) = std::mem::take(&mut self.adapters)
+            .into_iter()
+            .partition(|a| a.id == *adapter_id);
+        self.adapters = rest;
 
         if let Some(adapter) = self.get_actual_adapter(adapter_id).await {
             self.adapters.push(Adapter::from(adapter).await);
         }
+        self.age_adapter(adapter_id);
+        self.track_discoverable(&old);
+        self.track_idle();
+        self.flag_new();
+        self.merge_identities();
+    }
+    /// Re-reads a single device into the cache, dropping it once bluez forgot it
+    pub async fn refresh_device(&mut self, adapter_id: &AdapterId, device_id: &DeviceId) {
+        let Some(actual) = self.get_actual_adapter(adapter_id).await else {
+            return;
+        };
+        let known = actual
+            .device_addresses()
+            .await
+            .is_ok_and(|addrs| addrs.contains(&device_id.0));
+        let device = match actual.device(device_id.0) {
+            Ok(device) if known && device_id.is_allowed() => Some(Device::from(device).await),
+            _ => None,
+        };
+        let Some(adapter) = self.adapters.iter_mut().find(|a| a.id == *adapter_id) else {
+            return;
+        };
+        let row = adapter.devices.iter().position(|d| d.id == *device_id);
+        match (row, device) {
+            (Some(i), Some(device)) => adapter.devices[i] = device,
+            (Some(i), None) => {
+                let _ = adapter.devices.remove(i);
+            }
+            (None, Some(device)) => adapter.devices.push(device),
+            (None, None) => {}
+        }
+        adapter.connections = adapter.devices.iter().filter(|d| d.is_connected).count();
+        self.age_adapter(adapter_id);
+        self.track_idle();
+        self.flag_new();
+        self.merge_identities();
     }
 
     pub fn mark_new_device(&mut self, device_id: &DeviceId) {
-        for a in self.adapters.iter_mut() {
-            for d in a.devices.iter_mut() {
-                if d.id == *device_id {
-                    d.is_new = true;
-                    return;
-                }
+        let _ = self.found.insert(*device_id);
+        let _ = self.unseen.insert(*device_id);
+        self.flag_new();
+    }
+    pub fn acknowledge_device(&mut self, device_id: &DeviceId) {
+        if self.unseen.remove(device_id) {
+            self.flag_new();
+        }
+    }
+    /// Clears the new flag of every device of the adapter
+    pub fn acknowledge_new(&mut self, adapter_id: &AdapterId) {
+        if let Some(adapter) = self.adapters.iter().find(|a| a.id == *adapter_id) {
+            for d in &adapter.devices {
+                let _ = self.unseen.remove(&d.id);
+            }
+        }
+        self.flag_new();
+    }
+    /// Queues a repeated advertisement of a listed device, false when the
+    /// device isn't listed yet and needs a full refresh
+    pub fn note_advertisement(&mut self, adapter_id: &AdapterId, device_id: &DeviceId) -> bool {
+        let listed = self
+            .get_adapter(adapter_id)
+            .is_some_and(|a| a.get_device(device_id).is_some());
+        if listed {
+            let _ = self.advertised.insert((*adapter_id, *device_id));
+        }
+        listed
+    }
+    /// Re-reads the devices that advertised since the last flush, at most
+    /// every `ADVERTISEMENT_FLUSH`, returning whether any row changed
+    pub async fn flush_advertisements(&mut self) -> bool {
+        if self.advertised.is_empty() || self.last_flush.elapsed() < ADVERTISEMENT_FLUSH {
+            return false;
+        }
+        self.last_flush = Instant::now();
+        let mut changed = false;
+        for (adapter_id, device_id) in std::mem::take(&mut self.advertised) {
+            let Some(actual) = self.get_actual_adapter(&adapter_id).await else {
+                continue;
+            };
+            let Ok(device) = actual.device(device_id.0) else {
+                continue;
+            };
+            let device = Device::from(device).await;
+            if let Some(row) = self
+                .adapters
+                .iter_mut()
+                .find(|a| a.id == adapter_id)
+                .and_then(|a| a.get_device_mut(&device_id))
+                && (row.rssi != device.rssi
+                    || row.uuids != device.uuids
+                    || row.alias != device.alias)
+            {
+                *row = device;
+                changed = true;
             }
         }
+        if changed {
+            self.flag_new();
+            self.merge_identities();
+        }
+        changed
+    }
+    fn merge_identities(&mut self) {
+        for adapter in &mut self.adapters {
+            merge_identities(&mut adapter.devices, &self.identities);
+        }
+    }
+    // reloaded devices start unflagged
+    fn flag_new(&mut self) {
+        for d in self.adapters.iter_mut().flat_map(|a| a.devices.iter_mut()) {
+            d.is_new = self.unseen.contains(&d.id);
+        }
     }
     pub fn get_adapters(&self, sorter: &Sorter<Adapter>) -> Vec<Adapter> {
         self.adapters.iter().cloned().sorted_by(sorter.0).collect()
     }
+    pub fn update_monitored(&mut self, prop: &bluer::DeviceProperty) {
+        if let Some((adapter_id, device_id)) = self.monitored_device {
+            let _ = self.apply_device_property(&adapter_id, &device_id, prop);
+        }
+        let mut row = PropertyRow::from(prop);
+        match self.monitored.iter_mut().find(|r| r.name == row.name) {
+            Some(r) if r.value != row.value => {
+                r.value = row.value;
+                r.changed_at = Some(Instant::now());
+            }
+            Some(_) => {}
+            None => {
+                row.changed_at = Some(Instant::now());
+                self.monitored.push(row);
+            }
+        }
+    }
+    /// Applies a property change of the adapter to the cache, false when it needs a reload
+    pub fn apply_adapter_property(
+        &mut self,
+        adapter_id: &AdapterId,
+        prop: &bluer::AdapterProperty,
+    ) -> bool {
+        let Some(adapter) = self.get_adapter_mut(adapter_id) else {
+            return false;
+        };
+        let before = adapter.clone();
+        if !adapter.apply(prop) {
+            return false;
+        }
+        self.track_discoverable(&[before]);
+        self.track_idle();
+        true
+    }
+    /// Applies a property change of the device to the cache, false when the device isn't
+    /// listed or the property isn't kept
+    pub fn apply_device_property(
+        &mut self,
+        adapter_id: &AdapterId,
+        device_id: &DeviceId,
+        prop: &bluer::DeviceProperty,
+    ) -> bool {
+        let Some(adapter) = self.get_adapter_mut(adapter_id) else {
+            return false;
+        };
+        let Some(device) = adapter.get_device_mut(device_id) else {
+            return false;
+        };
+        if !device.apply(prop) {
+            return false;
+        }
+        adapter.connections = adapter.devices.iter().filter(|d| d.is_connected).count();
+        self.track_idle();
+        true
+    }
     pub fn get_adapter(&self, adapter_id: &AdapterId) -> Option<&Adapter> {
         self.adapters.iter().find(|a| a.id == *adapter_id)
     }
+    pub fn get_adapter_by_name(&self, name: &str) -> Option<&Adapter> {
+        self.adapters.iter().find(|a| a.name == name)
+    }
     pub fn get_adapter_mut(&mut self, adapter_id: &AdapterId) -> Option<&mut Adapter> {
         self.adapters.iter_mut().find(|a| a.id == *adapter_id)
     }
+    pub fn get_device_adapter(&self, device_id: &DeviceId) -> Option<&Adapter> {
+        self.adapters
+            .iter()
+            .find(|a| a.get_device(device_id).is_some())
+    }
     pub fn get_random_adapter(&self) -> Option<&Adapter> {
         self.adapters.first()
     }
@@ -79,23 +773,20 @@ impl BtManager {
         device_id: &DeviceId,
     ) -> Option<bluer::Device> {
         self.get_actual_adapter(adapter_id)
-            .await
-            .clone()
-            .unwrap()
+            .await?
             .device(device_id.0)
             .ok()
     }
     pub async fn get_actual_adapter(&self, adapter_id: &AdapterId) -> Option<bluer::Adapter> {
         let adapters = self
-            .session
-            .adapter_names()
+            .actual_adapter_names()
             .await
-            .unwrap()
+            .ok()?
             .into_iter()
-            .map(|a| self.session.adapter(&a).unwrap());
+            .filter_map(|a| self.session().and_then(|s| s.adapter(&a)).ok());
 
         for a in adapters {
-            if a.address().await.unwrap() == adapter_id.0 {
+            if a.address().await.is_ok_and(|addr| addr == adapter_id.0) {
                 return Some(a);
             }
         }
@@ -106,26 +797,43 @@ impl BtManager {
         &mut self,
         adapter_id: &AdapterId,
         action: AdapterAction,
-        finally: impl FnOnce() + Send + 'static,
     ) -> Option<JoinHandle<()>> {
+        let adapter = self.get_actual_adapter(adapter_id).await?;
         let (s, r) = tokio::sync::oneshot::channel();
         self.adapter_actions_ch = Some(r);
-        let adapter = self.get_actual_adapter(adapter_id).await?;
+        let label = action.label();
+        self.adapter_record = Some(self.record_action(self.adapter_name(adapter_id), label));
+        if let AdapterAction::SetPowered(false) = action
+            && let Some(adapter) = self.adapters.iter().find(|a| a.id == *adapter_id)
+        {
+            let connected = adapter.devices.iter().filter(|d| d.is_connected);
+            self.user_disconnects.extend(connected.map(|d| d.id));
+        }
 
         Some(tokio::spawn(async move {
             let res = match action {
                 AdapterAction::SetPowered(v) => adapter.set_powered(v.into()).await,
                 AdapterAction::SetDiscoverable(v) => adapter.set_discoverable(v.into()).await,
                 AdapterAction::SetPairable(v) => adapter.set_pairable(v.into()).await,
-                AdapterAction::SetScanning(_) | AdapterAction::Info => Ok(()),
+                AdapterAction::SetPrivacy(v) => {
+                    let powered = adapter.is_powered().await.unwrap_or(false);
+                    privacy::set_privacy(adapter.name(), v, powered)
+                        .await
+                        .map_err(|message| bluer::Error {
+                            kind: bluer::ErrorKind::Failed,
+                            message,
+                        })
+                }
+                AdapterAction::SetScanning(_)
+                | AdapterAction::ScanOnly(_)
+                | AdapterAction::Info => Ok(()),
             };
             let id = AdapterId(adapter.address().await.unwrap());
-            let _ = s.send(res.map(|_| id));
-            finally();
+            let _ = s.send(res.map(|_| (id, adapter.name().to_string(), action)));
         }))
     }
-    pub async fn poll_exec_adapter_action(&mut self) -> TaskStatus<()> {
-        match &mut self.adapter_actions_ch {
+    pub async fn poll_exec_adapter_action(&mut self) -> TaskStatus<(String, AdapterAction)> {
+        let status = match &mut self.adapter_actions_ch {
             Some(rx) => match rx.try_recv() {
                 Err(TryRecvError::Empty) => TaskStatus::Running,
                 Err(TryRecvError::Closed) => {
@@ -134,48 +842,138 @@ impl BtManager {
                 }
                 Ok(Err(e)) => {
                     self.adapter_actions_ch = None;
-                    TaskStatus::Error(e.message)
+                    TaskStatus::Error(friendly_message(&e))
                 }
-                Ok(Ok(id)) => {
+                Ok(Ok((id, name, action))) => {
                     self.adapter_actions_ch = None;
+                    if let AdapterAction::SetPrivacy(on) = action {
+                        let _ = self.privacy.insert(id, on);
+                    }
                     self.update_adapter(&id).await;
-                    TaskStatus::Done(())
+                    TaskStatus::Done((name, action))
                 }
             },
             None => TaskStatus::None,
+        };
+        if let Some(i) = self.adapter_record
+            && self.finish_action(i, &status)
+        {
+            self.adapter_record = None;
         }
+        status
     }
 
+    /// LE privacy of the adapter, btmgmt being asked only the first time
+    pub async fn privacy(&mut self, adapter: &Adapter) -> Option<bool> {
+        if let Some(on) = self.privacy.get(&adapter.id) {
+            return Some(*on);
+        }
+        let on = privacy::privacy(&adapter.name).await?;
+        let _ = self.privacy.insert(adapter.id, on);
+        Some(on)
+    }
+    /// Makes the action the running one, returning where its task sends the outcome
+    fn start_device_action(
+        &mut self,
+        adapter_id: &AdapterId,
+        device_id: &DeviceId,
+        alias: String,
+        action: DeviceAction,
+    ) -> oneshot::Sender<Result<Finished<DeviceAction>, bluer::Error>> {
+        let (s, r) = oneshot::channel();
+        self.device_actions_ch = Some(r);
+        self.last_device_action = Some((*adapter_id, *device_id, action));
+        self.device_record = Some(self.record_action(alias, action.label()));
+        match action {
+            DeviceAction::SetConnected(false)
+            | DeviceAction::SetPaired(false)
+            | DeviceAction::SetBlocked(true) => {
+                let _ = self.user_disconnects.insert(*device_id);
+            }
+            DeviceAction::SetConnected(true) => {
+                let _ = self.user_disconnects.remove(device_id);
+                self.reconnects.retain(|(_, id, _)| id != device_id);
+            }
+            _ => {}
+        }
+        if let DeviceAction::SetConnected(true) | DeviceAction::SetPaired(true) = action {
+            self.acknowledge_device(device_id);
+        }
+        s
+    }
     pub async fn exec_device_action(
         &mut self,
         adapter_id: &AdapterId,
         device_id: &DeviceId,
         action: DeviceAction,
-        finally: impl FnOnce() + Send + 'static,
     ) -> Option<JoinHandle<()>> {
-        let (s, r) = tokio::sync::oneshot::channel();
-        self.device_actions_ch = Some(r);
-
+        let alias = self
+            .get_adapter(adapter_id)
+            .and_then(|a| a.get_device(device_id))
+            .map_or(device_id.to_string(), |d| d.alias.clone());
         let adapter = self.get_actual_adapter(adapter_id).await?;
         let device = self.get_actual_device(adapter_id, device_id).await?;
+        let s = self.start_device_action(adapter_id, device_id, alias.clone(), action);
+        self.pairing = match action {
+            DeviceAction::SetPaired(true) => Some(Pairing {
+                device: device.clone(),
+                alias: alias.clone(),
+                deadline: Instant::now() + Duration::from_secs(CONFIG.pair_timeout.into()),
+                cancelled: false,
+            }),
+            _ => None,
+        };
 
         Some(tokio::spawn(async move {
-            let res = match action {
-                DeviceAction::SetConnected(true) => device.connect().await,
-                DeviceAction::SetConnected(false) => device.disconnect().await,
-                DeviceAction::SetPaired(true) => device.pair().await,
-                DeviceAction::SetPaired(false) => adapter.remove_device(device.address()).await,
-                DeviceAction::SetTrusted(val) => device.set_trusted(val).await,
-                DeviceAction::SetBlocked(val) => device.set_blocked(val).await,
-                DeviceAction::Info => Ok(()),
-            };
+            let res = apply_device_action(&adapter, &device, action).await;
             let id = AdapterId(adapter.address().await.unwrap());
-            let _ = s.send(res.map(|_| id));
-            finally();
+            let _ = s.send(res.map(|_| (id, alias, action)));
+        }))
+    }
+    /// Connects to an address the adapter may not know, for devices out of the scan results.
+    /// Without an address type both bearers are tried, then the random one for the addresses
+    /// that may be random static ones.
+    pub async fn connect_address(
+        &mut self,
+        adapter_id: &AdapterId,
+        device_id: &DeviceId,
+        kind: Option<AddressType>,
+    ) -> Option<JoinHandle<()>> {
+        let action = DeviceAction::SetConnected(true);
+        let adapter = self.get_actual_adapter(adapter_id).await?;
+        let known = adapter.device_addresses().await.ok()?;
+        if known.contains(&device_id.0) {
+            return self.exec_device_action(adapter_id, device_id, action).await;
+        }
+        let kinds = match kind {
+            Some(kind) => vec![kind],
+            // random static addresses have their two top bits set
+            None if device_id.0 .0[0] >> 6 == 0b11 => {
+                vec![
+                    AddressType::BrEdr,
+                    AddressType::LePublic,
+                    AddressType::LeRandom,
+                ]
+            }
+            None => vec![AddressType::BrEdr, AddressType::LePublic],
+        };
+        let s = self.start_device_action(adapter_id, device_id, device_id.to_string(), action);
+        let (adapter_id, address) = (*adapter_id, device_id.0);
+        Some(tokio::spawn(async move {
+            // bluez needs the address type to create the device
+            let mut res = adapter.connect_device(address, kinds[0]).await;
+            for kind in &kinds[1..] {
+                if res.is_ok() {
+                    break;
+                }
+                res = adapter.connect_device(address, *kind).await;
+            }
+            let res = res.map_err(experimental_error);
+            let _ = s.send(res.map(|_| (adapter_id, address.to_string(), action)));
         }))
     }
-    pub async fn poll_exec_device_action(&mut self) -> TaskStatus<()> {
-        match &mut self.device_actions_ch {
+    pub async fn poll_exec_device_action(&mut self) -> TaskStatus<(String, DeviceAction)> {
+        let status = match &mut self.device_actions_ch {
             Some(rx) => match rx.try_recv() {
                 Err(TryRecvError::Empty) => TaskStatus::Running,
                 Err(TryRecvError::Closed) => {
@@ -184,16 +982,715 @@ impl BtManager {
                 }
                 Ok(Err(e)) => {
                     self.device_actions_ch = None;
-                    TaskStatus::Error(e.message)
+                    self.pin_code = None;
+                    if let Some((_, _, DeviceAction::SetConnected(true))) = self.last_device_action
+                    {
+                        self.connect_failures += 1;
+                    }
+                    TaskStatus::Error(friendly_message(&e))
                 }
-                Ok(Ok(id)) => {
+                Ok(Ok((id, alias, action))) => {
                     self.device_actions_ch = None;
-                    self.update_adapter(&id).await;
-                    TaskStatus::Done(())
+                    self.pin_code = None;
+                    match action {
+                        // pairing may have handed over a resolving key
+                        DeviceAction::SetPaired(true) => {
+                            if let Ok(identities) = bluez_identities() {
+                                self.identities = identities;
+                            }
+                        }
+                        DeviceAction::SetConnected(true) => self.connections += 1,
+                        _ => {}
+                    }
+                    match self.last_device_action {
+                        Some((adapter_id, device_id, _)) => {
+                            self.refresh_device(&adapter_id, &device_id).await;
+                        }
+                        None => self.update_adapter(&id).await,
+                    }
+                    TaskStatus::Done((alias, action))
                 }
             },
             None => TaskStatus::None,
+        };
+        if let Some(i) = self.device_record
+            && self.finish_action(i, &status)
+        {
+            self.device_record = None;
+        }
+        status
+    }
+    /// The device being paired and the time left before the attempt is abandoned
+    pub fn pairing(&self) -> Option<(&str, Duration)> {
+        self.running_pairing().map(|p| {
+            (
+                p.alias.as_str(),
+                p.deadline.saturating_duration_since(Instant::now()),
+            )
+        })
+    }
+    /// Whether the device is being paired from bluerat, alone or in a batch
+    pub fn started_pairing(&self, device_id: &DeviceId) -> bool {
+        self.running_pairing()
+            .is_some_and(|p| p.device.address() == device_id.0)
+            || self
+                .batch
+                .iter()
+                .any(|r| r.id == *device_id && !r.status.is_finished())
+    }
+    fn running_pairing(&self) -> Option<&Pairing> {
+        self.pairing
+            .as_ref()
+            .filter(|p| self.device_actions_ch.is_some() && !p.cancelled)
+    }
+    /// Whether the last pairing was cancelled by the user
+    pub fn pairing_cancelled(&self) -> bool {
+        self.pairing.as_ref().is_some_and(|p| p.cancelled)
+    }
+    /// Asks bluez to abort the running pairing, the action then fails as cancelled
+    pub async fn cancel_pairing(&mut self) -> bool {
+        if self.running_pairing().is_none() {
+            return false;
+        }
+        let Some(pairing) = &mut self.pairing else {
+            return false;
+        };
+        pairing.cancelled = true;
+        let _ = pairing.device.cancel_pairing().await;
+        true
+    }
+
+    /// Takes the latest code bluez asked to show, true when one arrived
+    pub fn poll_pin_code(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(pin) = self.pin_ch.try_recv() {
+            self.pin_code = Some(pin);
+            changed = true;
         }
+        changed
+    }
+
+    /// Takes the next request to ask once the current one is answered or abandoned by bluez,
+    /// true when the request changed
+    pub fn poll_pairing_prompt(&mut self) -> bool {
+        let abandoned = self
+            .pairing_prompt
+            .take_if(|p| p.answer.is_closed())
+            .is_some();
+        if self.pairing_prompt.is_some() {
+            return abandoned;
+        }
+        self.pairing_prompt = self.prompt_ch.try_recv().ok();
+        abandoned || self.pairing_prompt.is_some()
+    }
+    pub fn answer_pairing(&mut self, accept: bool) {
+        if let Some(prompt) = self.pairing_prompt.take() {
+            let _ = prompt.answer.send(accept);
+        }
+    }
+
+    pub async fn exec_batch_pair(
+        &mut self,
+        adapter_id: &AdapterId,
+        device_ids: Vec<DeviceId>,
+    ) -> Option<JoinHandle<()>> {
+        let adapter = self.get_actual_adapter(adapter_id).await?;
+        self.batch = device_ids
+            .iter()
+            .map(|id| BatchRow {
+                id: *id,
+                alias: self
+                    .get_adapter(adapter_id)
+                    .and_then(|a| a.get_device(id))
+                    .map_or(id.to_string(), |d| d.alias.clone()),
+                status: BatchStatus::Waiting,
+            })
+            .collect();
+        let (s, r) = tokio::sync::mpsc::unbounded_channel();
+        self.batch_ch = Some(r);
+
+        Some(tokio::spawn(async move {
+            for id in device_ids {
+                let device = match adapter.device(id.0) {
+                    Ok(device) => device,
+                    Err(e) => {
+                        let _ = s.send((id, BatchStatus::Failed(friendly_message(&e))));
+                        continue;
+                    }
+                };
+                let _ = s.send((id, BatchStatus::Pairing));
+                let mut res = pair_with_timeout(&device).await;
+                if res.is_ok() {
+                    let _ = s.send((id, BatchStatus::Trusting));
+                    res = device.set_trusted(true).await;
+                }
+                let status = match res {
+                    Ok(_) => BatchStatus::Done,
+                    Err(e) => BatchStatus::Failed(friendly_message(&e)),
+                };
+                let _ = s.send((id, status));
+            }
+        }))
+    }
+    /// Applies the progress of the running batch, returning whether anything changed
+    pub fn poll_batch(&mut self) -> bool {
+        let Some(rx) = &mut self.batch_ch else {
+            return false;
+        };
+        let mut changed = false;
+        loop {
+            match rx.try_recv() {
+                Ok((id, status)) => {
+                    if !matches!(status, BatchStatus::Pairing) {
+                        self.pin_code = None;
+                    }
+                    if let Some(row) = self.batch.iter_mut().find(|r| r.id == id) {
+                        row.status = status;
+                    }
+                    changed = true;
+                }
+                Err(mpsc::error::TryRecvError::Empty) => return changed,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    self.batch_ch = None;
+                    return true;
+                }
+            }
+        }
+    }
+
+    /// Lists the characteristics of the device with their cached values, stopping the polls
+    /// of the previous one
+    pub async fn load_gatt(
+        &mut self,
+        adapter_id: &AdapterId,
+        device_id: &DeviceId,
+    ) -> Result<(), String> {
+        self.stop_gatt();
+        let device = self
+            .get_actual_device(adapter_id, device_id)
+            .await
+            .ok_or(format!("Device {device_id} not found"))?;
+        let services = device.services().await.map_err(|e| friendly_message(&e))?;
+        for service in services {
+            let chars = service.characteristics().await.unwrap_or_default();
+            for c in chars {
+                let (Ok(uuid), Ok(flags)) = (c.uuid().await, c.flags().await) else {
+                    continue;
+                };
+                let row = GattRow {
+                    service: service.id(),
+                    id: c.id(),
+                    uuid: uuid.to_string(),
+                    flags,
+                    value: c.cached_value().await.unwrap_or_default(),
+                    samples: Vec::new(),
+                    is_polled: false,
+                };
+                let _ = self.gatt_chars.insert(row.key(), c);
+                self.gatt.push(row);
+            }
+        }
+        self.gatt_device = Some(*device_id);
+        Ok(())
+    }
+    /// The ATT MTU negotiated with the connected device. Bluez exposes it on the characteristics
+    /// only, and keeps the PHY of the link to itself.
+    pub async fn att_mtu(&self, adapter_id: &AdapterId, device_id: &DeviceId) -> Option<usize> {
+        let device = self.get_actual_device(adapter_id, device_id).await?;
+        if !device.is_connected().await.unwrap_or_default() {
+            return None;
+        }
+        for service in device.services().await.ok()? {
+            for c in service.characteristics().await.unwrap_or_default() {
+                if let Ok(mtu) = c.mtu().await {
+                    return Some(mtu);
+                }
+            }
+        }
+        None
+    }
+    /// Reads the advertising data of the device opened in the info view
+    pub async fn load_ad_data(&mut self, adapter_id: &AdapterId, device_id: &DeviceId) {
+        let data = self.advertising_data(adapter_id, device_id).await;
+        self.ad_data = Some((*adapter_id, *device_id, data));
+    }
+    /// Reads the advertising data of the device in the info view again, when one is open
+    pub async fn reload_ad_data(&mut self) {
+        if let Some((adapter_id, device_id, _)) = self.ad_data.take() {
+            self.load_ad_data(&adapter_id, &device_id).await;
+        }
+    }
+    /// The advertising data last read for the device, empty when it wasn't loaded
+    pub fn ad_data(&self, device_id: &DeviceId) -> AdData {
+        match &self.ad_data {
+            Some((_, id, data)) if id == device_id => data.clone(),
+            _ => vec![],
+        }
+    }
+    /// Manufacturer and service data the device advertised last, labelled by company or service
+    async fn advertising_data(
+        &self,
+        adapter_id: &AdapterId,
+        device_id: &DeviceId,
+    ) -> Vec<(String, Vec<u8>)> {
+        let Some(device) = self.get_actual_device(adapter_id, device_id).await else {
+            return vec![];
+        };
+        let manufacturer = device.manufacturer_data().await.ok().flatten();
+        let service = device.service_data().await.ok().flatten();
+        manufacturer
+            .unwrap_or_default()
+            .into_iter()
+            .sorted()
+            .map(|(company, data)| (format!("Manufacturer 0x{company:04x}"), data))
+            .chain(
+                service
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(uuid, data)| {
+                        let uuid = uuid.to_string();
+                        let name = uuid_name(&uuid).map_or(uuid.clone(), str::to_string);
+                        (format!("Service data {name}"), data)
+                    })
+                    .sorted(),
+            )
+            .collect()
+    }
+    /// Reads the characteristic once, the value arrives through `poll_gatt`
+    pub fn read_characteristic(&self, key: (u16, u16)) {
+        let Some(c) = self.gatt_chars.get(&key).cloned() else {
+            return;
+        };
+        let tx = self.gatt_tx.clone();
+        let _ = tokio::spawn(async move {
+            let res = c.read().await.map_err(|e| friendly_message(&e));
+            let _ = tx.send(GattResult::Read(key, res));
+        });
+    }
+    /// Writes the value to the characteristic, the outcome arrives through `poll_gatt`
+    pub fn write_characteristic(&self, key: (u16, u16), value: Vec<u8>, write_type: WriteType) {
+        let Some(c) = self.gatt_chars.get(&key).cloned() else {
+            return;
+        };
+        let tx = self.gatt_tx.clone();
+        let _ = tokio::spawn(async move {
+            let req = CharacteristicWriteRequest {
+                op_type: write_type.op(),
+                ..Default::default()
+            };
+            let res = c.write_ext(&value, &req).await;
+            let res = res.map(|_| value.len()).map_err(|e| friendly_message(&e));
+            let _ = tx.send(GattResult::Written(key, res));
+        });
+    }
+    /// Starts or stops reading the characteristic every `gatt_poll_every` seconds,
+    /// returning whether it is now polled
+    pub fn toggle_gatt_poll(&mut self, key: (u16, u16)) -> bool {
+        let Some(row) = self.gatt.iter_mut().find(|r| r.key() == key) else {
+            return false;
+        };
+        row.samples.clear();
+        if let Some(task) = self.gatt_polls.remove(&key) {
+            task.abort();
+            row.is_polled = false;
+            return false;
+        }
+        let Some(c) = self.gatt_chars.get(&key).cloned() else {
+            return false;
+        };
+        let tx = self.gatt_tx.clone();
+        let every = Duration::from_secs(CONFIG.gatt_poll_every.into());
+        let task = tokio::spawn(async move {
+            loop {
+                let res = c.read().await.map_err(|e| friendly_message(&e));
+                if tx.send(GattResult::Read(key, res)).is_err() {
+                    return;
+                }
+                tokio::time::sleep(every).await;
+            }
+        });
+        let _ = self.gatt_polls.insert(key, task);
+        row.is_polled = true;
+        true
+    }
+    pub fn stop_gatt(&mut self) {
+        for (_, task) in self.gatt_polls.drain() {
+            task.abort();
+        }
+        self.gatt.clear();
+        self.gatt_chars.clear();
+        self.gatt_device = None;
+    }
+    /// Snippets saved for the characteristics of the device in the GATT view
+    pub fn gatt_snippets(&self) -> Vec<Snippet> {
+        self.gatt_device
+            .map_or(vec![], |device| self.state.device_snippets(&device))
+    }
+    /// Saves the value under the name for the characteristic, or forgets the name when the
+    /// value is empty, returning what to tell
+    pub fn save_snippet(
+        &mut self,
+        key: (u16, u16),
+        name: &str,
+        value: &str,
+        write_type: WriteType,
+    ) -> Result<String, String> {
+        let (Some(device), Some(row)) =
+            (self.gatt_device, self.gatt.iter().find(|r| r.key() == key))
+        else {
+            return Err("The characteristic is gone".to_string());
+        };
+        if name.is_empty() {
+            return Err("A snippet needs a name before the =".to_string());
+        }
+        let msg = match value.is_empty() {
+            true => match self.state.remove_snippet(&device, &row.uuid, name) {
+                true => format!("Removed snippet {name:?} of {}", row.name()),
+                false => return Err(format!("{} has no snippet {name:?}", row.name())),
+            },
+            false => {
+                let _ = parse_hex(value)?;
+                self.state.save_snippet(Snippet {
+                    device,
+                    characteristic: row.uuid.clone(),
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    write_type,
+                });
+                format!("Saved snippet {name:?} of {}", row.name())
+            }
+        };
+        self.state
+            .save()
+            .map_err(|e| format!("Saving the state failed: {e}"))?;
+        Ok(msg)
+    }
+    pub fn is_polling_gatt(&self) -> bool {
+        !self.gatt_polls.is_empty()
+    }
+    /// Applies the values read since the last call, returning whether any arrived and
+    /// what to tell about the writes. A failed read stops the poll of its characteristic.
+    pub fn poll_gatt(&mut self) -> (bool, Option<String>) {
+        let mut changed = false;
+        while let Ok(res) = self.gatt_ch.try_recv() {
+            let (GattResult::Read(key, _) | GattResult::Written(key, _)) = res;
+            let Some(row) = self.gatt.iter_mut().find(|r| r.key() == key) else {
+                continue;
+            };
+            changed = true;
+            match res {
+                GattResult::Read(_, Ok(value)) => row.record(value),
+                GattResult::Read(_, Err(e)) => {
+                    if let Some(task) = self.gatt_polls.remove(&key) {
+                        task.abort();
+                        row.is_polled = false;
+                    }
+                    return (true, Some(format!("Reading {} failed: {e}", row.name())));
+                }
+                GattResult::Written(_, Ok(len)) => {
+                    return (true, Some(format!("Wrote {len} bytes to {}", row.name())));
+                }
+                GattResult::Written(_, Err(e)) => {
+                    return (true, Some(format!("Writing {} failed: {e}", row.name())));
+                }
+            }
+        }
+        (changed, None)
+    }
+
+    /// Follows the connection state of the trusted devices, replacing the previous watch
+    pub async fn watch_connections(&mut self) {
+        if let Some(task) = self.watchdog.take() {
+            task.abort();
+        }
+        let mut watched: Vec<BoxStream<'static, (AdapterId, DeviceId)>> = vec![];
+        for adapter in self.adapters.iter().filter(|a| !a.is_cached) {
+            let Some(actual_adapter) = self.get_actual_adapter(&adapter.id).await else {
+                continue;
+            };
+            for device in adapter
+                .devices
+                .iter()
+                .filter(|d| d.is_trusted || self.state.auto_connect(&d.id) == Some(true))
+            {
+                let Ok(actual_device) = actual_adapter.device(device.id.0) else {
+                    continue;
+                };
+                let Ok(events) = actual_device.events().await else {
+                    continue;
+                };
+                let ids = (adapter.id, device.id);
+                watched.push(
+                    events
+                        .filter_map(move |ev| async move {
+                            match ev {
+                                DeviceEvent::PropertyChanged(DeviceProperty::Connected(false)) => {
+                                    Some(ids)
+                                }
+                                _ => None,
+                            }
+                        })
+                        .boxed(),
+                );
+            }
+        }
+        let (s, r) = tokio::sync::mpsc::unbounded_channel();
+        self.disconnect_ch = Some(r);
+        self.watchdog = Some(tokio::spawn(async move {
+            let mut events = stream::select_all(watched);
+            while let Some(ids) = events.next().await {
+                if s.send(ids).is_err() {
+                    return;
+                }
+            }
+        }));
+    }
+    /// Returns the trusted devices that dropped their connection without the user asking
+    pub fn poll_disconnects(&mut self) -> Vec<(AdapterId, DeviceId)> {
+        let Some(rx) = &mut self.disconnect_ch else {
+            return vec![];
+        };
+        let mut lost = vec![];
+        while let Ok((adapter_id, device_id)) = rx.try_recv() {
+            if !self.user_disconnects.remove(&device_id) {
+                lost.push((adapter_id, device_id));
+            }
+        }
+        lost
+    }
+    pub fn schedule_reconnect(
+        &mut self,
+        adapter_id: AdapterId,
+        device_id: DeviceId,
+        delay: Duration,
+    ) {
+        self.reconnects.retain(|(_, id, _)| *id != device_id);
+        self.reconnects
+            .push((adapter_id, device_id, Instant::now() + delay));
+    }
+    /// Whether the device is reconnected when it drops, by its own choice or `auto_reconnect`
+    pub fn auto_connects(&self, device_id: &DeviceId) -> bool {
+        self.state
+            .auto_connect(device_id)
+            .unwrap_or(CONFIG.auto_reconnect)
+    }
+    /// Remembers the auto-connect choice for the device, following its connection from now on
+    pub async fn set_auto_connect(
+        &mut self,
+        device_id: &DeviceId,
+        enabled: bool,
+    ) -> io::Result<()> {
+        self.state.set_auto_connect(*device_id, enabled);
+        self.watch_connections().await;
+        self.state.save()
+    }
+    /// Queues a connection to the devices set to auto-connect that are in reach of a powered
+    /// adapter, one after the other
+    pub fn auto_connect_all(&mut self) {
+        let targets = self
+            .adapters
+            .iter()
+            .filter(|a| a.is_on && !a.is_cached)
+            .flat_map(|a| a.devices.iter().map(move |d| (a.id, d)))
+            .filter(|(_, d)| !d.is_connected && self.state.auto_connect(&d.id) == Some(true))
+            .map(|(adapter_id, d)| (adapter_id, d.id))
+            .collect_vec();
+        for (adapter_id, device_id) in targets {
+            self.schedule_reconnect(adapter_id, device_id, Duration::ZERO);
+        }
+    }
+    /// Takes the next reconnection whose delay has elapsed, if no other device action is running
+    pub fn due_reconnect(&mut self) -> Option<(AdapterId, DeviceId)> {
+        if self.device_actions_ch.is_some() {
+            return None;
+        }
+        let now = Instant::now();
+        let pos = self.reconnects.iter().position(|(_, _, at)| *at <= now)?;
+        let (adapter_id, device_id, _) = self.reconnects.remove(pos);
+        Some((adapter_id, device_id))
+    }
+
+    /// Follows the signal strength of the device configured for proximity hooks
+    pub async fn watch_proximity(&mut self, device_id: &DeviceId) {
+        let Some(adapter_id) = self.get_device_adapter(device_id).map(|a| a.id) else {
+            return;
+        };
+        let Some(device) = self.get_actual_device(&adapter_id, device_id).await else {
+            return;
+        };
+        let Ok(events) = device.events().await else {
+            return;
+        };
+        let (s, r) = tokio::sync::mpsc::unbounded_channel();
+        self.proximity_ch = Some(r);
+        self.proximity_device = Some((adapter_id, *device_id));
+        self.proximity_seen = Instant::now();
+        let _ = tokio::spawn(async move {
+            let mut events = Box::pin(events);
+            while let Some(DeviceEvent::PropertyChanged(prop)) = events.next().await {
+                if matches!(prop, DeviceProperty::Rssi(_) | DeviceProperty::Connected(_))
+                    && s.send(prop).is_err()
+                {
+                    return;
+                }
+            }
+        });
+    }
+    /// Returns the new proximity state when the watched device left or returned, a device that
+    /// stops advertising leaves once nothing was heard from it for `leave_after`
+    pub fn poll_proximity(
+        &mut self,
+        leave_rssi: i16,
+        return_rssi: i16,
+        leave_after: Duration,
+    ) -> Option<ProximityState> {
+        let rx = self.proximity_ch.as_mut()?;
+        let before = self.proximity;
+        let mut props = vec![];
+        while let Ok(prop) = rx.try_recv() {
+            self.proximity_seen = Instant::now();
+            self.proximity = match prop {
+                DeviceProperty::Rssi(rssi) => self.proximity.next(rssi, leave_rssi, return_rssi),
+                // connected devices stop reporting an rssi
+                DeviceProperty::Connected(true) => ProximityState::Near,
+                _ => self.proximity,
+            };
+            props.push(prop);
+        }
+        if let Some((adapter_id, device_id)) = self.proximity_device {
+            for prop in &props {
+                let _ = self.apply_device_property(&adapter_id, &device_id, prop);
+            }
+            let connected = self
+                .get_adapter(&adapter_id)
+                .and_then(|a| a.get_device(&device_id))
+                .is_some_and(|d| d.is_connected);
+            if self.proximity == ProximityState::Near
+                && !connected
+                && self.proximity_seen.elapsed() >= leave_after
+            {
+                self.proximity = ProximityState::Away;
+            }
+        }
+        match (before, self.proximity) {
+            (ProximityState::Near, ProximityState::Away)
+            | (ProximityState::Away, ProximityState::Near) => Some(self.proximity),
+            _ => None,
+        }
+    }
+
+    /// Blocks a device matched by an `auto_block` pattern, logging it in the action history
+    pub async fn auto_block(
+        &mut self,
+        adapter_id: &AdapterId,
+        device_id: &DeviceId,
+    ) -> TaskStatus<()> {
+        let action = format!("{} (auto_block)", DeviceAction::SetBlocked(true).label());
+        let i = self.record_action(device_id.to_string(), &action);
+        let status = match self.get_actual_device(adapter_id, device_id).await {
+            Some(device) => match device.set_blocked(true).await {
+                Ok(_) => TaskStatus::Done(()),
+                Err(e) => TaskStatus::Error(friendly_message(&e)),
+            },
+            None => TaskStatus::Error(format!("Device {device_id} not found")),
+        };
+        let _ = self.finish_action(i, &status);
+        status
+    }
+    pub fn adapter_name(&self, adapter_id: &AdapterId) -> String {
+        self.get_adapter(adapter_id)
+            .map_or(adapter_id.to_string(), |a| a.name.clone())
+    }
+    /// Adds a running entry to the action history, returning its index
+    pub fn record_action(&mut self, target: String, action: &str) -> usize {
+        self.actions.push(ActionRecord {
+            target,
+            action: action.to_string(),
+            started: Local::now(),
+            finished: None,
+            outcome: ActionOutcome::Running,
+        });
+        self.actions.len() - 1
+    }
+    /// Settles the entry once the task is over, returning whether it was
+    pub fn finish_action<T>(&mut self, i: usize, status: &TaskStatus<T>) -> bool {
+        let outcome = match status {
+            TaskStatus::Done(_) => ActionOutcome::Done,
+            TaskStatus::Error(e) => ActionOutcome::Failed(e.clone()),
+            TaskStatus::None | TaskStatus::Running => return false,
+        };
+        if let Some(record) = self.actions.get_mut(i) {
+            record.finished = Some(Local::now());
+            record.outcome = outcome;
+            if CONFIG.persist_actions {
+                let _ = State::append_action_log(&record.to_line());
+            }
+        }
+        true
+    }
+
+    /// Powers every adapter on or off, recording one action per adapter
+    pub async fn power_all_adapters(&mut self, on: bool) -> Vec<(String, TaskStatus<()>)> {
+        let action = AdapterAction::SetPowered(on);
+        let results = match self.session() {
+            Ok(session) => power_all(session, on).await,
+            Err(e) => Err(e),
+        };
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => {
+                return vec![(
+                    "adapters".to_string(),
+                    TaskStatus::Error(friendly_message(&e)),
+                )]
+            }
+        };
+        let mut statuses = vec![];
+        for (name, res) in results {
+            let status = match res {
+                Ok(_) => TaskStatus::Done(()),
+                Err(e) => TaskStatus::Error(friendly_message(&e)),
+            };
+            let i = self.record_action(name.clone(), action.label());
+            let _ = self.finish_action(i, &status);
+            statuses.push((name, status));
+        }
+        self.update_adapters().await;
+        statuses
+    }
+
+    /// Plain text summary of the session, printed on quit for scripts
+    pub fn session_summary(&self) -> String {
+        let failed = self
+            .actions
+            .iter()
+            .filter(|a| matches!(a.outcome, ActionOutcome::Failed(_)))
+            .count();
+        [
+            format!("devices discovered: {}", self.found.len()),
+            format!("connections made: {}", self.connections),
+            format!("actions failed: {failed}"),
+        ]
+        .join("\n")
+    }
+    /// The actions still running with their index in the history, the pairing
+    /// being left out as it has its own countdown
+    pub fn running_actions(&self) -> impl Iterator<Item = (usize, &ActionRecord)> {
+        let device_record = self
+            .device_record
+            .filter(|_| self.running_pairing().is_none());
+        [self.adapter_record, device_record]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| self.actions.get(i).map(|record| (i, record)))
+    }
+    pub fn has_pending_tasks(&self) -> bool {
+        self.adapter_actions_ch.is_some()
+            || self.device_actions_ch.is_some()
+            || self.batch_ch.is_some()
+    }
+    pub fn last_device_action(&self) -> Option<(AdapterId, DeviceId, DeviceAction)> {
+        self.last_device_action
     }
 
     fn sort_adapters(&mut self) {
@@ -220,4 +1717,213 @@ impl Device {
     pub const BY_NAME: Sorter<Self> = Sorter(|a, b| a.alias.cmp(&b.alias));
     pub const BY_CONNECTED: Sorter<Self> = Sorter(|b, a| a.is_connected.cmp(&b.is_connected));
     pub const BY_BATTERY: Sorter<Self> = Sorter(|a, b| a.battery.cmp(&b.battery));
+    pub const BY_RSSI: Sorter<Self> = Sorter(|b, a| a.rssi.cmp(&b.rssi));
+}
+
+impl DeviceOrder {
+    pub fn sorter(&self) -> Sorter<Device> {
+        match self {
+            DeviceOrder::Address => Device::BY_ADDRESS,
+            DeviceOrder::Nearest => Device::BY_RSSI,
+        }
+    }
+}
+
+/// Powers every adapter on or off, returning the result for each adapter name
+pub async fn power_all(
+    session: &bluer::Session,
+    on: bool,
+) -> bluer::Result<Vec<(String, bluer::Result<()>)>> {
+    let mut results = vec![];
+    for name in session.adapter_names().await? {
+        let res = match session.adapter(&name) {
+            Ok(adapter) => adapter.set_powered(on).await,
+            Err(e) => Err(e),
+        };
+        results.push((name, res));
+    }
+    Ok(results)
+}
+
+/// Registers the agent bluez asks for the codes to type on keyboards being paired, and
+/// for the confirmations that `pairing.auto_accept` leaves to the user
+async fn register_agent(
+    session: &bluer::Session,
+) -> (
+    bluer::Result<AgentHandle>,
+    UnboundedReceiver<PinCode>,
+    UnboundedReceiver<PairingPrompt>,
+) {
+    let (s, r) = mpsc::unbounded_channel();
+    let passkey_s = s.clone();
+    let (prompt_s, prompt_r) = mpsc::unbounded_channel();
+    let (confirm_session, confirm_s) = (session.clone(), prompt_s.clone());
+    let (authorize_session, authorize_s) = (session.clone(), prompt_s);
+    let agent = Agent {
+        request_confirmation: Some(Box::new(move |req| {
+            let (session, s) = (confirm_session.clone(), confirm_s.clone());
+            Box::pin(confirm(
+                session,
+                s,
+                req.adapter,
+                req.device,
+                Some(req.passkey),
+            ))
+        })),
+        request_authorization: Some(Box::new(move |req| {
+            let (session, s) = (authorize_session.clone(), authorize_s.clone());
+            Box::pin(confirm(session, s, req.adapter, req.device, None))
+        })),
+        display_pin_code: Some(Box::new(move |req| {
+            let _ = s.send(PinCode {
+                device: DeviceId(req.device),
+                code: req.pincode,
+                entered: None,
+            });
+            Box::pin(async { Ok(()) })
+        })),
+        display_passkey: Some(Box::new(move |req| {
+            let _ = passkey_s.send(PinCode {
+                device: DeviceId(req.device),
+                code: format!("{:06}", req.passkey),
+                entered: Some(req.entered),
+            });
+            Box::pin(async { Ok(()) })
+        })),
+        ..Default::default()
+    };
+    (session.register_agent(agent).await, r, prompt_r)
+}
+
+async fn confirm(
+    session: bluer::Session,
+    prompts: UnboundedSender<PairingPrompt>,
+    adapter: String,
+    address: Address,
+    passkey: Option<u32>,
+) -> ReqResult<()> {
+    if CONFIG
+        .pairing
+        .auto_accepts(device_category(&session, &adapter, address).await)
+    {
+        return Ok(());
+    }
+    let (answer, rx) = oneshot::channel();
+    let _ = prompts.send(PairingPrompt {
+        device: DeviceId(address),
+        passkey,
+        answer,
+    });
+    match rx.await {
+        Ok(true) => Ok(()),
+        _ => Err(ReqError::Rejected),
+    }
+}
+
+async fn device_category(
+    session: &bluer::Session,
+    adapter: &str,
+    address: Address,
+) -> DeviceCategory {
+    let Ok(device) = session.adapter(adapter).and_then(|a| a.device(address)) else {
+        return DeviceCategory::Other;
+    };
+    let icon = device.icon().await.ok().flatten().unwrap_or_default();
+    DeviceCategory::decode(&icon, device.appearance().await.ok().flatten())
+}
+
+/// Discovery events of the adapter, for as long as the stream is kept
+pub async fn scan_events(
+    adapter: &bluer::Adapter,
+    scan_type: ScanType,
+) -> bluer::Result<BoxStream<'static, AdapterEvent>> {
+    match scan_type {
+        ScanType::Active => Ok(adapter.discover_devices().await?.boxed()),
+        // bluez scans passively only for advertisement monitors, which need a
+        // pattern, so match every value of the flags field
+        ScanType::Passive => {
+            let monitors = adapter.monitor().await.map_err(experimental_error)?;
+            let patterns = (0..=0x1f)
+                .map(|flags| Pattern {
+                    data_type: 0x01,
+                    start_position: 0,
+                    content: vec![flags],
+                })
+                .collect();
+            let monitor = Monitor {
+                patterns: Some(patterns),
+                ..Default::default()
+            };
+            let handle = monitors
+                .register(monitor)
+                .await
+                .map_err(experimental_error)?;
+            Ok(handle
+                .map(move |ev| {
+                    let _monitors = &monitors;
+                    match ev {
+                        MonitorEvent::DeviceFound(id) => AdapterEvent::DeviceAdded(id.device),
+                        MonitorEvent::DeviceLost(id) => AdapterEvent::DeviceRemoved(id.device),
+                    }
+                })
+                .boxed())
+        }
+    }
+}
+
+/// Forwards the events of the device until the returned sender fires or is dropped,
+/// shared by the monitor view and the command line
+pub async fn monitor_device(
+    device: bluer::Device,
+    capacity: usize,
+) -> bluer::Result<(broadcast::Receiver<DeviceEvent>, oneshot::Sender<()>)> {
+    let events = device.events().await?;
+    let (sx, rx) = broadcast::channel(capacity);
+    let (stop_sx, mut stop_rx) = oneshot::channel();
+    let _ = tokio::spawn(async move {
+        let mut events = Box::pin(events);
+        while let Some(ev) = events.next().await {
+            match stop_rx.try_recv() {
+                Ok(_) | Err(TryRecvError::Closed) => return,
+                Err(TryRecvError::Empty) => {
+                    if sx.send(ev).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    Ok((rx, stop_sx))
+}
+
+/// Runs the action on the device, shared by the views and the command line
+pub async fn apply_device_action(
+    adapter: &bluer::Adapter,
+    device: &bluer::Device,
+    action: DeviceAction,
+) -> bluer::Result<()> {
+    match action {
+        DeviceAction::SetConnected(true) => device.connect().await,
+        DeviceAction::SetConnected(false) => device.disconnect().await,
+        DeviceAction::SetPaired(true) => pair_with_timeout(device).await,
+        DeviceAction::SetPaired(false) => adapter.remove_device(device.address()).await,
+        DeviceAction::SetTrusted(val) => device.set_trusted(val).await,
+        DeviceAction::SetBlocked(val) => device.set_blocked(val).await,
+        DeviceAction::SetAutoConnect(_) | DeviceAction::Info => Ok(()),
+    }
+}
+
+/// Pairs with the device, cancelling the attempt after `pair_timeout` seconds
+async fn pair_with_timeout(device: &bluer::Device) -> bluer::Result<()> {
+    let timeout = Duration::from_secs(CONFIG.pair_timeout.into());
+    match tokio::time::timeout(timeout, device.pair()).await {
+        Ok(res) => res,
+        Err(_) => {
+            let _ = device.cancel_pairing().await;
+            Err(bluer::Error {
+                kind: bluer::ErrorKind::AuthenticationTimeout,
+                message: "Pairing timed out".to_string(),
+            })
+        }
+    }
 }
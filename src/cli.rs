@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bluer::Address;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use crate::config::ConfigSource;
+use crate::models::DeviceId;
+
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Deep link to open, e.g. `connect://AA:BB:CC:DD:EE:FF` or `bluerat://AA:BB:CC:DD:EE:FF`
+    pub uri: Option<DeepLink>,
+    /// Focus the device with this address on startup
+    #[arg(long)]
+    pub device: Option<Address>,
+    /// Connect to the focused device on startup
+    #[arg(long, requires = "device")]
+    pub connect: bool,
+    /// Draw a short UI below the prompt instead of taking over the screen
+    #[arg(long)]
+    pub inline: bool,
+    /// Print what happened during the session to stdout on quit
+    #[arg(long)]
+    pub summary: bool,
+    /// Read the configuration from this file instead of the default location
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Override the theme preset: default, light, monochrome or colorblind
+    #[arg(long, global = true)]
+    pub theme: Option<String>,
+}
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print a shell completion script to stdout
+    Completions { shell: Shell },
+    /// Block until the device connects or shows up in a scan, exiting with 1 on timeout
+    Wait {
+        device: Address,
+        /// Give up after this many seconds
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+    /// Print the known devices of every adapter, one per line
+    List {
+        /// Only print devices matching this filter, e.g. `type:audio state:connected`
+        #[arg(long)]
+        filter: Option<String>,
+    },
+    /// Power every adapter on or off, printing the result for each
+    Power { state: Switch },
+    /// Scan with the default adapter, printing each device as it is discovered
+    Scan {
+        /// Stop after this many seconds instead of scanning until interrupted
+        #[arg(long)]
+        duration: Option<u64>,
+        #[arg(long, value_enum, default_value_t = Format::Tsv)]
+        format: Format,
+    },
+    /// Print the rssi, battery and connection changes of the device until interrupted
+    Monitor {
+        device: Address,
+        #[arg(long, value_enum, default_value_t = Format::Tsv)]
+        format: Format,
+    },
+    /// Report the beacons of the `[tracker]` config as they come and go,
+    /// also publishing to the MQTT broker when one is set
+    Track,
+    /// Trust the devices on every adapter that knows them
+    Trust(Targets),
+    /// Block the devices on every adapter that knows them
+    Block(Targets),
+    /// Remove the devices, unpairing them, from every adapter that knows them
+    Remove(Targets),
+    /// Dump the cached devices, battery history and action log as JSON
+    ExportState {
+        /// Write to this file instead of stdout
+        path: Option<PathBuf>,
+    },
+    /// Replace the cached devices, battery history and action log with a dump from `export-state`
+    ImportState { path: PathBuf },
+}
+
+#[derive(Args)]
+pub struct Targets {
+    /// Addresses of the devices
+    #[arg(required_unless_present = "all_unpaired")]
+    pub addresses: Vec<Address>,
+    /// Act on every known device that is not paired
+    #[arg(long, conflicts_with = "addresses")]
+    pub all_unpaired: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// Tab separated fields, e.g. address, name, rssi and uuids for `scan`
+    Tsv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Switch {
+    On,
+    Off,
+}
+
+impl Cli {
+    pub fn config_source(&self) -> ConfigSource {
+        ConfigSource {
+            path: self.config.clone(),
+            theme: self.theme.clone(),
+        }
+    }
+    pub fn deep_link(&self) -> Option<DeepLink> {
+        match (&self.uri, self.device) {
+            (Some(link), _) => Some(link.clone()),
+            (None, Some(addr)) => Some(DeepLink {
+                device: DeviceId(addr),
+                connect: self.connect,
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DeepLink {
+    pub device: DeviceId,
+    pub connect: bool,
+}
+impl FromStr for DeepLink {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix("bluerat://").unwrap_or(s);
+        let (connect, addr) = match s
+            .strip_prefix("connect://")
+            .or_else(|| s.strip_prefix("connect/"))
+        {
+            Some(addr) => (true, addr),
+            None => (false, s.strip_prefix("device://").unwrap_or(s)),
+        };
+        let addr = addr.trim_end_matches('/');
+        Address::from_str(addr)
+            .map(|addr| Self {
+                device: DeviceId(addr),
+                connect,
+            })
+            .map_err(|_| format!("invalid device address in link: {addr}"))
+    }
+}
@@ -0,0 +1,520 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use bluer::{
+    AdapterEvent, Address, DeviceEvent, DeviceProperty, DiscoveryFilter, DiscoveryTransport,
+};
+use chrono::Local;
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::CommandFactory;
+use clap_complete::Shell;
+use futures::StreamExt;
+use itertools::Itertools;
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::bt_manager::{apply_device_action, monitor_device, power_all};
+use crate::cli::{Cli, Format, Switch, Targets};
+use crate::errors::friendly_message;
+use crate::filter::Filter;
+use crate::globals::{CONFIG, EVENT_QUEUE, PROJECT_NAME};
+use crate::models::{Adapter, Device, DeviceAction, DeviceId};
+use crate::mqtt::Publisher;
+use crate::state::{Export, State};
+use crate::tracker::BeaconTracker;
+
+pub async fn completions(shell: Shell) -> ExitCode {
+    // an adapter shares its devices with the others, clap refuses a value offered twice
+    let devices = State::load()
+        .adapters
+        .into_iter()
+        .flat_map(|a| a.devices)
+        .unique_by(|d| d.id)
+        .map(|d| PossibleValue::new(d.id.to_string()).help(d.alias))
+        .collect::<Vec<_>>();
+
+    let mut cmd = Cli::command();
+    if !devices.is_empty() {
+        cmd = complete_devices(cmd, &devices);
+    }
+    clap_complete::generate(shell, &mut cmd, PROJECT_NAME, &mut io::stdout());
+    ExitCode::SUCCESS
+}
+/// Offers the known devices for every device argument of the command and its subcommands
+fn complete_devices(cmd: clap::Command, devices: &[PossibleValue]) -> clap::Command {
+    cmd.mut_args(|arg| match arg.get_id().as_str() {
+        "device" | "addresses" => arg.value_parser(PossibleValuesParser::new(devices.to_vec())),
+        _ => arg,
+    })
+    .mut_subcommands(|sub| complete_devices(sub, devices))
+}
+
+pub async fn wait(device: Address, timeout: Option<u64>) -> ExitCode {
+    let found = match timeout {
+        Some(secs) => tokio::time::timeout(Duration::from_secs(secs), wait_for_device(device))
+            .await
+            .unwrap_or(false),
+        None => wait_for_device(device).await,
+    };
+    match found {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+pub async fn list(filter: Option<String>) -> ExitCode {
+    let filter = match filter.as_deref().map(Filter::parse).transpose() {
+        Ok(filter) => filter,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (session, names) = match open_adapters().await {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    for name in names {
+        let Ok(adapter) = session.adapter(&name) else {
+            continue;
+        };
+        for device in Adapter::from(adapter).await.devices {
+            if filter.as_ref().is_none_or(|f| f.matches(&device)) {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    device.id,
+                    device.alias,
+                    device.kind,
+                    device.states().join(",")
+                );
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub async fn power(state: Switch) -> ExitCode {
+    let session = match open_session().await {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let results = match power_all(&session, matches!(state, Switch::On)).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("{}", friendly_message(&e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut code = ExitCode::SUCCESS;
+    for (name, res) in results {
+        match res {
+            Ok(_) => println!("{name}\tok"),
+            Err(e) => {
+                println!("{name}\t{}", friendly_message(&e));
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    code
+}
+
+pub async fn device_action(targets: Targets, action: DeviceAction) -> ExitCode {
+    let (session, names) = match open_adapters().await {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut code = ExitCode::SUCCESS;
+    let mut missing = targets.addresses.clone();
+    for name in names {
+        let Ok(adapter) = session.adapter(&name) else {
+            continue;
+        };
+        let devices = Adapter::from(adapter.clone()).await.devices;
+        let chosen = devices.iter().filter(|d| match targets.all_unpaired {
+            true => !d.is_paired,
+            false => targets.addresses.contains(&d.id.0),
+        });
+        for device in chosen {
+            missing.retain(|a| *a != device.id.0);
+            let res = match adapter.device(device.id.0) {
+                Ok(actual) => apply_device_action(&adapter, &actual, action).await,
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(_) => println!("{}\t{name}\t{action}\tok", device.id),
+                Err(e) => {
+                    println!("{}\t{name}\t{action}\t{}", device.id, friendly_message(&e));
+                    code = ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+    for addr in missing {
+        println!("{}\t-\t{action}\tnot found", DeviceId(addr));
+        code = ExitCode::FAILURE;
+    }
+    code
+}
+
+/// A discovered device as printed by `scan`
+#[derive(Serialize)]
+struct ScanRecord<'a> {
+    address: String,
+    name: &'a str,
+    rssi: Option<i16>,
+    uuids: &'a [String],
+}
+
+pub async fn scan(duration: Option<u64>, format: Format) -> ExitCode {
+    let adapter = match open_adapter().await {
+        Ok(adapter) => adapter,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut events = match adapter.discover_devices().await {
+        Ok(events) => Box::pin(events),
+        Err(e) => {
+            eprintln!("{}", friendly_message(&e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let print = |device: &Device| match format {
+        Format::Tsv => println!(
+            "{}\t{}\t{}\t{}",
+            device.id,
+            device.alias,
+            device.rssi.map_or("-".to_string(), |r| r.to_string()),
+            device.uuids.join(",")
+        ),
+        Format::Ndjson => {
+            let record = ScanRecord {
+                address: device.id.to_string(),
+                name: &device.alias,
+                rssi: device.rssi,
+                uuids: &device.uuids,
+            };
+            if let Ok(line) = serde_json::to_string(&record) {
+                println!("{line}");
+            }
+        }
+    };
+    let scan = async {
+        while let Some(ev) = events.next().await {
+            if let AdapterEvent::DeviceAdded(addr) = ev
+                && let Ok(device) = adapter.device(addr)
+            {
+                let device = Device::from(device).await;
+                if device.id.is_allowed() {
+                    print(&device);
+                }
+            }
+        }
+    };
+    match duration {
+        Some(secs) => {
+            let _ = tokio::time::timeout(Duration::from_secs(secs), scan).await;
+        }
+        None => scan.await,
+    }
+    ExitCode::SUCCESS
+}
+
+/// A property change of the monitored device as printed by `monitor`
+#[derive(Serialize)]
+struct MonitorRecord {
+    time: String,
+    address: String,
+    property: &'static str,
+    value: serde_json::Value,
+}
+
+pub async fn monitor(device: Address, format: Format) -> ExitCode {
+    let (session, names) = match open_adapters().await {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut actual = None;
+    for name in names {
+        let Ok(adapter) = session.adapter(&name) else {
+            continue;
+        };
+        if adapter
+            .device_addresses()
+            .await
+            .is_ok_and(|addrs| addrs.contains(&device))
+            && let Ok(found) = adapter.device(device)
+        {
+            actual = Some((adapter, found));
+            break;
+        }
+    }
+    let Some((adapter, actual)) = actual else {
+        eprintln!("{} is not known to any adapter", DeviceId(device));
+        return ExitCode::FAILURE;
+    };
+    // bluez only reports the rssi of the devices seen by a running discovery
+    let mut discovery = match adapter.discover_devices().await {
+        Ok(discovery) => Box::pin(discovery),
+        Err(e) => {
+            eprintln!("{}", friendly_message(&e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let (mut events, _stop) = match monitor_device(actual, EVENT_QUEUE).await {
+        Ok(monitor) => monitor,
+        Err(e) => {
+            eprintln!("{}", friendly_message(&e));
+            return ExitCode::FAILURE;
+        }
+    };
+    loop {
+        let ev = tokio::select! {
+            ev = events.recv() => ev,
+            // polled only to keep the discovery going
+            Some(_) = discovery.next() => continue,
+        };
+        let prop = match ev {
+            Ok(DeviceEvent::PropertyChanged(prop)) => prop,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        let (property, value) = match prop {
+            DeviceProperty::Rssi(rssi) => ("rssi", serde_json::Value::from(rssi)),
+            DeviceProperty::BatteryPercentage(level) => ("battery", level.into()),
+            DeviceProperty::Connected(connected) => ("connected", connected.into()),
+            _ => continue,
+        };
+        let record = MonitorRecord {
+            time: Local::now().to_rfc3339(),
+            address: DeviceId(device).to_string(),
+            property,
+            value,
+        };
+        match format {
+            Format::Tsv => println!(
+                "{}\t{}\t{}\t{}",
+                record.time, record.address, record.property, record.value
+            ),
+            Format::Ndjson => {
+                if let Ok(line) = serde_json::to_string(&record) {
+                    println!("{line}");
+                }
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub async fn track() -> ExitCode {
+    if CONFIG.tracker.beacons.is_empty() {
+        eprintln!("No beacons to track, add them under [tracker] in the config");
+        return ExitCode::FAILURE;
+    }
+    let adapter = match open_adapter().await {
+        Ok(adapter) => adapter,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // every advertisement counts, not only the first one of each address
+    let filter = DiscoveryFilter {
+        transport: DiscoveryTransport::Le,
+        duplicate_data: true,
+        ..Default::default()
+    };
+    if let Err(e) = adapter.set_discovery_filter(filter).await {
+        eprintln!("{}", friendly_message(&e));
+        return ExitCode::FAILURE;
+    }
+    let mut events = match adapter.discover_devices_with_changes().await {
+        Ok(events) => Box::pin(events),
+        Err(e) => {
+            eprintln!("{}", friendly_message(&e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut mqtt = Publisher::start(&CONFIG.mqtt);
+    let mut tracker = BeaconTracker::new(&CONFIG.tracker.beacons, Instant::now());
+    let away_after = Duration::from_secs(CONFIG.tracker.away_after.into());
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        let reports = tokio::select! {
+            ev = events.next() => match ev {
+                Some(AdapterEvent::DeviceAdded(addr)) => tracker.seen(addr, Instant::now()),
+                Some(_) => continue,
+                None => break,
+            },
+            _ = tick.tick() => tracker.expire(away_after, Instant::now()),
+        };
+        for report in reports {
+            let address = report.address.map_or("-".to_string(), |a| a.to_string());
+            let (state, payload) = match report.present {
+                true => ("present", "home"),
+                false => ("away", "not_home"),
+            };
+            println!("{state}\t{}\t{address}", report.name);
+            if let Some(mqtt) = &mqtt {
+                let topic = CONFIG
+                    .tracker
+                    .topic
+                    .replace("{name}", &report.name)
+                    .replace("{address}", &address);
+                mqtt.publish(topic, payload.to_string());
+            }
+        }
+        if let Some(e) = mqtt.as_mut().and_then(Publisher::poll_error) {
+            eprintln!("{e}");
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub async fn export_state(path: Option<PathBuf>) -> ExitCode {
+    if !State::exists() {
+        eprintln!("No state to export yet");
+        return ExitCode::FAILURE;
+    }
+    let export = Export {
+        state: State::load(),
+        actions: State::action_log(),
+    };
+    let json = match serde_json::to_string_pretty(&export) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let res = match path {
+        Some(path) => fs::write(path, json + "\n"),
+        None => {
+            println!("{json}");
+            Ok(())
+        }
+    };
+    match res {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub async fn import_state(path: PathBuf) -> ExitCode {
+    let Export { state, actions } = match fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str::<Export>(&s).map_err(|e| e.to_string()))
+    {
+        Ok(export) => export,
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = state.save() {
+        eprintln!("{e}");
+        return ExitCode::FAILURE;
+    }
+    // an older dump without the log leaves the current one alone
+    if !actions.is_empty()
+        && let Err(e) = State::replace_action_log(&actions)
+    {
+        eprintln!("{e}");
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "Imported {} devices, the battery history of {} and {} logged actions",
+        state
+            .adapters
+            .iter()
+            .map(|a| a.devices.len())
+            .sum::<usize>(),
+        state.battery.len(),
+        actions.len()
+    );
+    ExitCode::SUCCESS
+}
+
+/// Connects to bluez, the error worded for the terminal
+async fn open_session() -> Result<bluer::Session, String> {
+    bluer::Session::new()
+        .await
+        .map_err(|e| friendly_message(&e))
+}
+/// Connects to bluez and lists the names of its adapters
+async fn open_adapters() -> Result<(bluer::Session, Vec<String>), String> {
+    let session = open_session().await?;
+    let names = session
+        .adapter_names()
+        .await
+        .map_err(|e| friendly_message(&e))?;
+    Ok((session, names))
+}
+/// Connects to bluez and opens its default adapter
+async fn open_adapter() -> Result<bluer::Adapter, String> {
+    let session = open_session().await?;
+    session
+        .default_adapter()
+        .await
+        .map_err(|e| friendly_message(&e))
+}
+
+async fn wait_for_device(addr: Address) -> bool {
+    let adapter = match open_adapter().await {
+        Ok(adapter) => adapter,
+        Err(e) => {
+            eprintln!("{e}");
+            return false;
+        }
+    };
+    let mut events = match adapter.discover_devices().await {
+        Ok(events) => Box::pin(events),
+        Err(e) => {
+            eprintln!("{}", friendly_message(&e));
+            return false;
+        }
+    };
+    let mut ticker = tokio::time::interval(Duration::from_millis(500));
+    loop {
+        tokio::select! {
+            Some(ev) = events.next() => {
+                if let AdapterEvent::DeviceAdded(a) = ev
+                    && a == addr
+                    && is_present(&adapter, addr).await
+                {
+                    return true;
+                }
+            }
+            _ = ticker.tick() => {
+                if is_present(&adapter, addr).await {
+                    return true;
+                }
+            }
+        }
+    }
+}
+
+async fn is_present(adapter: &bluer::Adapter, addr: Address) -> bool {
+    let Ok(device) = adapter.device(addr) else {
+        return false;
+    };
+    device.is_connected().await.unwrap_or(false) || device.rssi().await.ok().flatten().is_some()
+}
@@ -1,25 +1,64 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use std::{env, fs, io};
+
+use bluer::{DiscoveryFilter, DiscoveryTransport, Uuid};
+use chrono::format::{Item, StrftimeItems};
+use directories::ProjectDirs;
+use itertools::Itertools;
+use ratatui::style::Color;
 use serde::Deserialize;
 
+use crate::globals::{CONFIG_FILE, CONFIG_SOURCE, PROJECT_NAME};
+use crate::identity::parse_key;
+use crate::models::{DeviceCategory, DeviceOrder};
+
 #[derive(Deserialize, Default)]
 pub struct PartialTheme {
+    preset: Option<String>,
+
     fg_connected_color: Option<String>,
     fg_header_color: Option<String>,
     fg_selected_color: Option<String>,
     fg_normal_color: Option<String>,
     fg_new_device_color: Option<String>,
+    fg_changed_color: Option<String>,
+    fg_blocked_color: Option<String>,
+    fg_match_color: Option<String>,
+    bg_match_color: Option<String>,
 
     bg_connected_color: Option<String>,
     bg_header_color: Option<String>,
     bg_selected_color: Option<String>,
     bg_normal_color: Option<String>,
     bg_new_device_color: Option<String>,
+    bg_striped_color: Option<String>,
 
     column_spacing: Option<u16>,
     border_color: Option<String>,
+    focused_border_color: Option<String>,
     borders: Option<bool>,
     rounded_borders: Option<bool>,
     scrollbars: Option<bool>,
+    striped_rows: Option<bool>,
     date_format: Option<String>,
+    icons: Option<String>,
+
+    status_fg_color: Option<String>,
+    status_bg_color: Option<String>,
+    popup_fg_color: Option<String>,
+    popup_bg_color: Option<String>,
+    popup_border_color: Option<String>,
+
+    audio_color: Option<String>,
+    input_color: Option<String>,
+    phone_color: Option<String>,
+    sensor_color: Option<String>,
 }
 #[derive(Deserialize)]
 pub struct Theme {
@@ -28,41 +67,350 @@ pub struct Theme {
     pub fg_selected_color: String,
     pub fg_normal_color: String,
     pub fg_new_device_color: String,
+    pub fg_changed_color: String,
+    pub fg_blocked_color: String,
+    pub fg_match_color: String,
+    pub bg_match_color: String,
 
     pub bg_connected_color: String,
     pub bg_header_color: String,
     pub bg_selected_color: String,
     pub bg_normal_color: String,
     pub bg_new_device_color: String,
+    pub bg_striped_color: String,
 
     pub column_spacing: u16,
     pub border_color: String,
+    pub focused_border_color: String,
     pub borders: bool,
     pub rounded_borders: bool,
     pub scrollbars: bool,
+    pub striped_rows: bool,
     pub date_format: String,
+    pub icons: String,
+
+    pub status_fg_color: Option<String>,
+    pub status_bg_color: Option<String>,
+    pub popup_fg_color: String,
+    pub popup_bg_color: String,
+    pub popup_border_color: String,
+
+    pub audio_color: String,
+    pub input_color: String,
+    pub phone_color: String,
+    pub sensor_color: String,
+}
+pub const THEME_PRESETS: &[&str] = &["default", "light", "monochrome", "colorblind"];
+pub const ICON_SETS: &[&str] = &["none", "ascii", "unicode", "nerd"];
+pub const ADAPTER_COLUMN_NAMES: &[&str] = &["power", "name", "connections", "state", "address"];
+
+/// Calls the macro with the required colors of the theme, the one list of them for the
+/// env overrides and the color checks
+macro_rules! theme_colors {
+    ($apply:ident) => {
+        $apply!(
+            fg_connected_color,
+            fg_header_color,
+            fg_selected_color,
+            fg_normal_color,
+            fg_new_device_color,
+            fg_changed_color,
+            fg_blocked_color,
+            fg_match_color,
+            bg_match_color,
+            bg_connected_color,
+            bg_header_color,
+            bg_selected_color,
+            bg_normal_color,
+            bg_new_device_color,
+            bg_striped_color,
+            border_color,
+            focused_border_color,
+            popup_fg_color,
+            popup_bg_color,
+            popup_border_color,
+            audio_color,
+            input_color,
+            phone_color,
+            sensor_color
+        )
+    };
+}
+
+impl PartialTheme {
+    fn apply_env(&mut self) {
+        let theme = self;
+        macro_rules! override_from_env {
+            ($($field:ident),*) => {
+                $(
+                    let var = format!("{ENV_PREFIX}THEME_{}", stringify!($field).to_uppercase());
+                    theme.$field.override_from(&var);
+                )*
+            };
+        }
+        theme_colors!(override_from_env);
+        override_from_env!(
+            preset,
+            status_fg_color,
+            status_bg_color,
+            column_spacing,
+            borders,
+            rounded_borders,
+            scrollbars,
+            striped_rows,
+            date_format,
+            icons
+        );
+    }
+    fn preset(name: Option<&str>) -> Self {
+        let color = |c: &str| Some(c.to_string());
+        match name {
+            Some("light") => Self {
+                fg_connected_color: color("green"),
+                fg_header_color: color("blue"),
+                fg_selected_color: color("black"),
+                fg_normal_color: color("black"),
+                fg_new_device_color: color("magenta"),
+                fg_changed_color: color("red"),
+                fg_blocked_color: color("red"),
+                bg_connected_color: color("white"),
+                bg_header_color: color("white"),
+                bg_selected_color: color("lightblue"),
+                bg_normal_color: color("white"),
+                bg_new_device_color: color("white"),
+                bg_striped_color: color("#eeeeee"),
+                border_color: color("darkgray"),
+                focused_border_color: color("blue"),
+                popup_fg_color: color("black"),
+                popup_bg_color: color("white"),
+                popup_border_color: color("darkgray"),
+                audio_color: color("blue"),
+                input_color: color("red"),
+                phone_color: color("cyan"),
+                sensor_color: color("magenta"),
+                ..Default::default()
+            },
+            Some("monochrome") => Self {
+                fg_connected_color: color("white"),
+                fg_header_color: color("white"),
+                fg_selected_color: color("black"),
+                fg_normal_color: color("gray"),
+                fg_new_device_color: color("white"),
+                fg_changed_color: color("white"),
+                fg_blocked_color: color("white"),
+                fg_match_color: color("black"),
+                bg_match_color: color("white"),
+                bg_connected_color: color("black"),
+                bg_header_color: color("black"),
+                bg_selected_color: color("white"),
+                bg_normal_color: color("black"),
+                bg_new_device_color: color("black"),
+                bg_striped_color: color("#1c1c1c"),
+                border_color: color("gray"),
+                focused_border_color: color("white"),
+                popup_fg_color: color("white"),
+                popup_bg_color: color("black"),
+                popup_border_color: color("white"),
+                audio_color: color("gray"),
+                input_color: color("gray"),
+                phone_color: color("gray"),
+                sensor_color: color("gray"),
+                ..Default::default()
+            },
+            // Okabe-Ito palette, distinguishable with the common color vision deficiencies
+            Some("colorblind") => Self {
+                fg_connected_color: color("#56B4E9"),
+                fg_header_color: color("#F0E442"),
+                fg_selected_color: color("white"),
+                fg_normal_color: color("white"),
+                fg_new_device_color: color("#E69F00"),
+                fg_changed_color: color("#F0E442"),
+                fg_blocked_color: color("#D55E00"),
+                bg_match_color: color("#F0E442"),
+                bg_connected_color: color("black"),
+                bg_header_color: color("black"),
+                bg_selected_color: color("#0072B2"),
+                bg_normal_color: color("black"),
+                bg_new_device_color: color("black"),
+                border_color: color("#56B4E9"),
+                focused_border_color: color("#F0E442"),
+                popup_border_color: color("#E69F00"),
+                audio_color: color("#CC79A7"),
+                input_color: color("#F0E442"),
+                phone_color: color("#56B4E9"),
+                sensor_color: color("#D55E00"),
+                ..Default::default()
+            },
+            _ => Self::default(),
+        }
+    }
 }
 impl From<PartialTheme> for Theme {
     fn from(val: PartialTheme) -> Self {
+        let preset = PartialTheme::preset(val.preset.as_deref());
         Self {
-            fg_connected_color: val.fg_connected_color.unwrap_or("lightgreen".to_string()),
-            fg_header_color: val.fg_header_color.unwrap_or("cyan".to_string()),
-            fg_selected_color: val.fg_selected_color.unwrap_or("white".to_string()),
-            fg_normal_color: val.fg_normal_color.unwrap_or("white".to_string()),
-            fg_new_device_color: val.fg_new_device_color.unwrap_or("yellow".to_string()),
-
-            bg_connected_color: val.bg_connected_color.unwrap_or("black".to_string()),
-            bg_header_color: val.bg_header_color.unwrap_or("black".to_string()),
-            bg_selected_color: val.bg_selected_color.unwrap_or("darkgray".to_string()),
-            bg_normal_color: val.bg_normal_color.unwrap_or("black".to_string()),
-            bg_new_device_color: val.bg_new_device_color.unwrap_or("black".to_string()),
-
-            border_color: val.border_color.unwrap_or("blue".to_string()),
+            fg_connected_color: val
+                .fg_connected_color
+                .or(preset.fg_connected_color)
+                .unwrap_or("lightgreen".to_string()),
+            fg_header_color: val
+                .fg_header_color
+                .or(preset.fg_header_color)
+                .unwrap_or("cyan".to_string()),
+            fg_selected_color: val
+                .fg_selected_color
+                .or(preset.fg_selected_color)
+                .unwrap_or("white".to_string()),
+            fg_normal_color: val
+                .fg_normal_color
+                .or(preset.fg_normal_color)
+                .unwrap_or("white".to_string()),
+            fg_new_device_color: val
+                .fg_new_device_color
+                .or(preset.fg_new_device_color)
+                .unwrap_or("yellow".to_string()),
+            fg_changed_color: val
+                .fg_changed_color
+                .or(preset.fg_changed_color)
+                .unwrap_or("yellow".to_string()),
+            fg_blocked_color: val
+                .fg_blocked_color
+                .or(preset.fg_blocked_color)
+                .unwrap_or("red".to_string()),
+            fg_match_color: val
+                .fg_match_color
+                .or(preset.fg_match_color)
+                .unwrap_or("black".to_string()),
+            bg_match_color: val
+                .bg_match_color
+                .or(preset.bg_match_color)
+                .unwrap_or("yellow".to_string()),
+
+            bg_connected_color: val
+                .bg_connected_color
+                .or(preset.bg_connected_color)
+                .unwrap_or("black".to_string()),
+            bg_header_color: val
+                .bg_header_color
+                .or(preset.bg_header_color)
+                .unwrap_or("black".to_string()),
+            bg_selected_color: val
+                .bg_selected_color
+                .or(preset.bg_selected_color)
+                .unwrap_or("darkgray".to_string()),
+            bg_normal_color: val
+                .bg_normal_color
+                .or(preset.bg_normal_color)
+                .unwrap_or("black".to_string()),
+            bg_new_device_color: val
+                .bg_new_device_color
+                .or(preset.bg_new_device_color)
+                .unwrap_or("black".to_string()),
+            bg_striped_color: val
+                .bg_striped_color
+                .or(preset.bg_striped_color)
+                .unwrap_or("#1c1c1c".to_string()),
+
+            border_color: val
+                .border_color
+                .or(preset.border_color)
+                .unwrap_or("blue".to_string()),
+            focused_border_color: val
+                .focused_border_color
+                .or(preset.focused_border_color)
+                .unwrap_or("yellow".to_string()),
             borders: val.borders.unwrap_or(true),
             rounded_borders: val.rounded_borders.unwrap_or(false),
             date_format: val.date_format.unwrap_or_else(|| "%Y-%m-%d".to_string()),
+            icons: val.icons.unwrap_or_else(|| "none".to_string()),
             scrollbars: val.scrollbars.unwrap_or(false),
+            striped_rows: val.striped_rows.unwrap_or(false),
             column_spacing: val.column_spacing.unwrap_or(4),
+
+            status_fg_color: val.status_fg_color.or(preset.status_fg_color),
+            status_bg_color: val.status_bg_color.or(preset.status_bg_color),
+            popup_fg_color: val
+                .popup_fg_color
+                .or(preset.popup_fg_color)
+                .unwrap_or("white".to_string()),
+            popup_bg_color: val
+                .popup_bg_color
+                .or(preset.popup_bg_color)
+                .unwrap_or("black".to_string()),
+            popup_border_color: val
+                .popup_border_color
+                .or(preset.popup_border_color)
+                .unwrap_or("blue".to_string()),
+
+            audio_color: val
+                .audio_color
+                .or(preset.audio_color)
+                .unwrap_or("cyan".to_string()),
+            input_color: val
+                .input_color
+                .or(preset.input_color)
+                .unwrap_or("yellow".to_string()),
+            phone_color: val
+                .phone_color
+                .or(preset.phone_color)
+                .unwrap_or("lightblue".to_string()),
+            sensor_color: val
+                .sensor_color
+                .or(preset.sensor_color)
+                .unwrap_or("lightmagenta".to_string()),
+        }
+    }
+}
+impl Theme {
+    fn validate(&mut self, preset: Option<String>, errors: &mut Vec<String>) {
+        let fallback = Theme::from(PartialTheme {
+            preset,
+            ..Default::default()
+        });
+        let theme = &mut *self;
+        macro_rules! check_colors {
+            ($($field:ident),*) => {
+                $(
+                    if Color::from_str(&theme.$field).is_err() {
+                        errors.push(format!(
+                            "theme.{} = {:?} is not a color, using {:?}",
+                            stringify!($field),
+                            theme.$field,
+                            fallback.$field
+                        ));
+                        theme.$field = fallback.$field;
+                    }
+                )*
+            };
+        }
+        theme_colors!(check_colors);
+        let optional_colors = [
+            ("status_fg_color", &mut self.status_fg_color),
+            ("status_bg_color", &mut self.status_bg_color),
+        ];
+        for (name, value) in optional_colors {
+            if let Some(color) = value
+                && Color::from_str(color).is_err()
+            {
+                errors.push(format!(
+                    "theme.{name} = {color:?} is not a color, using the default"
+                ));
+                *value = None;
+            }
+        }
+        if StrftimeItems::new(&self.date_format).any(|i| i == Item::Error) {
+            errors.push(format!(
+                "theme.date_format = {:?} is not a valid format, using {:?}",
+                self.date_format, fallback.date_format
+            ));
+            self.date_format = fallback.date_format;
+        }
+        if !ICON_SETS.contains(&self.icons.as_str()) {
+            errors.push(format!(
+                "theme.icons = {:?} is unknown, using {:?}",
+                self.icons, fallback.icons
+            ));
+            self.icons = fallback.icons;
         }
     }
 }
@@ -72,18 +420,1140 @@ impl Default for Theme {
     }
 }
 
+#[derive(Deserialize, Default)]
+pub struct PartialProximity {
+    device: Option<String>,
+    leave_rssi: Option<i16>,
+    return_rssi: Option<i16>,
+    leave_after: Option<u16>,
+    on_leave: Option<String>,
+    on_return: Option<String>,
+}
+impl PartialProximity {
+    fn apply_env(&mut self) {
+        let fields: [(&str, &mut dyn EnvOverride); 6] = [
+            ("device", &mut self.device),
+            ("leave_rssi", &mut self.leave_rssi),
+            ("return_rssi", &mut self.return_rssi),
+            ("leave_after", &mut self.leave_after),
+            ("on_leave", &mut self.on_leave),
+            ("on_return", &mut self.on_return),
+        ];
+        for (name, field) in fields {
+            field.override_from(&format!("{ENV_PREFIX}PROXIMITY_{}", name.to_uppercase()));
+        }
+    }
+}
+#[derive(Deserialize, Default)]
+pub struct Proximity {
+    pub device: Option<String>,
+    pub leave_rssi: i16,
+    pub return_rssi: i16,
+    /// Seconds without a signal from the disconnected device before it counts as away
+    pub leave_after: u16,
+    pub on_leave: Option<String>,
+    pub on_return: Option<String>,
+}
+impl Proximity {
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        if let Some(device) = &self.device
+            && bluer::Address::from_str(device).is_err()
+        {
+            errors.push(format!(
+                "proximity.device = {device:?} is not a bluetooth address, ignoring it"
+            ));
+            self.device = None;
+        }
+        if self.return_rssi <= self.leave_rssi {
+            errors.push(format!(
+                "proximity.return_rssi must be above leave_rssi ({}), using {}",
+                self.leave_rssi,
+                self.leave_rssi + 10
+            ));
+            self.return_rssi = self.leave_rssi + 10;
+        }
+    }
+}
+impl From<PartialProximity> for Proximity {
+    fn from(val: PartialProximity) -> Self {
+        Self {
+            device: val.device,
+            leave_rssi: val.leave_rssi.unwrap_or(-80),
+            return_rssi: val.return_rssi.unwrap_or(-65),
+            leave_after: val.leave_after.unwrap_or(30),
+            on_leave: val.on_leave,
+            on_return: val.on_return,
+        }
+    }
+}
+
+pub const ALERT_EVENTS: &[&str] = &["new_device", "pairing", "connection_lost"];
+
+#[derive(Deserialize, Default)]
+pub struct PartialAlerts {
+    bell: Option<bool>,
+    command: Option<String>,
+    events: Option<Vec<String>>,
+}
+impl PartialAlerts {
+    fn apply_env(&mut self) {
+        self.bell.override_from(&format!("{ENV_PREFIX}ALERTS_BELL"));
+        self.command
+            .override_from(&format!("{ENV_PREFIX}ALERTS_COMMAND"));
+        self.events
+            .override_from(&format!("{ENV_PREFIX}ALERTS_EVENTS"));
+    }
+}
+#[derive(Deserialize, Default)]
+pub struct Alerts {
+    pub bell: bool,
+    pub command: Option<String>,
+    pub events: Vec<String>,
+}
+impl Alerts {
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        for event in &self.events {
+            if !ALERT_EVENTS.contains(&event.as_str()) {
+                errors.push(format!(
+                    "alerts.events: {event:?} is unknown, expected one of {}",
+                    ALERT_EVENTS.join(", ")
+                ));
+            }
+        }
+        self.events.retain(|e| ALERT_EVENTS.contains(&e.as_str()));
+    }
+    pub fn wants(&self, event: &str) -> bool {
+        (self.bell || self.command.is_some()) && self.events.iter().any(|e| e == event)
+    }
+}
+impl From<PartialAlerts> for Alerts {
+    fn from(val: PartialAlerts) -> Self {
+        Self {
+            bell: val.bell.unwrap_or(false),
+            command: val.command,
+            events: val
+                .events
+                .unwrap_or_else(|| ALERT_EVENTS.iter().map(|e| e.to_string()).collect()),
+        }
+    }
+}
+
+pub const PAIRING_CATEGORIES: &[&str] = &["audio", "input", "phone", "sensor", "other"];
+
+#[derive(Deserialize, Default)]
+pub struct PartialPairing {
+    auto_accept: Option<Vec<String>>,
+}
+impl PartialPairing {
+    fn apply_env(&mut self) {
+        self.auto_accept
+            .override_from(&format!("{ENV_PREFIX}PAIRING_AUTO_ACCEPT"));
+    }
+}
+#[derive(Deserialize, Default)]
+pub struct Pairing {
+    /// Device categories whose confirmation and just-works requests are accepted without asking
+    pub auto_accept: Vec<String>,
+}
+impl Pairing {
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        for category in &self.auto_accept {
+            if category.parse::<DeviceCategory>().is_err() {
+                errors.push(format!(
+                    "pairing.auto_accept: {category:?} is unknown, expected one of {}",
+                    PAIRING_CATEGORIES.join(", ")
+                ));
+            }
+        }
+        self.auto_accept
+            .retain(|c| c.parse::<DeviceCategory>().is_ok());
+    }
+    pub fn auto_accepts(&self, category: DeviceCategory) -> bool {
+        self.auto_accept
+            .iter()
+            .any(|c| c.parse::<DeviceCategory>() == Ok(category))
+    }
+}
+impl From<PartialPairing> for Pairing {
+    fn from(val: PartialPairing) -> Self {
+        Self {
+            auto_accept: val
+                .auto_accept
+                .unwrap_or_else(|| vec!["sensor".to_string()]),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct PartialMqtt {
+    broker: Option<String>,
+    client_id: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    presence_topic: Option<String>,
+    battery_topic: Option<String>,
+}
+impl PartialMqtt {
+    fn apply_env(&mut self) {
+        let fields: [(&str, &mut dyn EnvOverride); 6] = [
+            ("broker", &mut self.broker),
+            ("client_id", &mut self.client_id),
+            ("username", &mut self.username),
+            ("password", &mut self.password),
+            ("presence_topic", &mut self.presence_topic),
+            ("battery_topic", &mut self.battery_topic),
+        ];
+        for (name, field) in fields {
+            field.override_from(&format!("{ENV_PREFIX}MQTT_{}", name.to_uppercase()));
+        }
+    }
+}
+#[derive(Deserialize, Default)]
+pub struct Mqtt {
+    /// `host:port` of the broker, publishing is off without it
+    pub broker: Option<String>,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Topic templates, `{address}`, `{name}` and `{adapter}` are substituted
+    pub presence_topic: String,
+    pub battery_topic: String,
+}
+impl Mqtt {
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        if let Some(broker) = &self.broker
+            && broker
+                .rsplit_once(':')
+                .is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err())
+        {
+            errors.push(format!(
+                "mqtt.broker = {broker:?} is not a host:port, not publishing"
+            ));
+            self.broker = None;
+        }
+        if self.password.is_some() && self.username.is_none() {
+            errors.push("mqtt.password is set without a username, ignoring it".to_string());
+            self.password = None;
+        }
+    }
+}
+impl From<PartialMqtt> for Mqtt {
+    fn from(val: PartialMqtt) -> Self {
+        Self {
+            broker: val.broker,
+            client_id: val.client_id.unwrap_or(PROJECT_NAME.to_string()),
+            username: val.username,
+            password: val.password,
+            presence_topic: val
+                .presence_topic
+                .unwrap_or("bluerat/{address}/presence".to_string()),
+            battery_topic: val
+                .battery_topic
+                .unwrap_or("bluerat/{address}/battery".to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct BeaconEntry {
+    name: String,
+    address: Option<String>,
+    irk: Option<String>,
+}
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum BeaconKey {
+    Address(bluer::Address),
+    /// Identity resolving key, most significant byte first
+    Irk([u8; 16]),
+}
+#[derive(Deserialize, Clone, Debug)]
+pub struct Beacon {
+    pub name: String,
+    pub key: BeaconKey,
+}
+impl TryFrom<BeaconEntry> for Beacon {
+    type Error = String;
+    fn try_from(entry: BeaconEntry) -> Result<Self, Self::Error> {
+        let key = match (&entry.address, &entry.irk) {
+            (Some(address), None) => bluer::Address::from_str(address)
+                .map(BeaconKey::Address)
+                .map_err(|_| format!("{address:?} is not a bluetooth address"))?,
+            (None, Some(irk)) => {
+                BeaconKey::Irk(parse_key(irk).ok_or(format!("{irk:?} is not 32 hex digits"))?)
+            }
+            _ => return Err("expected either an address or an irk".to_string()),
+        };
+        Ok(Self {
+            name: entry.name,
+            key,
+        })
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct PartialTracker {
+    away_after: Option<u16>,
+    topic: Option<String>,
+    beacons: Option<Vec<BeaconEntry>>,
+}
+impl PartialTracker {
+    fn apply_env(&mut self) {
+        self.away_after
+            .override_from(&format!("{ENV_PREFIX}TRACKER_AWAY_AFTER"));
+        self.topic
+            .override_from(&format!("{ENV_PREFIX}TRACKER_TOPIC"));
+    }
+    fn validate(&self, errors: &mut Vec<String>) {
+        for entry in self.beacons.iter().flatten() {
+            if let Err(e) = Beacon::try_from(entry.clone()) {
+                errors.push(format!("tracker.beacons: {}: {e}, ignoring it", entry.name));
+            }
+        }
+    }
+}
+#[derive(Deserialize, Default)]
+pub struct Tracker {
+    /// Seconds without an advertisement before a beacon is reported away
+    pub away_after: u16,
+    /// Topic template, `{name}` and `{address}` are substituted
+    pub topic: String,
+    pub beacons: Vec<Beacon>,
+}
+impl Tracker {
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        if self.away_after == 0 {
+            errors.push("tracker.away_after = 0 is too short, using 120".to_string());
+            self.away_after = 120;
+        }
+    }
+}
+impl From<PartialTracker> for Tracker {
+    fn from(val: PartialTracker) -> Self {
+        Self {
+            away_after: val.away_after.unwrap_or(120),
+            topic: val.topic.unwrap_or("bluerat/tracker/{name}".to_string()),
+            beacons: val
+                .beacons
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|b| Beacon::try_from(b).ok())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum ScanType {
+    #[default]
+    Active,
+    /// Only listens for advertisements, sending no scan requests
+    Passive,
+}
+impl ScanType {
+    pub fn toggled(self) -> Self {
+        match self {
+            ScanType::Active => ScanType::Passive,
+            ScanType::Passive => ScanType::Active,
+        }
+    }
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "active" => Some(ScanType::Active),
+            "passive" => Some(ScanType::Passive),
+            _ => None,
+        }
+    }
+}
+impl Display for ScanType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanType::Active => write!(f, "active"),
+            ScanType::Passive => write!(f, "passive"),
+        }
+    }
+}
+
+/// An `[adapter."hci0"]` section, keyed by adapter name or address
+#[derive(Deserialize, Default, Clone)]
+pub struct PartialAdapterSection {
+    auto_power: Option<bool>,
+    discoverable_timeout: Option<u32>,
+    idle_power_off: Option<u16>,
+    sort: Option<String>,
+    scan_transport: Option<String>,
+    scan_rssi: Option<i16>,
+    scan_uuids: Option<Vec<String>>,
+}
+impl PartialAdapterSection {
+    fn validate(&self, key: &str, errors: &mut Vec<String>) {
+        if let Some(sort) = &self.sort
+            && parse_order(sort).is_none()
+        {
+            errors.push(format!(
+                "adapter.{key:?}.sort = {sort:?} is unknown, expected address or nearest"
+            ));
+        }
+        if let Some(transport) = &self.scan_transport
+            && parse_transport(transport).is_none()
+        {
+            errors.push(format!(
+                "adapter.{key:?}.scan_transport = {transport:?} is not auto, le or bredr"
+            ));
+        }
+        for uuid in self.scan_uuids.iter().flatten() {
+            if Uuid::parse_str(uuid).is_err() {
+                errors.push(format!(
+                    "adapter.{key:?}.scan_uuids: {uuid:?} is not a uuid, ignoring it"
+                ));
+            }
+        }
+    }
+}
+/// Defaults of one adapter, merged over the global options
+#[derive(Default, Clone, Debug)]
+pub struct AdapterSection {
+    /// Powered on when bluerat starts or the adapter shows up
+    pub auto_power: bool,
+    /// Seconds the adapter stays discoverable, 0 for ever
+    pub discoverable_timeout: Option<u32>,
+    /// Overrides `idle_power_off`
+    pub idle_power_off: Option<u16>,
+    pub sort: Option<DeviceOrder>,
+    /// `None` when the section sets none of the scan options
+    pub scan_filter: Option<DiscoveryFilter>,
+}
+impl From<PartialAdapterSection> for AdapterSection {
+    fn from(val: PartialAdapterSection) -> Self {
+        let has_filter =
+            val.scan_transport.is_some() || val.scan_rssi.is_some() || val.scan_uuids.is_some();
+        Self {
+            auto_power: val.auto_power.unwrap_or(false),
+            discoverable_timeout: val.discoverable_timeout,
+            idle_power_off: val.idle_power_off,
+            sort: val.sort.as_deref().and_then(parse_order),
+            scan_filter: has_filter.then(|| DiscoveryFilter {
+                uuids: val
+                    .scan_uuids
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|u| Uuid::parse_str(u).ok())
+                    .collect(),
+                rssi: val.scan_rssi,
+                transport: val
+                    .scan_transport
+                    .as_deref()
+                    .and_then(parse_transport)
+                    .unwrap_or(DiscoveryTransport::Auto),
+                ..Default::default()
+            }),
+        }
+    }
+}
+fn parse_order(name: &str) -> Option<DeviceOrder> {
+    match name {
+        "address" => Some(DeviceOrder::Address),
+        "nearest" => Some(DeviceOrder::Nearest),
+        _ => None,
+    }
+}
+fn parse_transport(name: &str) -> Option<DiscoveryTransport> {
+    match name {
+        "auto" => Some(DiscoveryTransport::Auto),
+        "le" => Some(DiscoveryTransport::Le),
+        "bredr" => Some(DiscoveryTransport::BrEdr),
+        _ => None,
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum StaleAction {
+    #[default]
+    Hide,
+    /// Also removes the device from bluez, forgetting it until it is discovered again
+    Remove,
+}
+impl StaleAction {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "hide" => Some(StaleAction::Hide),
+            "remove" => Some(StaleAction::Remove),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Deserialize, Default)]
 pub struct PartialConfig {
     theme: Option<PartialTheme>,
+    proximity: Option<PartialProximity>,
+    alerts: Option<PartialAlerts>,
+    pairing: Option<PartialPairing>,
+    mqtt: Option<PartialMqtt>,
+    tracker: Option<PartialTracker>,
+    adapter_columns: Option<Vec<String>>,
+    persist_actions: Option<bool>,
+    auto_block: Option<Vec<String>>,
+    allowed_devices: Option<Vec<String>>,
+    scan_on_startup: Option<bool>,
+    scan_type: Option<String>,
+    stop_scan_on_connect: Option<bool>,
+    resume_scan: Option<bool>,
+    auto_reconnect: Option<bool>,
+    reconnect_delay: Option<u16>,
+    pair_timeout: Option<u16>,
+    default_adapter: Option<String>,
+    idle_power_off: Option<u16>,
+    adapter: Option<HashMap<String, PartialAdapterSection>>,
+    quick_devices: Option<HashMap<String, String>>,
+    scan_every: Option<u16>,
+    scan_for: Option<u16>,
+    stale_after: Option<u16>,
+    stale_action: Option<String>,
+    gatt_poll_every: Option<u16>,
+    double_click: Option<u16>,
+    metrics_listen: Option<String>,
+    language: Option<String>,
+}
+impl PartialConfig {
+    fn apply_env(&mut self) {
+        self.adapter_columns
+            .override_from(&format!("{ENV_PREFIX}ADAPTER_COLUMNS"));
+        self.persist_actions
+            .override_from(&format!("{ENV_PREFIX}PERSIST_ACTIONS"));
+        self.auto_block
+            .override_from(&format!("{ENV_PREFIX}AUTO_BLOCK"));
+        self.allowed_devices
+            .override_from(&format!("{ENV_PREFIX}ALLOWED_DEVICES"));
+        self.scan_on_startup
+            .override_from(&format!("{ENV_PREFIX}SCAN_ON_STARTUP"));
+        self.scan_type
+            .override_from(&format!("{ENV_PREFIX}SCAN_TYPE"));
+        self.stop_scan_on_connect
+            .override_from(&format!("{ENV_PREFIX}STOP_SCAN_ON_CONNECT"));
+        self.resume_scan
+            .override_from(&format!("{ENV_PREFIX}RESUME_SCAN"));
+        self.auto_reconnect
+            .override_from(&format!("{ENV_PREFIX}AUTO_RECONNECT"));
+        self.reconnect_delay
+            .override_from(&format!("{ENV_PREFIX}RECONNECT_DELAY"));
+        self.pair_timeout
+            .override_from(&format!("{ENV_PREFIX}PAIR_TIMEOUT"));
+        self.default_adapter
+            .override_from(&format!("{ENV_PREFIX}DEFAULT_ADAPTER"));
+        self.idle_power_off
+            .override_from(&format!("{ENV_PREFIX}IDLE_POWER_OFF"));
+        self.scan_every
+            .override_from(&format!("{ENV_PREFIX}SCAN_EVERY"));
+        self.scan_for
+            .override_from(&format!("{ENV_PREFIX}SCAN_FOR"));
+        self.stale_after
+            .override_from(&format!("{ENV_PREFIX}STALE_AFTER"));
+        self.stale_action
+            .override_from(&format!("{ENV_PREFIX}STALE_ACTION"));
+        self.gatt_poll_every
+            .override_from(&format!("{ENV_PREFIX}GATT_POLL_EVERY"));
+        self.double_click
+            .override_from(&format!("{ENV_PREFIX}DOUBLE_CLICK"));
+        self.metrics_listen
+            .override_from(&format!("{ENV_PREFIX}METRICS_LISTEN"));
+        self.language
+            .override_from(&format!("{ENV_PREFIX}LANGUAGE"));
+        self.theme.get_or_insert_with(Default::default).apply_env();
+        self.proximity
+            .get_or_insert_with(Default::default)
+            .apply_env();
+        self.alerts.get_or_insert_with(Default::default).apply_env();
+        self.pairing
+            .get_or_insert_with(Default::default)
+            .apply_env();
+        self.mqtt.get_or_insert_with(Default::default).apply_env();
+        self.tracker
+            .get_or_insert_with(Default::default)
+            .apply_env();
+    }
 }
 #[derive(Deserialize, Default)]
 pub struct Config {
     pub theme: Theme,
+    pub proximity: Proximity,
+    pub alerts: Alerts,
+    pub pairing: Pairing,
+    pub mqtt: Mqtt,
+    pub tracker: Tracker,
+    pub adapter_columns: Vec<String>,
+    pub persist_actions: bool,
+    pub auto_block: Vec<String>,
+    pub allowed_devices: Vec<String>,
+    pub scan_on_startup: bool,
+    pub scan_type: ScanType,
+    pub stop_scan_on_connect: bool,
+    pub resume_scan: bool,
+    pub auto_reconnect: bool,
+    pub reconnect_delay: u16,
+    pub pair_timeout: u16,
+    /// Name or address of the adapter opened on startup
+    pub default_adapter: Option<String>,
+    /// Minutes an adapter may stay idle before it is powered off, 0 to never
+    pub idle_power_off: u16,
+    /// Per adapter name or address sections
+    #[serde(skip)]
+    pub adapters: HashMap<String, AdapterSection>,
+    /// Number keys toggling the connection of a device by name or address, ordered by key
+    pub quick_devices: Vec<(char, String)>,
+    /// Minutes between the background scans, 0 to never
+    pub scan_every: u16,
+    /// Seconds each background scan lasts
+    pub scan_for: u16,
+    /// Days an unpaired device may go unseen before it is dropped, 0 to never
+    pub stale_after: u16,
+    pub stale_action: StaleAction,
+    /// Seconds between the reads of a polled characteristic
+    pub gatt_poll_every: u16,
+    /// Milliseconds within which a second click on a row opens it
+    pub double_click: u16,
+    /// Address the prometheus `/metrics` endpoint listens on
+    pub metrics_listen: Option<SocketAddr>,
+    /// Language of the labels, from the locale of the environment when unset
+    pub language: Option<String>,
+    #[serde(skip)]
+    pub errors: Vec<String>,
+}
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", PROJECT_NAME).map(|dirs| dirs.config_dir().join(CONFIG_FILE))
+    }
+    /// The file the config is read from
+    pub fn path() -> Option<PathBuf> {
+        CONFIG_SOURCE
+            .get()
+            .and_then(|s| s.path.clone())
+            .or_else(Self::default_path)
+    }
+    pub fn load(source: &ConfigSource) -> Self {
+        let mut errors = vec![];
+        let mut partial = source
+            .path
+            .clone()
+            .or_else(Self::default_path)
+            .and_then(|p| fs::read_to_string(p).ok())
+            .map(|s| Self::parse_lenient(s, &mut errors))
+            .unwrap_or_default();
+        partial.apply_env();
+        if let Some(preset) = &source.theme {
+            partial.theme.get_or_insert_with(Default::default).preset = Some(preset.clone());
+        }
+
+        let mut preset = partial.theme.as_ref().and_then(|t| t.preset.clone());
+        if let Some(name) = &preset
+            && !THEME_PRESETS.contains(&name.as_str())
+        {
+            errors.push(format!(
+                "theme.preset = {name:?} is unknown, using \"default\""
+            ));
+            preset = None;
+        }
+        if let Some(listen) = &partial.metrics_listen
+            && listen.parse::<SocketAddr>().is_err()
+        {
+            errors.push(format!(
+                "metrics_listen = {listen:?} is not a socket address, not serving metrics"
+            ));
+        }
+        if let Some(scan_type) = &partial.scan_type
+            && ScanType::parse(scan_type).is_none()
+        {
+            errors.push(format!(
+                "scan_type = {scan_type:?} is unknown, expected active or passive"
+            ));
+        }
+        if let Some(action) = &partial.stale_action
+            && StaleAction::parse(action).is_none()
+        {
+            errors.push(format!(
+                "stale_action = {action:?} is unknown, expected hide or remove"
+            ));
+        }
+        if let Some(tracker) = &partial.tracker {
+            tracker.validate(&mut errors);
+        }
+        for (key, section) in partial.adapter.iter().flatten() {
+            section.validate(key, &mut errors);
+        }
+        for key in partial.quick_devices.iter().flat_map(|q| q.keys()) {
+            if quick_key(key).is_none() {
+                errors.push(format!(
+                    "quick_devices: {key:?} is not a number from 1 to 9, ignoring it"
+                ));
+            }
+        }
+        let mut config = Self::from(partial);
+        config.theme.validate(preset, &mut errors);
+        config.proximity.validate(&mut errors);
+        config.alerts.validate(&mut errors);
+        config.pairing.validate(&mut errors);
+        config.mqtt.validate(&mut errors);
+        config.tracker.validate(&mut errors);
+        config.validate_columns(&mut errors);
+        config.validate_address_patterns(&mut errors);
+        if config.pair_timeout == 0 {
+            errors.push("pair_timeout = 0 is too short, using 30".to_string());
+            config.pair_timeout = 30;
+        }
+        if config.scan_for == 0 {
+            errors.push("scan_for = 0 is too short, using 10".to_string());
+            config.scan_for = 10;
+        }
+        if config.gatt_poll_every == 0 {
+            errors.push("gatt_poll_every = 0 is too short, using 2".to_string());
+            config.gatt_poll_every = 2;
+        }
+        if config.double_click == 0 {
+            errors.push("double_click = 0 is too short, using 400".to_string());
+            config.double_click = 400;
+        }
+        config.errors = errors;
+        config
+    }
+    /// The section of the adapter, empty when it has none, a section keyed by address wins over one
+    /// keyed by name
+    pub fn adapter(&self, name: &str, address: &str) -> AdapterSection {
+        self.adapters
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(address))
+            .or_else(|| self.adapters.iter().find(|(key, _)| *key == name))
+            .map(|(_, section)| section.clone())
+            .unwrap_or_default()
+    }
+    /// How long the adapter may stay idle before it is powered off
+    pub fn idle_power_off(&self, name: &str, address: &str) -> Option<Duration> {
+        let minutes = self
+            .adapter(name, address)
+            .idle_power_off
+            .unwrap_or(self.idle_power_off);
+        (minutes > 0).then(|| Duration::from_secs(u64::from(minutes) * 60))
+    }
+    /// Interval of the background scans, when they are enabled
+    pub fn background_scan(&self) -> Option<Duration> {
+        (self.scan_every > 0).then(|| Duration::from_secs(u64::from(self.scan_every) * 60))
+    }
+    /// How long an unpaired device may go unseen before it is dropped, when aging is enabled
+    pub fn stale_after(&self) -> Option<Duration> {
+        (self.stale_after > 0)
+            .then(|| Duration::from_secs(u64::from(self.stale_after) * 24 * 60 * 60))
+    }
+    /// Sets a top level option in the config file, keeping the rest of it as written
+    pub fn persist(key: &str, value: toml::Value) -> io::Result<PathBuf> {
+        let path = Self::path().ok_or(io::ErrorKind::NotFound)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let src = match fs::read_to_string(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            res => res?,
+        };
+        fs::write(&path, set_top_level(&src, key, &value))?;
+        Ok(path)
+    }
+    fn validate_columns(&mut self, errors: &mut Vec<String>) {
+        for column in &self.adapter_columns {
+            if !ADAPTER_COLUMN_NAMES.contains(&column.as_str()) {
+                errors.push(format!(
+                    "adapter_columns: {column:?} is unknown, expected one of {}",
+                    ADAPTER_COLUMN_NAMES.join(", ")
+                ));
+            }
+        }
+        self.adapter_columns
+            .retain(|c| ADAPTER_COLUMN_NAMES.contains(&c.as_str()));
+        if self.adapter_columns.is_empty() {
+            self.adapter_columns = Self::default_adapter_columns();
+        }
+    }
+    fn validate_address_patterns(&mut self, errors: &mut Vec<String>) {
+        let valid = |p: &String| {
+            p.chars()
+                .all(|c| c.is_ascii_hexdigit() || ":*?".contains(c))
+        };
+        for (name, patterns) in [
+            ("auto_block", &mut self.auto_block),
+            ("allowed_devices", &mut self.allowed_devices),
+        ] {
+            for pattern in patterns.iter().filter(|p| !valid(p)) {
+                errors.push(format!(
+                    "{name}: {pattern:?} is not an address pattern, ignoring it"
+                ));
+            }
+            patterns.retain(valid);
+        }
+    }
+    fn default_adapter_columns() -> Vec<String> {
+        ADAPTER_COLUMN_NAMES[..4]
+            .iter()
+            .map(|c| c.to_string())
+            .collect()
+    }
+    /// Parses the config, dropping every line that fails to deserialize so
+    /// that the remaining fields still apply.
+    fn parse_lenient(mut src: String, errors: &mut Vec<String>) -> PartialConfig {
+        while !src.trim().is_empty() {
+            let e = match toml::from_str(&src) {
+                Ok(partial) => return partial,
+                Err(e) => e,
+            };
+            let Some(span) = e.span() else {
+                break;
+            };
+            let mut start = src[..span.start].rfind('\n').map_or(0, |i| i + 1);
+            let mut end = src[span.start..]
+                .find('\n')
+                .map_or(src.len(), |i| span.start + i);
+            // errors at the end of the input point past the line that caused them
+            while src[start..end].trim().is_empty() && start > 0 {
+                end = start - 1;
+                start = src[..end].rfind('\n').map_or(0, |i| i + 1);
+            }
+            if start == end {
+                errors.push(format!("{}, ignoring the file", e.message().trim()));
+                break;
+            }
+            errors.push(format!(
+                "{}: {}, ignoring it",
+                src[start..end].trim(),
+                e.message().trim()
+            ));
+            src.replace_range(start..end, "");
+        }
+        PartialConfig::default()
+    }
 }
 impl From<PartialConfig> for Config {
     fn from(val: PartialConfig) -> Self {
         Self {
             theme: Theme::from(val.theme.unwrap_or_default()),
+            proximity: Proximity::from(val.proximity.unwrap_or_default()),
+            alerts: Alerts::from(val.alerts.unwrap_or_default()),
+            pairing: Pairing::from(val.pairing.unwrap_or_default()),
+            mqtt: Mqtt::from(val.mqtt.unwrap_or_default()),
+            tracker: Tracker::from(val.tracker.unwrap_or_default()),
+            adapter_columns: val
+                .adapter_columns
+                .unwrap_or_else(Self::default_adapter_columns),
+            persist_actions: val.persist_actions.unwrap_or(false),
+            auto_block: val.auto_block.unwrap_or_default(),
+            allowed_devices: val.allowed_devices.unwrap_or_default(),
+            scan_on_startup: val.scan_on_startup.unwrap_or(false),
+            scan_type: val
+                .scan_type
+                .as_deref()
+                .and_then(ScanType::parse)
+                .unwrap_or_default(),
+            stop_scan_on_connect: val.stop_scan_on_connect.unwrap_or(false),
+            resume_scan: val.resume_scan.unwrap_or(false),
+            auto_reconnect: val.auto_reconnect.unwrap_or(false),
+            reconnect_delay: val.reconnect_delay.unwrap_or(5),
+            pair_timeout: val.pair_timeout.unwrap_or(30),
+            default_adapter: val.default_adapter,
+            idle_power_off: val.idle_power_off.unwrap_or(0),
+            scan_every: val.scan_every.unwrap_or(0),
+            scan_for: val.scan_for.unwrap_or(10),
+            stale_after: val.stale_after.unwrap_or(0),
+            stale_action: val
+                .stale_action
+                .as_deref()
+                .and_then(StaleAction::parse)
+                .unwrap_or_default(),
+            gatt_poll_every: val.gatt_poll_every.unwrap_or(2),
+            double_click: val.double_click.unwrap_or(400),
+            adapters: val
+                .adapter
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(key, section)| (key, AdapterSection::from(section)))
+                .collect(),
+            quick_devices: val
+                .quick_devices
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(key, device)| Some((quick_key(&key)?, device)))
+                .sorted()
+                .collect(),
+            metrics_listen: val.metrics_listen.and_then(|s| s.parse().ok()),
+            language: val.language,
+            errors: vec![],
+        }
+    }
+}
+
+fn quick_key(key: &str) -> Option<char> {
+    match key.chars().collect::<Vec<_>>()[..] {
+        [c @ '1'..='9'] => Some(c),
+        _ => None,
+    }
+}
+
+fn set_top_level(src: &str, key: &str, value: &toml::Value) -> String {
+    let line = format!("{key} = {value}");
+    let mut lines = src.lines().map(str::to_string).collect::<Vec<_>>();
+    let top = lines
+        .iter()
+        .position(|l| l.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    let existing = lines[..top].iter().position(|l| {
+        l.trim_start()
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    });
+    match existing {
+        Some(i) => lines[i] = line,
+        None => lines.insert(top, line),
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Answers of the first-run setup
+#[derive(Clone, Debug)]
+pub struct Setup {
+    pub preset: String,
+    pub default_adapter: Option<String>,
+    pub scan_on_startup: bool,
+}
+impl Setup {
+    /// Writes the answers as a new config file, never replacing an existing one
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = Config::path().ok_or(io::ErrorKind::NotFound)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut theme = toml::Table::new();
+        let _ = theme.insert("preset".to_string(), self.preset.clone().into());
+        let mut config = toml::Table::new();
+        let _ = config.insert("scan_on_startup".to_string(), self.scan_on_startup.into());
+        if let Some(adapter) = &self.default_adapter {
+            let _ = config.insert("default_adapter".to_string(), adapter.clone().into());
         }
+        let _ = config.insert("theme".to_string(), theme.into());
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        file.write_all(
+            toml::to_string(&config)
+                .map_err(io::Error::other)?
+                .as_bytes(),
+        )?;
+        Ok(path)
+    }
+}
+
+#[derive(Default)]
+pub struct ConfigSource {
+    pub path: Option<PathBuf>,
+    pub theme: Option<String>,
+}
+
+const ENV_PREFIX: &str = "BLUERAT_";
+
+/// A config field that can be replaced by the value of an environment variable.
+trait EnvOverride {
+    fn override_from(&mut self, var: &str);
+}
+impl EnvOverride for Option<String> {
+    fn override_from(&mut self, var: &str) {
+        if let Ok(val) = env::var(var) {
+            *self = Some(val);
+        }
+    }
+}
+impl EnvOverride for Option<Vec<String>> {
+    fn override_from(&mut self, var: &str) {
+        if let Ok(val) = env::var(var) {
+            *self = Some(val.split(',').map(|v| v.trim().to_string()).collect());
+        }
+    }
+}
+impl EnvOverride for Option<bool> {
+    fn override_from(&mut self, var: &str) {
+        match env::var(var).map(|v| v.to_lowercase()).as_deref() {
+            Ok("1" | "true" | "yes" | "on") => *self = Some(true),
+            Ok("0" | "false" | "no" | "off") => *self = Some(false),
+            _ => {}
+        }
+    }
+}
+impl EnvOverride for Option<i16> {
+    fn override_from(&mut self, var: &str) {
+        if let Some(val) = env::var(var).ok().and_then(|v| v.parse().ok()) {
+            *self = Some(val);
+        }
+    }
+}
+impl EnvOverride for Option<u16> {
+    fn override_from(&mut self, var: &str) {
+        if let Some(val) = env::var(var).ok().and_then(|v| v.parse().ok()) {
+            *self = Some(val);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persisting_keeps_the_rest_of_the_file() {
+        let value = toml::Value::from("passive");
+        let src = "# scanning\nscan_type = \"active\"\n\n[theme]\nscan_type = 1\n";
+        assert_eq!(
+            set_top_level(src, "scan_type", &value),
+            "# scanning\nscan_type = \"passive\"\n\n[theme]\nscan_type = 1\n"
+        );
+        assert_eq!(
+            set_top_level("[theme]\nborders = false\n", "scan_type", &value),
+            "scan_type = \"passive\"\n[theme]\nborders = false\n"
+        );
+    }
+
+    #[test]
+    fn invalid_fields_are_dropped_individually() {
+        let mut errors = vec![];
+        let src = "scan_on_startup = true\n[theme]\nborders = \"yes\"\nscrollbars = true\n";
+        let partial = Config::parse_lenient(src.to_string(), &mut errors);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(partial.scan_on_startup, Some(true));
+        let theme = partial.theme.unwrap();
+        assert_eq!(theme.borders, None);
+        assert_eq!(theme.scrollbars, Some(true));
+    }
+
+    #[test]
+    fn bad_last_line_is_dropped() {
+        for src in [
+            "scan_for = 20\nscan_every\n",
+            "scan_for = 20\nscan_every\n\n\n",
+        ] {
+            let mut errors = vec![];
+            let partial = Config::parse_lenient(src.to_string(), &mut errors);
+            assert_eq!(errors.len(), 1, "{src:?}: {errors:?}");
+            assert_eq!(partial.scan_for, Some(20));
+        }
+    }
+
+    #[test]
+    fn auto_accept_matches_categories() {
+        let mut pairing = Pairing {
+            auto_accept: vec!["sensor".to_string(), "Audio".to_string()],
+        };
+        let mut errors = vec![];
+        pairing.validate(&mut errors);
+        assert_eq!(errors.len(), 1);
+        assert!(pairing.auto_accepts(DeviceCategory::Sensor));
+        assert!(!pairing.auto_accepts(DeviceCategory::Audio));
+        assert!(!pairing.auto_accepts(DeviceCategory::Input));
+    }
+
+    #[test]
+    fn invalid_colors_fall_back_to_preset() {
+        let mut errors = vec![];
+        let mut theme = Theme::from(PartialTheme {
+            fg_normal_color: Some("blurple".to_string()),
+            ..Default::default()
+        });
+        theme.validate(Some("light".to_string()), &mut errors);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(theme.fg_normal_color, "black");
+    }
+
+    #[test]
+    fn idle_power_off_per_adapter() {
+        let section = |minutes| PartialAdapterSection {
+            idle_power_off: Some(minutes),
+            ..Default::default()
+        };
+        let config = Config::from(PartialConfig {
+            idle_power_off: Some(10),
+            adapter: Some(HashMap::from([
+                ("hci1".to_string(), section(0)),
+                ("00:1A:7D:DA:71:13".to_string(), section(2)),
+            ])),
+            ..Default::default()
+        });
+        let minutes = |m: u64| Some(Duration::from_secs(m * 60));
+        assert_eq!(
+            config.idle_power_off("hci0", "AA:BB:CC:DD:EE:FF"),
+            minutes(10)
+        );
+        assert_eq!(config.idle_power_off("hci1", "AA:BB:CC:DD:EE:FF"), None);
+        assert_eq!(
+            config.idle_power_off("hci2", "00:1a:7d:da:71:13"),
+            minutes(2)
+        );
+    }
+
+    #[test]
+    fn quick_devices_by_number() {
+        let config = Config::from(PartialConfig {
+            quick_devices: Some(HashMap::from([
+                ("2".to_string(), "speaker".to_string()),
+                ("1".to_string(), "AA:BB:CC:DD:EE:FF".to_string()),
+                ("10".to_string(), "headset".to_string()),
+            ])),
+            ..Default::default()
+        });
+        assert_eq!(
+            config.quick_devices,
+            [
+                ('1', "AA:BB:CC:DD:EE:FF".to_string()),
+                ('2', "speaker".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn adapter_sections_override_globals() {
+        let config = Config::from(PartialConfig {
+            idle_power_off: Some(10),
+            adapter: Some(HashMap::from([(
+                "hci1".to_string(),
+                PartialAdapterSection {
+                    idle_power_off: Some(0),
+                    sort: Some("nearest".to_string()),
+                    scan_transport: Some("le".to_string()),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        });
+        assert_eq!(config.idle_power_off("hci1", "AA:BB:CC:DD:EE:FF"), None);
+        let section = config.adapter("hci1", "AA:BB:CC:DD:EE:FF");
+        assert_eq!(section.sort, Some(DeviceOrder::Nearest));
+        assert!(section
+            .scan_filter
+            .is_some_and(|f| f.transport == DiscoveryTransport::Le));
+        assert!(config.adapter("hci0", "").scan_filter.is_none());
+    }
+
+    #[test]
+    fn adapter_address_wins_over_name() {
+        let section = |minutes| PartialAdapterSection {
+            idle_power_off: Some(minutes),
+            ..Default::default()
+        };
+        let config = Config::from(PartialConfig {
+            adapter: Some(HashMap::from([
+                ("hci0".to_string(), section(5)),
+                ("aa:bb:cc:dd:ee:ff".to_string(), section(7)),
+            ])),
+            ..Default::default()
+        });
+        let minutes = |m: u64| Some(Duration::from_secs(m * 60));
+        assert_eq!(
+            config.idle_power_off("hci0", "AA:BB:CC:DD:EE:FF"),
+            minutes(7)
+        );
+        assert_eq!(
+            config.idle_power_off("hci0", "00:00:00:00:00:01"),
+            minutes(5)
+        );
     }
 }
@@ -0,0 +1,160 @@
+use std::fs;
+
+use bluer::ErrorKind;
+
+pub const EXPERIMENTAL_HINT: &str =
+    "Not supported by bluetoothd, it may need to run with --experimental";
+
+const MESSAGES: &[(&str, &str)] = &[
+    (
+        "br-connection-page-timeout",
+        "Device out of range or turned off",
+    ),
+    ("br-connection-canceled", "Connection cancelled"),
+    ("br-connection-refused", "Connection refused by the device"),
+    (
+        "br-connection-profile-unavailable",
+        "Device has no profile this system can connect to",
+    ),
+    (
+        "br-connection-create-socket",
+        "Device out of range or not accepting connections",
+    ),
+    (
+        "le-connection-abort-by-local",
+        "Connection aborted, the device may be out of range or asleep",
+    ),
+    (
+        "le-connection-abort-by-remote",
+        "Connection closed by the device",
+    ),
+    ("Host is down", "Device out of range or turned off"),
+    ("Software caused connection abort", "Connection dropped"),
+];
+
+/// Replaces the error of a call that bluez only exports in experimental mode with the hint
+pub fn experimental_error(err: bluer::Error) -> bluer::Error {
+    let unknown = ["UnknownMethod", "UnknownInterface"];
+    match unknown.iter().any(|name| err.message.contains(name)) {
+        true => bluer::Error {
+            kind: ErrorKind::Failed,
+            message: EXPERIMENTAL_HINT.to_string(),
+        },
+        false => err,
+    }
+}
+
+/// Translates BlueZ errors into actionable messages, falling back to the raw D-Bus text.
+pub fn friendly_message(err: &bluer::Error) -> String {
+    if let Some((_, msg)) = MESSAGES.iter().find(|(key, _)| err.message.contains(key)) {
+        return msg.to_string();
+    }
+    let msg = match err.kind {
+        ErrorKind::AlreadyConnected => "Device is already connected",
+        ErrorKind::AlreadyExists => "Device is already paired",
+        ErrorKind::AuthenticationCanceled => "Pairing was cancelled",
+        ErrorKind::AuthenticationFailed => "Pairing failed, wrong PIN or key rejected",
+        ErrorKind::AuthenticationRejected => "Pairing rejected on the device",
+        ErrorKind::AuthenticationTimeout => "Pairing timed out, confirm it on the device",
+        ErrorKind::ConnectionAttemptFailed => "Connection attempt failed",
+        ErrorKind::DoesNotExist => "Device is no longer known, scan for it again",
+        ErrorKind::InProgress => "Another operation is already in progress",
+        ErrorKind::NotReady => "Adapter is not ready, power it on first",
+        ErrorKind::NotAuthorized | ErrorKind::NotPermitted => {
+            "Not permitted, check rfkill and D-Bus permissions"
+        }
+        ErrorKind::NotSupported => "Not supported by the adapter or device",
+        _ => return err.message.clone(),
+    };
+    msg.to_string()
+}
+
+/// Whether bluetoothd runs with its experimental interfaces, `None` when the daemon is not visible
+pub fn bluez_experimental() -> Option<bool> {
+    let conf = fs::read_to_string("/etc/bluetooth/main.conf").unwrap_or_default();
+    if experimental_in_conf(&conf) {
+        return Some(true);
+    }
+    fs::read_dir("/proc")
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            fs::read_to_string(entry.path().join("comm"))
+                .is_ok_and(|comm| comm.trim() == "bluetoothd")
+        })
+        .find_map(|entry| fs::read(entry.path().join("cmdline")).ok())
+        .map(|cmdline| {
+            cmdline
+                .split(|b| *b == 0)
+                .any(|arg| arg == b"-E" || arg == b"--experimental")
+        })
+}
+
+fn experimental_in_conf(conf: &str) -> bool {
+    let mut general = false;
+    for line in conf.lines().map(str::trim) {
+        if line.starts_with('[') {
+            general = line == "[General]";
+        } else if general
+            && let Some((key, val)) = line.split_once('=')
+            && key.trim() == "Experimental"
+        {
+            return val.trim().eq_ignore_ascii_case("true");
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(kind: ErrorKind, message: &str) -> bluer::Error {
+        bluer::Error {
+            kind,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn known_messages_take_precedence() {
+        let err = error(ErrorKind::Failed, "br-connection-page-timeout");
+        assert_eq!(friendly_message(&err), "Device out of range or turned off");
+    }
+
+    #[test]
+    fn kinds_are_translated() {
+        let err = error(ErrorKind::AuthenticationRejected, "Authentication Rejected");
+        assert_eq!(friendly_message(&err), "Pairing rejected on the device");
+    }
+
+    #[test]
+    fn unknown_errors_keep_raw_message() {
+        let err = error(ErrorKind::Failed, "something odd");
+        assert_eq!(friendly_message(&err), "something odd");
+    }
+
+    #[test]
+    fn unknown_methods_of_experimental_calls_hint_at_experimental() {
+        let err = error(
+            ErrorKind::Failed,
+            "org.freedesktop.DBus.Error.UnknownMethod: ConnectDevice",
+        );
+        assert_eq!(
+            friendly_message(&experimental_error(err)),
+            EXPERIMENTAL_HINT
+        );
+        let err = error(ErrorKind::Failed, "br-connection-page-timeout");
+        assert_eq!(
+            friendly_message(&experimental_error(err)),
+            "Device out of range or turned off"
+        );
+    }
+
+    #[test]
+    fn experimental_is_read_from_the_general_section() {
+        assert!(experimental_in_conf("[General]\nExperimental = true\n"));
+        assert!(!experimental_in_conf("[General]\n#Experimental = true\n"));
+        assert!(!experimental_in_conf("[Policy]\nExperimental = true\n"));
+    }
+}
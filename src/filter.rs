@@ -0,0 +1,207 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::models::Device;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Any,
+    Name,
+    Address,
+    Type,
+    State,
+    Uuid,
+}
+impl Field {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "name" | "alias" => Ok(Field::Name),
+            "addr" | "address" => Ok(Field::Address),
+            "type" | "kind" => Ok(Field::Type),
+            "state" => Ok(Field::State),
+            "uuid" | "service" => Ok(Field::Uuid),
+            _ => Err(format!(
+                "Unknown filter field {name:?}, expected name, addr, type, state or uuid"
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Term {
+    field: Field,
+    pattern: Regex,
+}
+impl Term {
+    fn matches(&self, device: &Device) -> bool {
+        match self.field {
+            Field::Any => {
+                self.pattern.is_match(&device.alias)
+                    || self.pattern.is_match(&device.id.to_string())
+                    || self.pattern.is_match(&device.kind)
+            }
+            Field::Name => self.pattern.is_match(&device.alias),
+            Field::Address => self.pattern.is_match(&device.id.to_string()),
+            Field::Type => {
+                self.pattern.is_match(&device.kind)
+                    || self.pattern.is_match(&format!("{:?}", device.category))
+            }
+            Field::State => device.states().iter().any(|s| self.pattern.is_match(s)),
+            Field::Uuid => device.uuids.iter().any(|u| self.pattern.is_match(u)),
+        }
+    }
+}
+
+/// Device filter made of whitespace separated terms that must all match.
+///
+/// A term is either `field:value` or a bare value matched against the name,
+/// address and type, except a bare partial address like `F4:BB` which only
+/// matches the address. The `uuid` field matches the advertised service uuids,
+/// so `uuid:0x180D` keeps the devices advertising Heart Rate. Values are case insensitive regexes and fall back to a
+/// plain substring match when they don't compile.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    text: String,
+    terms: Vec<Term>,
+}
+impl Filter {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut terms = vec![];
+        for term in text.split_whitespace() {
+            let (field, value) = match term.split_once(':') {
+                _ if is_address_prefix(term) => (Field::Address, term),
+                Some((field, value)) => (Field::parse(field)?, value),
+                None => (Field::Any, term),
+            };
+            // uuids are stored without the hex prefix of assigned numbers like 0x180D
+            let value = match field {
+                Field::Uuid => value.trim_start_matches("0x").trim_start_matches("0X"),
+                _ => value,
+            };
+            if value.is_empty() {
+                continue;
+            }
+            terms.push(Term {
+                field,
+                pattern: Self::pattern(value)?,
+            });
+        }
+        Ok(Self {
+            text: text.trim().to_string(),
+            terms,
+        })
+    }
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+    pub fn matches(&self, device: &Device) -> bool {
+        self.terms.iter().all(|t| t.matches(device))
+    }
+    pub fn match_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        self.terms
+            .iter()
+            .flat_map(|t| t.pattern.find_iter(text).map(|m| m.range()))
+            .filter(|r| !r.is_empty())
+            .collect()
+    }
+    fn pattern(value: &str) -> Result<Regex, String> {
+        RegexBuilder::new(value)
+            .case_insensitive(true)
+            .build()
+            .or_else(|_| {
+                RegexBuilder::new(&regex::escape(value))
+                    .case_insensitive(true)
+                    .build()
+            })
+            .map_err(|e| e.to_string())
+    }
+}
+impl Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Whether the term reads like the start of an address, pairs of hex digits
+/// separated by colons such as `F4:BB` or `f4:b`
+fn is_address_prefix(term: &str) -> bool {
+    term.contains(':')
+        && term.split(':').all(|part| {
+            (1..=2).contains(&part.len()) && part.chars().all(|c| c.is_ascii_hexdigit())
+        })
+}
+
+/// Case insensitive match of an address against a pattern where `*` stands
+/// for any run of characters and `?` for a single one, e.g. `F0:9F:*`.
+pub fn address_matches(pattern: &str, address: &str) -> bool {
+    fn matches(p: &[u8], a: &[u8]) -> bool {
+        match (p.first(), a.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => matches(&p[1..], a) || (!a.is_empty() && matches(p, &a[1..])),
+            (Some(b'?'), Some(_)) => matches(&p[1..], &a[1..]),
+            (Some(x), Some(y)) if x.eq_ignore_ascii_case(y) => matches(&p[1..], &a[1..]),
+            _ => false,
+        }
+    }
+    matches(pattern.as_bytes(), address.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DeviceCategory;
+
+    fn device(alias: &str, kind: &str) -> Device {
+        Device {
+            kind: kind.to_string(),
+            category: DeviceCategory::decode(kind, None),
+            is_connected: true,
+            ..Device::fake([0xF4, 0xBB, 0xCC, 0xDD, 0xEE, 0x01], alias)
+        }
+    }
+
+    #[test]
+    fn fields_and_bare_terms() {
+        let headset = device("WH-1000XM4", "audio-headset");
+        let keyboard = device("MX Keys", "input-keyboard");
+        let filter = Filter::parse("type:audio state:connected").unwrap();
+        assert!(filter.matches(&headset));
+        assert!(!filter.matches(&keyboard));
+        assert!(Filter::parse("addr:^F4").unwrap().matches(&keyboard));
+        assert!(Filter::parse("mx").unwrap().matches(&keyboard));
+        assert!(!Filter::parse("state:paired").unwrap().matches(&keyboard));
+        let mut sensor = device("HRM", "");
+        sensor.uuids = vec!["0000180d-0000-1000-8000-00805f9b34fb".to_string()];
+        assert!(Filter::parse("uuid:0x180D").unwrap().matches(&sensor));
+        assert!(!Filter::parse("uuid:180f").unwrap().matches(&sensor));
+    }
+
+    #[test]
+    fn bare_partial_addresses() {
+        let keyboard = device("MX Keys", "input-keyboard");
+        assert!(Filter::parse("F4:BB").unwrap().matches(&keyboard));
+        assert!(Filter::parse("f4:bb:c").unwrap().matches(&keyboard));
+        assert!(!Filter::parse("F4:BC").unwrap().matches(&keyboard));
+        assert!(!Filter::parse("ad:ff").unwrap().matches(&keyboard));
+        assert!(Filter::parse("name:mx").unwrap().matches(&keyboard));
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(Filter::parse("color:red").is_err());
+        assert!(Filter::parse("type:").unwrap().is_empty());
+        // not a valid regex, matched literally
+        assert!(Filter::parse("(wh").unwrap().matches(&device("(WH)", "")));
+    }
+
+    #[test]
+    fn address_patterns() {
+        assert!(address_matches("F0:9F:*", "f0:9f:c2:11:22:33"));
+        assert!(address_matches("*:33", "F0:9F:C2:11:22:33"));
+        assert!(address_matches("F0:9?:C2:11:22:33", "F0:9F:C2:11:22:33"));
+        assert!(!address_matches("F0:9F:*", "F0:9E:C2:11:22:33"));
+        assert!(!address_matches("F0:9F", "F0:9F:C2:11:22:33"));
+    }
+}
@@ -1,11 +1,19 @@
+use std::sync::OnceLock;
+
 use lazy_static::lazy_static;
-use ratatui_helpers::config::parse_toml;
 
-use crate::config::{Config, PartialConfig};
+use crate::config::{Config, ConfigSource};
 
 pub const PROJECT_NAME: &str = "bluerat";
 pub const CONFIG_FILE: &str = "config.toml";
+pub const STATE_FILE: &str = "state.toml";
+pub const ACTION_LOG_FILE: &str = "actions.log";
+/// Property changes queued for the interface and the monitor command, the oldest are
+/// dropped past it
+pub const EVENT_QUEUE: usize = 1024;
+
+pub static CONFIG_SOURCE: OnceLock<ConfigSource> = OnceLock::new();
 
 lazy_static! {
-    pub static ref CONFIG: Config = parse_toml::<PartialConfig, _>(PROJECT_NAME, CONFIG_FILE);
+    pub static ref CONFIG: Config = Config::load(CONFIG_SOURCE.get_or_init(Default::default));
 }
@@ -1,30 +1,81 @@
-use std::io::{self};
+use std::fs;
+use std::io::{self, Write};
 
-use ratatui::crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ratatui::crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
 use ratatui::crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::crossterm::{self, terminal};
 use ratatui::layout::Rect;
 use ratatui::prelude::CrosstermBackend;
-use ratatui::Terminal;
+use ratatui::{Terminal, TerminalOptions, Viewport};
 
-pub fn try_init_term() -> Result<Terminal<CrosstermBackend<io::Stdout>>, Box<io::Error>> {
+/// Rows taken below the prompt by the inline mode
+const INLINE_HEIGHT: u16 = 12;
+
+pub fn try_init_term(
+    inline: bool,
+) -> Result<Terminal<CrosstermBackend<io::Stdout>>, Box<io::Error>> {
     let mut stdout = io::stdout();
     terminal::enable_raw_mode()?;
+    // pasted text arrives as a single event instead of keys that would trigger shortcuts
+    crossterm::execute!(stdout, EnableBracketedPaste)?;
+    if inline {
+        // the mouse is left to the terminal so that the scrollback stays usable
+        let options = TerminalOptions {
+            viewport: Viewport::Inline(INLINE_HEIGHT),
+        };
+        return Ok(Terminal::with_options(
+            CrosstermBackend::new(stdout),
+            options,
+        )?);
+    }
     crossterm::execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     Ok(Terminal::new(CrosstermBackend::new(stdout))?)
 }
 pub fn try_release_term(
     mut term: Terminal<CrosstermBackend<io::Stdout>>,
+    inline: bool,
 ) -> Result<(), Box<io::Error>> {
     terminal::disable_raw_mode()?;
-    crossterm::execute!(
-        term.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    crossterm::execute!(term.backend_mut(), DisableBracketedPaste)?;
+    if inline {
+        term.clear()?;
+    } else {
+        crossterm::execute!(
+            term.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
+    }
     term.show_cursor()?;
     Ok(())
 }
+/// Pasted text as a single line, without the newlines and tabs copied along with it
+pub fn paste_line(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+/// Whether the machine runs on external power, assumed when no supply tells
+pub fn on_ac_power() -> bool {
+    let Ok(supplies) = fs::read_dir("/sys/class/power_supply") else {
+        return true;
+    };
+    let read = |path: std::path::PathBuf| fs::read_to_string(path).unwrap_or_default();
+    let external = supplies
+        .filter_map(|e| e.ok())
+        .filter(|e| matches!(read(e.path().join("type")).trim(), "Mains" | "USB"))
+        .map(|e| read(e.path().join("online")).trim() == "1")
+        .collect::<Vec<_>>();
+    external.is_empty() || external.contains(&true)
+}
+/// Puts the text on the clipboard through the terminal, with the OSC 52 sequence
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut out = io::stdout();
+    write!(out, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    out.flush()
+}
 pub fn centered_rect(area: Rect, (width, height): (u16, u16)) -> Rect {
     Rect {
         x: (area.x + area.width / 2).saturating_sub(width / 2),
@@ -0,0 +1,189 @@
+use std::fmt::Display;
+
+use itertools::Itertools;
+use ratatui::layout::Rect;
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+
+use crate::theme::StyledWidget;
+
+/// Bytes shown on each line of the dump
+const WIDTH: usize = 16;
+
+/// Byte order used to read integers out of the dumped bytes
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Endian {
+    /// The order of GATT values and advertising data
+    #[default]
+    Little,
+    Big,
+}
+impl Endian {
+    pub fn toggled(self) -> Self {
+        match self {
+            Endian::Little => Endian::Big,
+            Endian::Big => Endian::Little,
+        }
+    }
+}
+impl Display for Endian {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endian::Little => write!(f, "LE"),
+            Endian::Big => write!(f, "BE"),
+        }
+    }
+}
+
+/// Offset, hex and ascii columns of a hexdump, e.g. `0000  48 69  |Hi|`
+pub fn lines(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(WIDTH)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex = chunk.iter().map(|b| format!("{b:02x}")).join(" ");
+            let ascii: String = chunk
+                .iter()
+                .map(|b| match b.is_ascii_graphic() || *b == b' ' {
+                    true => *b as char,
+                    false => '.',
+                })
+                .collect();
+            format!(
+                "{:04x}  {hex:<width$}  |{ascii}|",
+                i * WIDTH,
+                width = WIDTH * 3 - 1
+            )
+        })
+        .collect()
+}
+
+/// The leading bytes read as unsigned and signed integers of each width they fill
+pub fn integers(bytes: &[u8], endian: Endian) -> String {
+    let readings = [1, 2, 4, 8]
+        .into_iter()
+        .filter(|len| bytes.len() >= *len)
+        .map(|len| {
+            let mut buf = [0u8; 8];
+            match endian {
+                Endian::Little => buf[..len].copy_from_slice(&bytes[..len]),
+                Endian::Big => bytes[..len]
+                    .iter()
+                    .rev()
+                    .enumerate()
+                    .for_each(|(i, b)| buf[i] = *b),
+            }
+            let unsigned = u64::from_le_bytes(buf);
+            // sign extend from the top bit of the width
+            let shift = 64 - 8 * len as u32;
+            let signed = ((unsigned << shift) as i64) >> shift;
+            format!("u{0} {unsigned}  i{0} {signed}", len * 8)
+        })
+        .join("  ");
+    format!("{endian} {readings}")
+}
+
+/// Bytes typed as hex, pairs of digits with optional `0x` prefixes and
+/// space, colon or comma separators, e.g. `0x01 ff` or `01:ff`
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text
+        .split([' ', ':', ','])
+        .map(|part| part.trim_start_matches("0x").trim_start_matches("0X"))
+        .collect();
+    // only ascii digits may be sliced by byte below
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("{c:?} is not a hex digit"));
+    }
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(format!("{text:?} is not an even number of hex digits"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| format!("{:?} is not a hex byte", &digits[i..i + 2]))
+        })
+        .collect()
+}
+
+/// Labelled byte strings shown as hexdumps, one after the other
+pub struct HexdumpPane {
+    sections: Vec<(String, Vec<u8>)>,
+    pub endian: Endian,
+}
+impl HexdumpPane {
+    pub fn new(sections: Vec<(String, Vec<u8>)>, endian: Endian) -> Self {
+        Self { sections, endian }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+    /// Rows the pane needs to show every section, borders included
+    pub fn height(&self) -> u16 {
+        let lines: usize = self
+            .sections
+            .iter()
+            .map(|(_, bytes)| bytes.len().div_ceil(WIDTH).max(1) + 2)
+            .sum();
+        lines.try_into().unwrap_or(u16::MAX).saturating_add(2)
+    }
+    pub fn draw(&self, f: &mut Frame<'_>, area: Rect, title: &str) {
+        let text = self
+            .sections
+            .iter()
+            .flat_map(|(label, bytes)| {
+                let dump = match bytes.is_empty() {
+                    true => vec!["(empty)".to_string()],
+                    false => lines(bytes),
+                };
+                [label.clone()]
+                    .into_iter()
+                    .chain(dump)
+                    .chain((!bytes.is_empty()).then(|| integers(bytes, self.endian)))
+            })
+            .join("\n");
+        let block = StyledWidget::block().title(format!("{title} ({}, e to swap)", self.endian));
+        f.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumps_offset_hex_and_ascii() {
+        let bytes = b"Hello, bluerat!\x00\x01";
+        assert_eq!(
+            lines(bytes),
+            [
+                "0000  48 65 6c 6c 6f 2c 20 62 6c 75 65 72 61 74 21 00  |Hello, bluerat!.|",
+                "0010  01                                               |.|",
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_typed_hex() {
+        assert_eq!(parse_hex("0x01 ff"), Ok(vec![0x01, 0xff]));
+        assert_eq!(parse_hex("01:FF,10"), Ok(vec![0x01, 0xff, 0x10]));
+        assert_eq!(parse_hex("0X0A 0x0b"), Ok(vec![0x0a, 0x0b]));
+        assert!(parse_hex("1").is_err());
+        assert!(parse_hex("zz").is_err());
+        assert!(parse_hex("aé01").is_err());
+        assert!(parse_hex("é1").is_err());
+    }
+
+    #[test]
+    fn reads_integers_in_both_orders() {
+        let bytes = [0xfe, 0xff, 0x01];
+        assert_eq!(
+            integers(&bytes, Endian::Little),
+            "LE u8 254  i8 -2  u16 65534  i16 -2"
+        );
+        assert_eq!(
+            integers(&bytes, Endian::Big),
+            "BE u8 254  i8 -2  u16 65279  i16 -257"
+        );
+    }
+}
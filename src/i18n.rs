@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::{env, fs};
+
+use lazy_static::lazy_static;
+
+use crate::config::Config;
+use crate::globals::CONFIG;
+
+/// Translations shipped with bluerat, a `locales/<language>.toml` file next to the config
+/// adds to them or covers another language
+const BUNDLED: &[(&str, &str)] = &[("fr", include_str!("../locales/fr.toml"))];
+
+lazy_static! {
+    static ref CATALOG: HashMap<String, String> =
+        language().map_or_else(HashMap::new, |l| load(&l));
+}
+
+/// The label in the interface language, keyed by its English text
+pub fn tr(text: &str) -> &str {
+    CATALOG.get(text).map_or(text, String::as_str)
+}
+
+/// The configured language, or the one of the locale from the environment
+fn language() -> Option<String> {
+    CONFIG.language.clone().or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|val| !val.is_empty())
+            .and_then(|val| locale_language(&val))
+    })
+}
+
+/// Language of a POSIX locale, e.g. `fr` for `fr_FR.UTF-8`, `None` for the untranslated ones
+fn locale_language(locale: &str) -> Option<String> {
+    let language = locale.split(['_', '.', '@']).next()?.to_lowercase();
+    match language.as_str() {
+        "" | "c" | "posix" | "en" => None,
+        _ => Some(language),
+    }
+}
+
+fn load(language: &str) -> HashMap<String, String> {
+    let bundled = BUNDLED
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map(|(_, src)| src.to_string());
+    let local = Config::path()
+        .and_then(|p| {
+            p.parent()
+                .map(|dir| dir.join("locales").join(format!("{language}.toml")))
+        })
+        .and_then(|p| fs::read_to_string(p).ok());
+    bundled
+        .into_iter()
+        .chain(local)
+        .flat_map(|src| toml::from_str::<HashMap<String, String>>(&src).unwrap_or_default())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_from_locale() {
+        assert_eq!(locale_language("fr_FR.UTF-8"), Some("fr".to_string()));
+        assert_eq!(locale_language("de"), Some("de".to_string()));
+        assert_eq!(locale_language("C.UTF-8"), None);
+        assert_eq!(locale_language("en_US.UTF-8"), None);
+    }
+
+    #[test]
+    fn bundled_locales_parse() {
+        for (name, src) in BUNDLED {
+            let catalog = toml::from_str::<HashMap<String, String>>(src);
+            assert!(catalog.is_ok_and(|c| !c.is_empty()), "{name}");
+        }
+    }
+}
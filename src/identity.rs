@@ -0,0 +1,152 @@
+use std::fs;
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::{Aes128, Block};
+use bluer::Address;
+
+use crate::models::{Device, DeviceId};
+
+// where bluetoothd keeps the keys it learned while pairing, readable by root only
+const BLUEZ_STORAGE: &str = "/var/lib/bluetooth";
+
+/// Parses a 16 byte key written as 32 hex digits
+pub fn parse_key(hex: &str) -> Option<[u8; 16]> {
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>()?;
+    bytes.try_into().ok()
+}
+
+/// Identity addresses and resolving keys of the devices bluez paired with,
+/// failing when its storage can't be read, as it can't without root
+pub fn bluez_identities() -> Result<Vec<(DeviceId, [u8; 16])>, String> {
+    let adapters = fs::read_dir(BLUEZ_STORAGE).map_err(|e| {
+        format!("Can't read {BLUEZ_STORAGE} ({e}), private addresses of paired devices stay apart")
+    })?;
+    Ok(adapters
+        .flatten()
+        .filter_map(|adapter| fs::read_dir(adapter.path()).ok())
+        .flat_map(|devices| devices.flatten())
+        .filter_map(|device| {
+            let address = device.file_name().to_str()?.parse().ok()?;
+            let info = fs::read_to_string(device.path().join("info")).ok()?;
+            Some((DeviceId(address), irk_from_info(&info)?))
+        })
+        .collect())
+}
+
+// bluez writes the key least significant byte first, as it travels over the air
+fn irk_from_info(info: &str) -> Option<[u8; 16]> {
+    let mut lines = info
+        .lines()
+        .skip_while(|l| l.trim() != "[IdentityResolvingKey]")
+        .skip(1)
+        .take_while(|l| !l.starts_with('['));
+    let hex = lines.find_map(|l| l.trim().strip_prefix("Key="))?;
+    let mut key = parse_key(hex)?;
+    key.reverse();
+    Some(key)
+}
+
+/// Folds the devices seen under a resolvable private address into their
+/// identity, or just labels them with it when the identity isn't listed
+pub fn merge_identities(devices: &mut Vec<Device>, identities: &[(DeviceId, [u8; 16])]) {
+    let mut merged = vec![];
+    for i in 0..devices.len() {
+        let Some(identity) = identities
+            .iter()
+            .find(|(id, irk)| *id != devices[i].id && resolves(irk, &devices[i].id.0))
+            .map(|(id, _)| *id)
+        else {
+            continue;
+        };
+        let rssi = devices[i].rssi;
+        match devices.iter_mut().find(|d| d.id == identity) {
+            Some(target) => {
+                target.rssi = target.rssi.or(rssi);
+                merged.push(devices[i].id);
+            }
+            None => {
+                let device = &mut devices[i];
+                device.identity = Some(identity);
+                // unnamed devices are aliased by their address, which keeps changing
+                if device.alias.replace('-', ":") == device.id.to_string() {
+                    device.alias = identity.to_string();
+                }
+            }
+        }
+    }
+    devices.retain(|d| !merged.contains(&d.id));
+}
+
+/// Whether the resolvable private address was generated from the key
+pub fn resolves(irk: &[u8; 16], address: &Address) -> bool {
+    let a = address.0;
+    a[0] >> 6 == 0b01 && ah(irk, [a[0], a[1], a[2]]) == [a[3], a[4], a[5]]
+}
+
+/// The random address hash function `ah` of the Bluetooth core spec
+fn ah(irk: &[u8; 16], prand: [u8; 3]) -> [u8; 3] {
+    let mut block = Block::default();
+    block[13..].copy_from_slice(&prand);
+    Aes128::new(irk.into()).encrypt_block(&mut block);
+    [block[13], block[14], block[15]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DeviceCategory;
+
+    const IRK: [u8; 16] = [
+        0xec, 0x02, 0x34, 0xa3, 0x57, 0xc8, 0xad, 0x05, 0x34, 0x10, 0x10, 0xa6, 0x0a, 0x39, 0x7d,
+        0x9b,
+    ];
+
+    #[test]
+    fn resolves_private_addresses() {
+        // sample data of the core spec, vol 3 part H, D.7
+        assert_eq!(ah(&IRK, [0x70, 0x81, 0x94]), [0x0d, 0xfb, 0xaa]);
+        assert!(resolves(
+            &IRK,
+            &Address::new([0x70, 0x81, 0x94, 0x0d, 0xfb, 0xaa])
+        ));
+        assert!(!resolves(
+            &IRK,
+            &Address::new([0x70, 0x81, 0x94, 0x0d, 0xfb, 0xab])
+        ));
+    }
+
+    #[test]
+    fn irk_is_read_reversed() {
+        let info = "[General]\nName=Phone\n\n[IdentityResolvingKey]\n\
+            Key=9B7D390AA610103405ADC857A33402EC\n\n[LongTermKey]\nKey=00\n";
+        assert_eq!(irk_from_info(info), Some(IRK));
+        assert_eq!(irk_from_info("[General]\nName=Phone\n"), None);
+    }
+
+    #[test]
+    fn private_addresses_fold_into_identity() {
+        let device = |address: [u8; 6], rssi| Device {
+            kind: "phone".to_string(),
+            category: DeviceCategory::Phone,
+            rssi,
+            ..Device::fake(address, "Phone")
+        };
+        let identity = DeviceId(Address::new([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0x01]));
+        let rpa = [0x70, 0x81, 0x94, 0x0d, 0xfb, 0xaa];
+        let mut devices = vec![device(identity.0 .0, None), device(rpa, Some(-60))];
+        merge_identities(&mut devices, &[(identity, IRK)]);
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id, identity);
+        assert_eq!(devices[0].rssi, Some(-60));
+
+        let mut devices = vec![device(rpa, None)];
+        merge_identities(&mut devices, &[(identity, IRK)]);
+        assert_eq!(devices[0].identity, Some(identity));
+    }
+}
@@ -2,21 +2,51 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use std::vec;
 
+use itertools::Itertools;
 use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui_helpers::keymap::{KeyMap, ShortCut};
 use ratatui_helpers::stateful_table::TableKeyMap;
 
+use crate::app::ViewKind;
+use crate::globals::CONFIG;
+use crate::i18n::tr;
+
 pub enum AppCommand {
     CloseView,
+    Back,
+    Forward,
     OpenHelpView,
     RefreshView,
+    ConnectInRange,
+    Cheatsheet,
+    ActionHistory,
+    CancelPairing,
+    OpenNewAdapter,
+    PowerAll,
+    QuickList,
+    Search,
+    KeepPowered,
+    /// Toggles the connection of the device configured for the key, by name or address
+    QuickDevice(String),
 }
 impl Display for AppCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AppCommand::CloseView => write!(f, "quit view"),
-            AppCommand::OpenHelpView => write!(f, "help"),
-            AppCommand::RefreshView => write!(f, "refresh"),
+            AppCommand::CloseView => f.write_str(tr("quit view")),
+            AppCommand::Back => f.write_str(tr("go back")),
+            AppCommand::Forward => f.write_str(tr("go forward")),
+            AppCommand::OpenHelpView => f.write_str(tr("help")),
+            AppCommand::RefreshView => f.write_str(tr("refresh")),
+            AppCommand::ConnectInRange => f.write_str(tr("connect device in range")),
+            AppCommand::Cheatsheet => f.write_str(tr("cheatsheet")),
+            AppCommand::ActionHistory => f.write_str(tr("action history")),
+            AppCommand::CancelPairing => f.write_str(tr("cancel pairing")),
+            AppCommand::OpenNewAdapter => f.write_str(tr("open new adapter")),
+            AppCommand::PowerAll => f.write_str(tr("power all adapters on/off")),
+            AppCommand::QuickList => f.write_str(tr("paired devices")),
+            AppCommand::Search => f.write_str(tr("search all adapters")),
+            AppCommand::KeepPowered => f.write_str(tr("keep idle adapters on")),
+            AppCommand::QuickDevice(device) => write!(f, "{} {device}", tr("connect/disconnect")),
         }
     }
 }
@@ -27,27 +57,80 @@ impl KeyMap for AppKeyMap {
         &self.0
     }
     fn default() -> Self {
-        Self(Vec::from([
-            ShortCut(
-                AppCommand::CloseView,
-                vec![
-                    KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE),
-                    KeyEvent::new(KeyCode::Left, KeyModifiers::ALT),
-                    KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
-                ],
-            ),
-            ShortCut(
-                AppCommand::OpenHelpView,
-                vec![
-                    KeyEvent::new(KeyCode::Char('?'), KeyModifiers::NONE),
-                    KeyEvent::new(KeyCode::Char('h'), KeyModifiers::NONE),
-                ],
-            ),
-            ShortCut(
-                AppCommand::RefreshView,
-                vec![KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE)],
-            ),
-        ]))
+        Self(
+            Vec::from([
+                ShortCut(
+                    AppCommand::CloseView,
+                    vec![
+                        KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE),
+                        KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+                    ],
+                ),
+                ShortCut(
+                    AppCommand::Back,
+                    vec![KeyEvent::new(KeyCode::Left, KeyModifiers::ALT)],
+                ),
+                ShortCut(
+                    AppCommand::Forward,
+                    vec![KeyEvent::new(KeyCode::Right, KeyModifiers::ALT)],
+                ),
+                ShortCut(
+                    AppCommand::OpenHelpView,
+                    vec![
+                        KeyEvent::new(KeyCode::Char('?'), KeyModifiers::NONE),
+                        KeyEvent::new(KeyCode::Char('h'), KeyModifiers::NONE),
+                    ],
+                ),
+                ShortCut(
+                    AppCommand::RefreshView,
+                    vec![KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE)],
+                ),
+                ShortCut(
+                    AppCommand::ConnectInRange,
+                    vec![KeyEvent::new(KeyCode::Char('C'), KeyModifiers::SHIFT)],
+                ),
+                ShortCut(
+                    AppCommand::Cheatsheet,
+                    vec![KeyEvent::new(KeyCode::Char('\\'), KeyModifiers::NONE)],
+                ),
+                ShortCut(
+                    AppCommand::ActionHistory,
+                    vec![KeyEvent::new(KeyCode::Char('H'), KeyModifiers::SHIFT)],
+                ),
+                ShortCut(
+                    AppCommand::CancelPairing,
+                    vec![KeyEvent::new(KeyCode::Char('X'), KeyModifiers::SHIFT)],
+                ),
+                ShortCut(
+                    AppCommand::OpenNewAdapter,
+                    vec![KeyEvent::new(KeyCode::Char('O'), KeyModifiers::SHIFT)],
+                ),
+                ShortCut(
+                    AppCommand::PowerAll,
+                    vec![KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL)],
+                ),
+                ShortCut(
+                    AppCommand::QuickList,
+                    vec![KeyEvent::new(KeyCode::Char('T'), KeyModifiers::SHIFT)],
+                ),
+                ShortCut(
+                    AppCommand::Search,
+                    vec![KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL)],
+                ),
+                ShortCut(
+                    AppCommand::KeepPowered,
+                    vec![KeyEvent::new(KeyCode::Char('K'), KeyModifiers::SHIFT)],
+                ),
+            ])
+            .into_iter()
+            .chain(CONFIG.quick_devices.iter().map(|(key, device)| {
+                ShortCut(
+                    AppCommand::QuickDevice(device.clone()),
+                    vec![KeyEvent::new(KeyCode::Char(*key), KeyModifiers::NONE)],
+                )
+            }))
+            .collect(),
+        )
     }
 }
 
@@ -56,20 +139,34 @@ pub enum AdapterViewCommand {
     ToggleScan,
     TogglePairable,
     ToggleDiscoverable,
+    ToggleScanType,
     OpenMenu,
     OpenDevices,
     Info,
+    Summary,
+    Repeat,
+    WidenColumn,
+    NarrowColumn,
+    PrevColumn,
+    NextColumn,
 }
 impl Display for AdapterViewCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AdapterViewCommand::TogglePower => write!(f, "toggle power"),
-            AdapterViewCommand::ToggleScan => write!(f, "toggle scan"),
-            AdapterViewCommand::OpenMenu => write!(f, "open menu"),
-            AdapterViewCommand::Info => write!(f, "info"),
-            AdapterViewCommand::OpenDevices => write!(f, "open devices"),
-            AdapterViewCommand::TogglePairable => write!(f, "toggle pairable"),
-            AdapterViewCommand::ToggleDiscoverable => write!(f, "toggle discoverable"),
+            AdapterViewCommand::TogglePower => f.write_str(tr("toggle power")),
+            AdapterViewCommand::ToggleScan => f.write_str(tr("toggle scan")),
+            AdapterViewCommand::OpenMenu => f.write_str(tr("open menu")),
+            AdapterViewCommand::Info => f.write_str(tr("info")),
+            AdapterViewCommand::Summary => f.write_str(tr("summary")),
+            AdapterViewCommand::Repeat => f.write_str(tr("repeat last action")),
+            AdapterViewCommand::OpenDevices => f.write_str(tr("open devices")),
+            AdapterViewCommand::TogglePairable => f.write_str(tr("toggle pairable")),
+            AdapterViewCommand::ToggleDiscoverable => f.write_str(tr("toggle discoverable")),
+            AdapterViewCommand::ToggleScanType => f.write_str(tr("toggle active/passive scan")),
+            AdapterViewCommand::WidenColumn => f.write_str(tr("widen column")),
+            AdapterViewCommand::NarrowColumn => f.write_str(tr("narrow column")),
+            AdapterViewCommand::PrevColumn => f.write_str(tr("previous column")),
+            AdapterViewCommand::NextColumn => f.write_str(tr("next column")),
         }
     }
 }
@@ -97,6 +194,10 @@ impl KeyMap for AdapterViewKeyMap {
                 AdapterViewCommand::ToggleScan,
                 vec![KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE)],
             ),
+            ShortCut(
+                AdapterViewCommand::ToggleScanType,
+                vec![KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)],
+            ),
             ShortCut(
                 AdapterViewCommand::OpenMenu,
                 vec![KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE)],
@@ -109,6 +210,30 @@ impl KeyMap for AdapterViewKeyMap {
                 AdapterViewCommand::Info,
                 vec![KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE)],
             ),
+            ShortCut(
+                AdapterViewCommand::Summary,
+                vec![KeyEvent::new(KeyCode::Char('S'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                AdapterViewCommand::Repeat,
+                vec![KeyEvent::new(KeyCode::Char('.'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                AdapterViewCommand::WidenColumn,
+                vec![KeyEvent::new(KeyCode::Char('>'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                AdapterViewCommand::NarrowColumn,
+                vec![KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                AdapterViewCommand::PrevColumn,
+                vec![KeyEvent::new(KeyCode::Left, KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                AdapterViewCommand::NextColumn,
+                vec![KeyEvent::new(KeyCode::Right, KeyModifiers::NONE)],
+            ),
         ]))
     }
 }
@@ -124,20 +249,54 @@ pub enum DeviceViewCommand {
     Info,
     ShowAdapters,
     Monitor,
+    Gatt,
+    BatteryHistory,
+    ToggleOrder,
+    ToggleMark,
+    PairMarked,
+    Filter,
+    Jump,
+    ConnectAddress,
+    PairElsewhere,
+    ShowNew,
+    Acknowledge,
+    AcknowledgeAll,
+    Repeat,
+    WidenColumn,
+    NarrowColumn,
+    PrevColumn,
+    NextColumn,
 }
 impl Display for DeviceViewCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            DeviceViewCommand::ToggleConnect => write!(f, "toggle connect"),
-            DeviceViewCommand::ToggleBlock => write!(f, "toggle block"),
-            DeviceViewCommand::ToggleTrust => write!(f, "toggle trust"),
-            DeviceViewCommand::ToggleScan => write!(f, "toggle scan"),
-            DeviceViewCommand::Pair => write!(f, "pair"),
-            DeviceViewCommand::Unpair => write!(f, "unpair"),
-            DeviceViewCommand::OpenMenu => write!(f, "open menu"),
-            DeviceViewCommand::Info => write!(f, "info"),
-            DeviceViewCommand::ShowAdapters => write!(f, "show adapters"),
-            DeviceViewCommand::Monitor => write!(f, "monitor"),
+            DeviceViewCommand::ToggleConnect => f.write_str(tr("toggle connect")),
+            DeviceViewCommand::ToggleBlock => f.write_str(tr("toggle block")),
+            DeviceViewCommand::ToggleTrust => f.write_str(tr("toggle trust")),
+            DeviceViewCommand::ToggleScan => f.write_str(tr("toggle scan")),
+            DeviceViewCommand::Pair => f.write_str(tr("pair")),
+            DeviceViewCommand::Unpair => f.write_str(tr("unpair")),
+            DeviceViewCommand::OpenMenu => f.write_str(tr("open menu")),
+            DeviceViewCommand::Info => f.write_str(tr("info")),
+            DeviceViewCommand::ShowAdapters => f.write_str(tr("show adapters")),
+            DeviceViewCommand::Monitor => f.write_str(tr("monitor")),
+            DeviceViewCommand::Gatt => f.write_str(tr("GATT characteristics")),
+            DeviceViewCommand::BatteryHistory => f.write_str(tr("battery history")),
+            DeviceViewCommand::ToggleOrder => f.write_str(tr("toggle nearest first")),
+            DeviceViewCommand::ToggleMark => f.write_str(tr("mark for batch pairing")),
+            DeviceViewCommand::PairMarked => f.write_str(tr("pair and trust marked")),
+            DeviceViewCommand::Filter => f.write_str(tr("filter")),
+            DeviceViewCommand::Jump => f.write_str(tr("jump to row or name")),
+            DeviceViewCommand::ConnectAddress => f.write_str(tr("connect by address")),
+            DeviceViewCommand::PairElsewhere => f.write_str(tr("pair on other adapter")),
+            DeviceViewCommand::ShowNew => f.write_str(tr("show new devices, again to acknowledge")),
+            DeviceViewCommand::Acknowledge => f.write_str(tr("clear new flag")),
+            DeviceViewCommand::AcknowledgeAll => f.write_str(tr("clear every new flag")),
+            DeviceViewCommand::Repeat => f.write_str(tr("repeat last action")),
+            DeviceViewCommand::WidenColumn => f.write_str(tr("widen column")),
+            DeviceViewCommand::NarrowColumn => f.write_str(tr("narrow column")),
+            DeviceViewCommand::PrevColumn => f.write_str(tr("previous column")),
+            DeviceViewCommand::NextColumn => f.write_str(tr("next column")),
         }
     }
 }
@@ -191,10 +350,112 @@ impl KeyMap for DeviceViewKeyMap {
                 DeviceViewCommand::Monitor,
                 vec![KeyEvent::new(KeyCode::Char('m'), KeyModifiers::SHIFT)],
             ),
+            ShortCut(
+                DeviceViewCommand::Gatt,
+                vec![KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                DeviceViewCommand::BatteryHistory,
+                vec![KeyEvent::new(KeyCode::Char('B'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                DeviceViewCommand::ToggleOrder,
+                vec![KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::ToggleMark,
+                vec![KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::PairMarked,
+                vec![KeyEvent::new(KeyCode::Char('P'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                DeviceViewCommand::Filter,
+                vec![KeyEvent::new(KeyCode::Char('/'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::Jump,
+                vec![KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::ConnectAddress,
+                vec![KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::PairElsewhere,
+                vec![KeyEvent::new(KeyCode::Char('v'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::ShowNew,
+                vec![KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                DeviceViewCommand::Acknowledge,
+                vec![KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::AcknowledgeAll,
+                vec![KeyEvent::new(KeyCode::Char('Z'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                DeviceViewCommand::Repeat,
+                vec![KeyEvent::new(KeyCode::Char('.'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::WidenColumn,
+                vec![KeyEvent::new(KeyCode::Char('>'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::NarrowColumn,
+                vec![KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::PrevColumn,
+                vec![KeyEvent::new(KeyCode::Left, KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DeviceViewCommand::NextColumn,
+                vec![KeyEvent::new(KeyCode::Right, KeyModifiers::NONE)],
+            ),
         ]))
     }
 }
 
+/// Short human readable form of a key, e.g. `ctrl+c` or `Enter`
+pub fn key_label(key: &KeyEvent) -> String {
+    let mut label = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        label.push_str(tr("ctrl+"));
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        label.push_str(tr("alt+"));
+    }
+    match key.code {
+        KeyCode::Char(' ') => label.push_str(tr("space")),
+        KeyCode::Char(c) => label.push(c),
+        code => label.push_str(&code.to_string()),
+    }
+    label
+}
+
+/// The bindings of `kind`'s view together with the global ones, as `(keys, description)`
+pub fn bindings_for(kind: ViewKind) -> Vec<(String, String)> {
+    fn collect<T: Display>(shortcuts: Vec<ShortCut<T>>) -> Vec<(String, String)> {
+        shortcuts
+            .into_iter()
+            .map(|sc| (sc.1.iter().map(key_label).join("/"), sc.0.to_string()))
+            .collect()
+    }
+    let mut bindings = match kind {
+        ViewKind::AdapterView => collect(AdapterViewKeyMap::default().0),
+        ViewKind::DeviceView => collect(DeviceViewKeyMap::default().0),
+        _ => vec![],
+    };
+    bindings.extend(collect(AppKeyMap::default().0));
+    bindings
+}
+
 pub fn get_keymap_collisions() -> Vec<(KeyEvent, Vec<String>)> {
     let mut map: HashMap<KeyEvent, Vec<String>> = HashMap::new();
     for sc in AppKeyMap::default().0 {
@@ -3,15 +3,91 @@
 
 pub mod app;
 pub mod bt_manager;
+pub mod cli;
+pub mod commands;
 pub mod config;
+pub mod errors;
+pub mod filter;
 pub mod globals;
 pub mod helpers;
+pub mod hexdump;
+pub mod i18n;
+pub mod identity;
 pub mod keymaps;
+pub mod metrics;
 pub mod models;
+pub mod mouse;
+pub mod mqtt;
+pub mod privacy;
+pub mod rfkill;
+pub mod service;
+pub mod state;
 pub mod theme;
+pub mod ticker;
+pub mod tracker;
+pub mod uuids;
 pub mod views;
 
+use std::fs;
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use crate::cli::{Cli, Command};
+use crate::globals::CONFIG_SOURCE;
+use crate::models::DeviceAction;
+
 #[tokio::main]
-async fn main() {
-    app::App::new().await.init().await.run().await.unwrap();
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // a config asked for by name must be there, unlike the default one
+    if let Some(path) = &cli.config
+        && let Err(e) = fs::read_to_string(path)
+    {
+        eprintln!("Could not read the config {}: {e}", path.display());
+        return ExitCode::FAILURE;
+    }
+    let _ = CONFIG_SOURCE.set(cli.config_source());
+    match cli.command {
+        Some(Command::Completions { shell }) => commands::completions(shell).await,
+        Some(Command::Wait { device, timeout }) => commands::wait(device, timeout).await,
+        Some(Command::List { filter }) => commands::list(filter).await,
+        Some(Command::Power { state }) => commands::power(state).await,
+        Some(Command::Scan { duration, format }) => commands::scan(duration, format).await,
+        Some(Command::Monitor { device, format }) => commands::monitor(device, format).await,
+        Some(Command::Track) => commands::track().await,
+        Some(Command::Trust(targets)) => {
+            commands::device_action(targets, DeviceAction::SetTrusted(true)).await
+        }
+        Some(Command::Block(targets)) => {
+            commands::device_action(targets, DeviceAction::SetBlocked(true)).await
+        }
+        Some(Command::Remove(targets)) => {
+            commands::device_action(targets, DeviceAction::SetPaired(false)).await
+        }
+        Some(Command::ExportState { path }) => commands::export_state(path).await,
+        Some(Command::ImportState { path }) => commands::import_state(path).await,
+        None => {
+            let app = match app::App::new().await {
+                Ok(app) => app,
+                Err(e) => {
+                    eprintln!("{}", errors::friendly_message(&e));
+                    if let Some(state) = service::service_state().await {
+                        eprintln!(
+                            "{} is {state}, start it with `systemctl start {}`",
+                            service::SERVICE,
+                            service::SERVICE
+                        );
+                    }
+                    return ExitCode::FAILURE;
+                }
+            };
+            app.init(cli.deep_link())
+                .await
+                .run(cli.inline, cli.summary)
+                .await
+                .unwrap();
+            ExitCode::SUCCESS
+        }
+    }
 }
@@ -0,0 +1,146 @@
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::models::{Adapter, Device};
+
+/// Prometheus text exposition of the adapters and their devices
+pub fn render(adapters: &[Adapter], connect_failures: u64, dropped_events: u64) -> String {
+    let mut page = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        let _ = writeln!(page, "# HELP {name} {help}");
+        let _ = writeln!(page, "# TYPE {name} {kind}");
+        for (labels, value) in samples {
+            let _ = writeln!(page, "{name}{labels} {value}");
+        }
+    };
+    let devices = adapters
+        .iter()
+        .flat_map(|a| a.devices.iter().map(move |d| (a, d)))
+        .collect::<Vec<_>>();
+    let device_labels = |a: &Adapter, d: &Device| {
+        format!(
+            "{{adapter=\"{}\",address=\"{}\",name=\"{}\"}}",
+            escape(&a.name),
+            d.id,
+            escape(&d.alias)
+        )
+    };
+
+    metric(
+        "bluerat_connected_devices",
+        "gauge",
+        "Devices connected to the adapter",
+        adapters
+            .iter()
+            .map(|a| {
+                let labels = format!("{{adapter=\"{}\"}}", escape(&a.name));
+                (labels, a.connections.to_string())
+            })
+            .collect(),
+    );
+    metric(
+        "bluerat_device_battery_percent",
+        "gauge",
+        "Battery level reported by the device",
+        devices
+            .iter()
+            .filter_map(|(a, d)| Some((device_labels(a, d), d.battery?.to_string())))
+            .collect(),
+    );
+    metric(
+        "bluerat_device_rssi_dbm",
+        "gauge",
+        "Signal strength of the device while it is seen by a scan",
+        devices
+            .iter()
+            .filter_map(|(a, d)| Some((device_labels(a, d), d.rssi?.to_string())))
+            .collect(),
+    );
+    metric(
+        "bluerat_connect_failures_total",
+        "counter",
+        "Connection attempts that failed since startup",
+        vec![(String::new(), connect_failures.to_string())],
+    );
+    metric(
+        "bluerat_dropped_events_total",
+        "counter",
+        "Discovery and device events dropped because the interface fell behind",
+        vec![(String::new(), dropped_events.to_string())],
+    );
+    page
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Answers `GET /metrics` with the latest rendered page, in the background
+pub async fn serve(addr: SocketAddr, page: Arc<Mutex<String>>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    let _ = tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let page = page.clone();
+            let _ = tokio::spawn(async move {
+                let mut request = [0; 1024];
+                let Ok(n) = stream.read(&mut request).await else {
+                    return;
+                };
+                let response = match request[..n].starts_with(b"GET /metrics") {
+                    true => {
+                        let body = page.lock().unwrap().clone();
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                    }
+                    false => {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string()
+                    }
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DeviceCategory;
+
+    #[test]
+    fn renders_gauges_and_counter() {
+        let device = Device {
+            kind: "audio-headset".to_string(),
+            category: DeviceCategory::Audio,
+            battery: Some(80),
+            is_connected: true,
+            is_trusted: true,
+            is_paired: true,
+            ..Device::fake([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0x01], "Buds \"Pro\"")
+        };
+        let adapter = Adapter {
+            devices: vec![device],
+            connections: 1,
+            ..Adapter::fake([0, 0, 0, 0, 0, 1], "hci0")
+        };
+        let page = render(&[adapter], 2, 0);
+        assert!(page.contains("bluerat_connected_devices{adapter=\"hci0\"} 1\n"));
+        assert!(page.contains(
+            "bluerat_device_battery_percent{adapter=\"hci0\",address=\"AA:BB:CC:DD:EE:01\",name=\"Buds \\\"Pro\\\"\"} 80\n"
+        ));
+        assert!(!page.contains("bluerat_device_rssi_dbm{"));
+        assert!(page.contains("bluerat_connect_failures_total 2\n"));
+        assert!(page.contains("bluerat_dropped_events_total 0\n"));
+    }
+}
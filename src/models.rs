@@ -1,17 +1,30 @@
+use std::collections::HashSet;
 use std::fmt::Display;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use std::vec;
 
-use bluer::Address;
+use bluer::gatt::remote::CharacteristicFlags;
+use bluer::gatt::WriteOp;
+use bluer::{Address, DiscoveryTransport};
+use chrono::{DateTime, Local};
 use futures::future::join_all;
 use itertools::Itertools;
 use ratatui::layout::{Alignment, Constraint};
-use ratatui::style::{Color, Style};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui_helpers::stateful_table::Tabular;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
 
+use crate::filter::address_matches;
 use crate::globals::CONFIG;
+use crate::i18n::tr;
+use crate::privacy::privacy;
+use crate::rfkill::{rfkill_state, Rfkill};
+use crate::theme::{CellSpans, ColumnWidths, Icons};
+use crate::uuids::{is_custom, uuid_name};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct AdapterId(pub Address);
 impl Display for AdapterId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -19,7 +32,7 @@ impl Display for AdapterId {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct DeviceId(pub Address);
 impl Display for DeviceId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -27,7 +40,21 @@ impl Display for DeviceId {
     }
 }
 
-#[derive(Clone, Debug)]
+impl DeviceId {
+    /// Whether `allowed_devices` lets the device be shown and acted on
+    pub fn is_allowed(&self) -> bool {
+        CONFIG.allowed_devices.is_empty()
+            || CONFIG
+                .allowed_devices
+                .iter()
+                .any(|p| address_matches(p, &self.to_string()))
+    }
+}
+
+pub static ADAPTER_COLUMNS: ColumnWidths<5> = ColumnWidths::new();
+pub static DEVICE_COLUMNS: ColumnWidths<2> = ColumnWidths::new();
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Adapter {
     pub id: AdapterId,
     pub name: String,
@@ -37,8 +64,36 @@ pub struct Adapter {
     pub is_discoverable: bool,
     pub is_scanning: bool,
     pub connections: usize,
+    #[serde(skip)]
+    pub is_cached: bool,
+    /// Seconds the adapter stays discoverable for, 0 meaning forever
+    #[serde(skip)]
+    pub discoverable_timeout: u32,
+    /// Only known when the adapter was seen turning discoverable
+    #[serde(skip)]
+    pub discoverable_until: Option<Instant>,
+    #[serde(skip)]
+    pub rfkill: Rfkill,
 }
 impl Adapter {
+    /// A powered on adapter with no devices, for the tests to adjust
+    #[cfg(test)]
+    pub fn fake(address: [u8; 6], name: &str) -> Self {
+        Self {
+            id: AdapterId(Address::new(address)),
+            name: name.to_string(),
+            devices: vec![],
+            is_on: true,
+            is_pairable: false,
+            is_discoverable: false,
+            is_scanning: false,
+            connections: 0,
+            is_cached: false,
+            discoverable_timeout: 0,
+            discoverable_until: None,
+            rfkill: Default::default(),
+        }
+    }
     pub async fn from(adapter: bluer::Adapter) -> Self {
         let devices = adapter
             .device_addresses()
@@ -47,7 +102,8 @@ impl Adapter {
             .into_iter()
             .map(|addr| adapter.device(addr).unwrap())
             .map(|d| async move { Device::from(d).await });
-        let devices = join_all(devices).await;
+        let mut devices = join_all(devices).await;
+        devices.retain(|d| d.id.is_allowed());
 
         Self {
             id: AdapterId(adapter.address().await.unwrap()),
@@ -57,13 +113,31 @@ impl Adapter {
             is_discoverable: adapter.is_discoverable().await.unwrap(),
             is_scanning: adapter.is_discovering().await.unwrap(),
             connections: devices.iter().filter(|d| d.is_connected).count(),
+            discoverable_timeout: adapter.discoverable_timeout().await.unwrap_or(0),
+            discoverable_until: None,
+            rfkill: rfkill_state(adapter.name()),
             devices,
+            is_cached: false,
         }
     }
+    /// Powered state, or the rfkill block that keeps the adapter off
+    pub fn power_label(&self) -> String {
+        match self.rfkill {
+            Rfkill::Unblocked if self.is_on => "On".to_string(),
+            Rfkill::Unblocked => "Off".to_string(),
+            blocked => blocked.to_string(),
+        }
+    }
+    pub fn discoverable_left(&self) -> Option<Duration> {
+        self.discoverable_until
+            .filter(|_| self.is_discoverable)
+            .map(|until| until.saturating_duration_since(Instant::now()))
+    }
     pub fn get_info_line(&self) -> String {
         [
             format!("Name: {}", self.name),
             format!("Address: {}", self.id),
+            format!("Power: {}", self.power_label()),
         ]
         .into_iter()
         .chain(
@@ -71,6 +145,7 @@ impl Adapter {
                 (self.is_discoverable, "Discoverable"),
                 (self.is_pairable, "Pairable"),
                 (self.is_scanning, "Scanning"),
+                (self.is_cached, "Cached"),
             ]
             .into_iter()
             .filter(|(f, _)| *f)
@@ -79,18 +154,181 @@ impl Adapter {
         .map(|s| format!("[{s}]"))
         .join(" | ")
     }
+    pub fn get_info(&self, capabilities: &AdapterCapabilities) -> String {
+        [
+            format!("Name: {}", self.name),
+            format!("Address: {}", self.id),
+            format!("Power: {}", self.power_label()),
+            format!("Connections: {}", self.connections),
+        ]
+        .into_iter()
+        .chain(capabilities.lines())
+        .join("\n")
+    }
     pub fn get_device(&self, id: &DeviceId) -> Option<&Device> {
         self.devices.iter().find(|d| d.id == *id)
     }
     pub fn get_device_mut(&mut self, id: &DeviceId) -> Option<&mut Device> {
         self.devices.iter_mut().find(|d| d.id == *id)
     }
+    /// Applies a changed property to the cached adapter, false for the ones it doesn't hold
+    pub fn apply(&mut self, prop: &bluer::AdapterProperty) -> bool {
+        use bluer::AdapterProperty as P;
+        match prop {
+            P::Powered(on) => self.is_on = *on,
+            P::Pairable(on) => self.is_pairable = *on,
+            P::Discoverable(on) => self.is_discoverable = *on,
+            P::Discovering(on) => self.is_scanning = *on,
+            P::DiscoverableTimeout(secs) => self.discoverable_timeout = *secs,
+            _ => return false,
+        }
+        true
+    }
+}
+/// What the controller supports for low energy and how it shows itself, read on demand
+#[derive(Clone, Debug, Default)]
+pub struct AdapterCapabilities {
+    pub address_type: &'static str,
+    /// `None` when btmgmt could not tell
+    pub privacy: Option<bool>,
+    pub roles: Vec<&'static str>,
+    pub includes: Vec<String>,
+    pub features: Vec<String>,
+    pub secondary_channels: Vec<String>,
+    pub max_adv_instances: u8,
+    pub active_adv_instances: u8,
+    pub max_adv_len: Option<u8>,
+}
+impl AdapterCapabilities {
+    pub async fn read(adapter: &bluer::Adapter) -> Self {
+        let sorted = |set: Vec<String>| set.into_iter().sorted().collect_vec();
+        let max_adv_instances = adapter.supported_advertising_instances().await.unwrap_or(0);
+        // bluer doesn't expose the experimental Roles property, advertising
+        // instances are only offered by controllers that can be peripherals
+        let roles = match max_adv_instances {
+            0 => vec!["central"],
+            _ => vec!["central", "peripheral"],
+        };
+        let name = adapter.name();
+        Self {
+            address_type: match adapter.address_type().await {
+                Ok(bluer::AddressType::LeRandom) => "random",
+                Ok(_) => "public",
+                Err(_) => "unknown",
+            },
+            privacy: privacy(name).await,
+            roles,
+            includes: sorted(
+                adapter
+                    .supported_advertising_system_includes()
+                    .await
+                    .map(|s| s.iter().map(|f| f.to_string()).collect())
+                    .unwrap_or_default(),
+            ),
+            features: sorted(
+                adapter
+                    .supported_advertising_features()
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|s| s.iter().map(|f| f.to_string()).collect())
+                    .unwrap_or_default(),
+            ),
+            secondary_channels: sorted(
+                adapter
+                    .supported_advertising_secondary_channels()
+                    .await
+                    .map(|s| s.iter().map(|c| c.to_string()).collect())
+                    .unwrap_or_default(),
+            ),
+            max_adv_instances,
+            active_adv_instances: adapter.active_advertising_instances().await.unwrap_or(0),
+            max_adv_len: adapter
+                .supported_advertising_capabilities()
+                .await
+                .ok()
+                .flatten()
+                .and_then(|c| c.max_adv_len),
+        }
+    }
+    pub fn lines(&self) -> Vec<String> {
+        let list = |items: &[String]| match items.is_empty() {
+            true => "none".to_string(),
+            false => items.join(", "),
+        };
+        let privacy = match self.privacy {
+            Some(true) => "on",
+            Some(false) => "off",
+            None => "unknown, needs btmgmt",
+        };
+        [
+            format!("Address type: {}", self.address_type),
+            format!("LE privacy: {privacy}"),
+            format!("Roles: {}", self.roles.join(", ")),
+            format!(
+                "Advertisement instances: {} of {} in use",
+                self.active_adv_instances, self.max_adv_instances
+            ),
+            format!("Advertising includes: {}", list(&self.includes)),
+            format!("LE features: {}", list(&self.features)),
+            format!("Secondary channels: {}", list(&self.secondary_channels)),
+        ]
+        .into_iter()
+        .chain(
+            self.max_adv_len
+                .map(|len| format!("Max advertisement length: {len} bytes")),
+        )
+        .collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AdapterColumn {
+    Power,
+    Name,
+    Connections,
+    State,
+    Address,
+}
+impl AdapterColumn {
+    /// The columns listed in `adapter_columns`, in the configured order
+    pub fn configured() -> Vec<Self> {
+        CONFIG
+            .adapter_columns
+            .iter()
+            .filter_map(|c| match c.as_str() {
+                "power" => Some(Self::Power),
+                "name" => Some(Self::Name),
+                "connections" => Some(Self::Connections),
+                "state" => Some(Self::State),
+                "address" => Some(Self::Address),
+                _ => None,
+            })
+            .collect()
+    }
+}
+impl Display for AdapterColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AdapterColumn::Power => "Power",
+            AdapterColumn::Name => "Name",
+            AdapterColumn::Connections => "Connections",
+            AdapterColumn::State => "State",
+            AdapterColumn::Address => "Address",
+        };
+        f.write_str(tr(name))
+    }
+}
+impl Display for Adapter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
 }
 impl Tabular for Adapter {
     type Value = Self;
-    type ColumnValue = ();
+    type ColumnValue = AdapterColumn;
     fn column_values() -> Vec<Self::ColumnValue> {
-        vec![]
+        AdapterColumn::configured()
     }
 
     fn value(&self) -> Self::Value {
@@ -98,46 +336,72 @@ impl Tabular for Adapter {
     }
 
     fn content(&self) -> Vec<String> {
+        let discoverable = match self.discoverable_left() {
+            Some(left) => format!("Discoverable ({}s)", left.as_secs()),
+            None => "Discoverable".to_string(),
+        };
         let flags = [
-            (self.is_discoverable, "Discoverable"),
+            (self.is_discoverable, discoverable.as_str()),
             (self.is_pairable, "Pairable"),
             (self.is_scanning, "Scanning"),
+            (self.is_cached, "Cached"),
         ]
         .into_iter()
         .filter(|(f, _)| *f)
         .map(|(_, s)| s.to_string())
         .join(", ");
 
-        vec![
-            format!("{}", if self.is_on { "On" } else { "Off" }),
-            format!("{}", self.name),
-            format!("{}/{}", self.connections, self.devices.len()),
-            format!("{}", flags),
-        ]
+        Self::column_values()
+            .into_iter()
+            .map(|col| match col {
+                AdapterColumn::Power => self.power_label(),
+                AdapterColumn::Name => self.name.clone(),
+                AdapterColumn::Connections => {
+                    format!("{}/{}", self.connections, self.devices.len())
+                }
+                AdapterColumn::State => flags.clone(),
+                AdapterColumn::Address => self.id.to_string(),
+            })
+            .collect()
     }
     fn column_constraints() -> Vec<fn(u16) -> Constraint> {
-        vec![
-            Constraint::Length,
-            Constraint::Length,
-            Constraint::Length,
-            Constraint::Fill,
-        ]
+        Self::column_values()
+            .into_iter()
+            .map(|col| -> fn(u16) -> Constraint {
+                match col {
+                    AdapterColumn::Power => {
+                        |w| Constraint::Length(ADAPTER_COLUMNS.fit(0, w, 3, 12))
+                    }
+                    AdapterColumn::Name => |w| Constraint::Length(ADAPTER_COLUMNS.fit(1, w, 8, 32)),
+                    AdapterColumn::Connections => {
+                        |w| Constraint::Length(ADAPTER_COLUMNS.fit(2, w, 3, 16))
+                    }
+                    AdapterColumn::State => Constraint::Fill,
+                    AdapterColumn::Address => {
+                        |w| Constraint::Length(ADAPTER_COLUMNS.fit(4, w, 17, 17))
+                    }
+                }
+            })
+            .collect()
     }
     fn column_names() -> Option<Vec<String>> {
-        Some(vec![
-            "Power".to_string(),
-            "Name".to_string(),
-            "Connections".to_string(),
-            "State".to_string(),
-        ])
+        Some(
+            Self::column_values()
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        )
     }
     fn column_alignments() -> Option<Vec<Alignment>> {
-        Some(vec![
-            Alignment::Center,
-            Alignment::Center,
-            Alignment::Center,
-            Alignment::Right,
-        ])
+        Some(
+            Self::column_values()
+                .into_iter()
+                .map(|col| match col {
+                    AdapterColumn::State => Alignment::Right,
+                    _ => Alignment::Center,
+                })
+                .collect(),
+        )
     }
     fn style(&self) -> Style {
         let mut style = Style::default();
@@ -154,8 +418,11 @@ impl Tabular for Adapter {
 pub enum AdapterAction {
     SetPowered(bool),
     SetScanning(bool),
+    /// Starts a discovery restricted to one transport
+    ScanOnly(DiscoveryTransport),
     SetDiscoverable(bool),
     SetPairable(bool),
+    SetPrivacy(bool),
     Info,
 }
 impl AdapterAction {
@@ -163,25 +430,55 @@ impl AdapterAction {
         match self {
             AdapterAction::SetPowered(_) => "p".to_string(),
             AdapterAction::SetScanning(_) => "s".to_string(),
+            AdapterAction::ScanOnly(DiscoveryTransport::BrEdr) => "c".to_string(),
+            AdapterAction::ScanOnly(_) => "l".to_string(),
             AdapterAction::SetDiscoverable(_) => "d".to_string(),
             AdapterAction::SetPairable(_) => "p".to_string(),
+            AdapterAction::SetPrivacy(_) => "v".to_string(),
             AdapterAction::Info => "i".to_string(),
         }
     }
+    /// What the action did to the adapter, once it succeeded, nothing for the read-only ones
+    pub fn done(&self, adapter: &str) -> Option<String> {
+        let msg = match self {
+            AdapterAction::SetPowered(true) => format!("Powered on {adapter}"),
+            AdapterAction::SetPowered(false) => format!("Powered off {adapter}"),
+            AdapterAction::SetScanning(true) => format!("Started scanning on {adapter}"),
+            AdapterAction::SetScanning(false) => format!("Stopped scanning on {adapter}"),
+            AdapterAction::ScanOnly(_) => format!("Started {self} on {adapter}"),
+            AdapterAction::SetDiscoverable(true) => format!("{adapter} is discoverable"),
+            AdapterAction::SetDiscoverable(false) => format!("{adapter} is no longer discoverable"),
+            AdapterAction::SetPairable(true) => format!("{adapter} is pairable"),
+            AdapterAction::SetPairable(false) => format!("{adapter} is no longer pairable"),
+            AdapterAction::SetPrivacy(true) => format!("Enabled LE privacy on {adapter}"),
+            AdapterAction::SetPrivacy(false) => format!("Disabled LE privacy on {adapter}"),
+            AdapterAction::Info => return None,
+        };
+        Some(msg)
+    }
+    /// Untranslated name, as kept in the action log
+    pub fn label(&self) -> &'static str {
+        match self {
+            AdapterAction::SetPowered(true) => "Power On",
+            AdapterAction::SetPowered(false) => "Power Off",
+            AdapterAction::SetScanning(true) => "Start Scanning",
+            AdapterAction::SetScanning(false) => "Stop Scanning",
+            AdapterAction::ScanOnly(DiscoveryTransport::Le) => "Scan (LE only)",
+            AdapterAction::ScanOnly(DiscoveryTransport::BrEdr) => "Scan (Classic only)",
+            AdapterAction::ScanOnly(_) => "Scan",
+            AdapterAction::SetDiscoverable(true) => "Set Discoverable",
+            AdapterAction::SetDiscoverable(false) => "Set Not Discoverable",
+            AdapterAction::SetPairable(true) => "Set Pairable",
+            AdapterAction::SetPairable(false) => "Set Not Pairable",
+            AdapterAction::SetPrivacy(true) => "Enable LE Privacy",
+            AdapterAction::SetPrivacy(false) => "Disable LE Privacy",
+            AdapterAction::Info => "Info",
+        }
+    }
 }
 impl Display for AdapterAction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AdapterAction::SetPowered(true) => write!(f, "Power On"),
-            AdapterAction::SetPowered(false) => write!(f, "Power Off"),
-            AdapterAction::SetScanning(true) => write!(f, "Start Scanning"),
-            AdapterAction::SetScanning(false) => write!(f, "Stop Scanning"),
-            AdapterAction::SetDiscoverable(true) => write!(f, "Set Discoverable"),
-            AdapterAction::SetDiscoverable(false) => write!(f, "Set Not Discoverable"),
-            AdapterAction::SetPairable(true) => write!(f, "Set Pairable"),
-            AdapterAction::SetPairable(false) => write!(f, "Set Not Pairable"),
-            AdapterAction::Info => write!(f, "Info"),
-        }
+        f.write_str(tr(self.label()))
     }
 }
 impl Tabular for AdapterAction {
@@ -204,36 +501,287 @@ impl Tabular for AdapterAction {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum DeviceCategory {
+    Audio,
+    Input,
+    Phone,
+    Sensor,
+    #[default]
+    Other,
+}
+impl DeviceCategory {
+    pub fn decode(icon: &str, appearance: Option<u16>) -> Self {
+        match icon {
+            "audio-card" | "audio-headset" | "audio-headphones" | "multimedia-player" => {
+                return Self::Audio;
+            }
+            "input-keyboard" | "input-mouse" | "input-gaming" | "input-tablet" => {
+                return Self::Input;
+            }
+            "phone" => return Self::Phone,
+            _ => {}
+        }
+        // GAP appearance category, the upper 10 bits of the value
+        match appearance.map(|a| a >> 6) {
+            Some(0x01) => Self::Phone,
+            Some(0x0F) => Self::Input,
+            Some(0x0C..=0x0E | 0x10..=0x12 | 0x15) => Self::Sensor,
+            Some(0x25 | 0x28 | 0x29) => Self::Audio,
+            _ => Self::Other,
+        }
+    }
+    pub fn color(&self) -> Option<&'static str> {
+        match self {
+            DeviceCategory::Audio => Some(&CONFIG.theme.audio_color),
+            DeviceCategory::Input => Some(&CONFIG.theme.input_color),
+            DeviceCategory::Phone => Some(&CONFIG.theme.phone_color),
+            DeviceCategory::Sensor => Some(&CONFIG.theme.sensor_color),
+            DeviceCategory::Other => None,
+        }
+    }
+}
+impl FromStr for DeviceCategory {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "audio" => Ok(Self::Audio),
+            "input" => Ok(Self::Input),
+            "phone" => Ok(Self::Phone),
+            "sensor" => Ok(Self::Sensor),
+            "other" => Ok(Self::Other),
+            _ => Err(format!("{s:?} is not a device category")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum DeviceOrder {
+    #[default]
+    Address,
+    Nearest,
+}
+impl DeviceOrder {
+    pub fn next(&self) -> Self {
+        match self {
+            DeviceOrder::Address => DeviceOrder::Nearest,
+            DeviceOrder::Nearest => DeviceOrder::Address,
+        }
+    }
+}
+impl Display for DeviceOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceOrder::Address => write!(f, "by address"),
+            DeviceOrder::Nearest => write!(f, "nearest first"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Device {
     pub id: DeviceId,
     pub alias: String,
     pub kind: String,
+    #[serde(default)]
+    pub category: DeviceCategory,
     pub battery: Option<u8>,
+    #[serde(skip)]
+    pub rssi: Option<i16>,
+    /// Advertised service uuids, lowercase and hyphenated
+    #[serde(default)]
+    pub uuids: Vec<String>,
     pub is_connected: bool,
     pub is_trusted: bool,
     pub is_paired: bool,
     pub is_blocked: bool,
+    #[serde(skip)]
     pub is_new: bool,
+    #[serde(skip)]
+    pub is_cached: bool,
+    #[serde(skip)]
+    pub is_marked: bool,
+    /// Identity address a resolvable private address resolved to
+    #[serde(skip)]
+    pub identity: Option<DeviceId>,
 }
 impl Device {
+    /// An unknown and unpaired device out of range, for the tests to adjust
+    #[cfg(test)]
+    pub fn fake(address: [u8; 6], alias: &str) -> Self {
+        Self {
+            id: DeviceId(Address::new(address)),
+            alias: alias.to_string(),
+            kind: "Unknown".to_string(),
+            category: DeviceCategory::Other,
+            battery: None,
+            rssi: None,
+            uuids: vec![],
+            is_connected: false,
+            is_trusted: false,
+            is_paired: false,
+            is_blocked: false,
+            is_new: false,
+            is_cached: false,
+            is_marked: false,
+            identity: None,
+        }
+    }
     pub async fn from(device: bluer::Device) -> Self {
+        let kind = device
+            .icon()
+            .await
+            .unwrap_or_default()
+            .unwrap_or("Unknown".to_string());
+        let appearance = device.appearance().await.unwrap_or_default();
         Self {
             id: DeviceId(device.address()),
             alias: device.alias().await.unwrap(),
-            kind: device
-                .icon()
+            category: DeviceCategory::decode(&kind, appearance),
+            kind,
+            battery: device.battery_percentage().await.unwrap(),
+            rssi: device.rssi().await.unwrap_or_default(),
+            uuids: device
+                .uuids()
                 .await
                 .unwrap_or_default()
-                .unwrap_or("Unknown".to_string())
-                .to_string(),
-            battery: device.battery_percentage().await.unwrap(),
+                .unwrap_or_default()
+                .iter()
+                .map(|u| u.to_string())
+                .sorted()
+                .collect(),
             is_connected: device.is_connected().await.unwrap(),
             is_trusted: device.is_trusted().await.unwrap(),
-            is_paired: false,
+            is_paired: device.is_paired().await.unwrap_or(false),
             is_blocked: device.is_blocked().await.unwrap(),
             is_new: false,
+            is_cached: false,
+            is_marked: false,
+            identity: None,
+        }
+    }
+    pub fn get_info(&self) -> String {
+        [
+            format!("Name: {}", self.alias),
+            format!("Address: {}", self.id),
+            format!("Type: {}", self.kind),
+        ]
+        .into_iter()
+        .chain(self.identity.map(|id| format!("Identity: {id}")))
+        .chain(self.battery.map(|b| format!("Battery: {b}%")))
+        .chain(
+            [
+                (self.is_connected, "Connected"),
+                (self.is_paired, "Paired"),
+                (self.is_trusted, "Trusted"),
+                (self.is_blocked, "Blocked"),
+            ]
+            .into_iter()
+            .map(|(f, s)| format!("{s}: {}", if f { "yes" } else { "no" })),
+        )
+        .join("\n")
+    }
+    pub fn states(&self) -> Vec<&'static str> {
+        [
+            (self.is_connected, "connected"),
+            (self.is_paired, "paired"),
+            (self.is_trusted, "trusted"),
+            (self.is_blocked, "blocked"),
+            (self.is_new, "new"),
+            (self.is_cached, "cached"),
+            (self.is_marked, "marked"),
+        ]
+        .into_iter()
+        .filter(|(f, _)| *f)
+        .map(|(_, s)| s)
+        .collect()
+    }
+    /// Byte ranges of the flags standing out in the state column
+    pub fn cell_spans(&self) -> CellSpans {
+        let mut spans = vec![vec![], vec![], vec![]];
+        let mut start = 0;
+        for (flag, style) in self.state_flags() {
+            if let Some(style) = style {
+                spans[2].push((
+                    start..start + flag.len(),
+                    style.add_modifier(Modifier::BOLD),
+                ));
+            }
+            start += flag.len() + ", ".len();
         }
+        spans
+    }
+    fn state_flags(&self) -> Vec<(String, Option<Style>)> {
+        let color = |c: &str| Some(Style::default().fg(Color::from_str(c).unwrap()));
+        let battery = self
+            .battery
+            .map(|b| {
+                if Icons::enabled() {
+                    format!("{} {b}%", Icons::battery(b))
+                } else {
+                    format!("Battery {b}%")
+                }
+            })
+            .unwrap_or_default();
+        let signal = self.rssi.map(Icons::signal).unwrap_or_default();
+        [
+            (Icons::enabled() && self.rssi.is_some(), signal, None),
+            (
+                self.is_connected,
+                "Connected",
+                color(&CONFIG.theme.fg_connected_color),
+            ),
+            (self.battery.is_some(), battery.as_str(), None),
+            (self.is_paired, "Paired", None),
+            (
+                self.is_blocked,
+                "Blocked",
+                color(&CONFIG.theme.fg_blocked_color),
+            ),
+            (self.is_trusted, "Trusted", None),
+            (
+                self.is_new,
+                "New device",
+                color(&CONFIG.theme.fg_new_device_color),
+            ),
+            (self.is_cached, "Cached", None),
+            (self.is_marked, "Marked", None),
+        ]
+        .into_iter()
+        .filter(|(f, _, _)| *f)
+        .map(|(_, s, style)| (s.to_string(), style))
+        .collect()
+    }
+    /// Applies a changed property to the cached device, false for the ones it doesn't hold
+    pub fn apply(&mut self, prop: &bluer::DeviceProperty) -> bool {
+        use bluer::DeviceProperty as P;
+        match prop {
+            P::Alias(alias) => self.alias = alias.clone(),
+            P::Icon(icon) => {
+                self.kind = icon.clone();
+                // the appearance the category may come from isn't kept
+                let category = DeviceCategory::decode(icon, None);
+                if category != DeviceCategory::Other {
+                    self.category = category;
+                }
+            }
+            P::Appearance(appearance) => {
+                if self.category == DeviceCategory::Other {
+                    self.category = DeviceCategory::decode(&self.kind, Some(*appearance));
+                }
+            }
+            P::BatteryPercentage(level) => self.battery = Some(*level),
+            P::Rssi(rssi) => self.rssi = Some(*rssi),
+            P::Uuids(uuids) => {
+                self.uuids = uuids.iter().map(|u| u.to_string()).sorted().collect();
+            }
+            P::Connected(on) => self.is_connected = *on,
+            P::Trusted(on) => self.is_trusted = *on,
+            P::Paired(on) => self.is_paired = *on,
+            P::Blocked(on) => self.is_blocked = *on,
+            _ => return false,
+        }
+        true
     }
     pub async fn from_new(device: bluer::Device) -> Self {
         let mut new = Self::from(device).await;
@@ -251,44 +799,37 @@ impl Tabular for Device {
         self.clone()
     }
     fn content(&self) -> Vec<String> {
-        let battery = self
-            .battery
-            .map(|b| format!("Battery {b}%"))
-            .unwrap_or_default();
-        let flags = [
-            (self.is_connected, "Connected"),
-            (self.battery.is_some(), battery.as_str()),
-            (self.is_paired, "Paired"),
-            (self.is_blocked, "Blocked"),
-            (self.is_trusted, "Trusted"),
-            (self.is_new, "New device"),
-        ]
-        .into_iter()
-        .filter(|(f, _)| *f)
-        .map(|(_, s)| s.to_string())
-        .join(", ");
+        let flags = self.state_flags().into_iter().map(|(s, _)| s).join(", ");
 
-        vec![
-            format!("{}", self.kind),
-            format!("{}", self.alias),
-            format!("{}", flags),
-        ]
+        let kind = if Icons::enabled() {
+            format!("{} {}", Icons::device(self.category), self.kind)
+        } else {
+            self.kind.clone()
+        };
+        vec![kind, format!("{}", self.alias), format!("{}", flags)]
     }
     fn column_names() -> Option<Vec<String>> {
         Some(vec![
-            "Type".to_string(),
-            "Name".to_string(),
-            "State".to_string(),
+            tr("Type").to_string(),
+            tr("Name").to_string(),
+            tr("State").to_string(),
         ])
     }
     fn column_constraints() -> Vec<fn(u16) -> Constraint> {
-        vec![Constraint::Length, Constraint::Fill, Constraint::Min]
+        vec![
+            |w| Constraint::Length(DEVICE_COLUMNS.fit(0, w, 4, 24)),
+            |w| Constraint::Min(DEVICE_COLUMNS.fit(1, w, 8, 48)),
+            Constraint::Fill,
+        ]
     }
     fn column_alignments() -> Option<Vec<Alignment>> {
         Some(vec![Alignment::Left, Alignment::Left, Alignment::Right])
     }
     fn style(&self) -> Style {
         let mut style = Style::default();
+        if let Some(color) = self.category.color() {
+            style = style.fg(Color::from_str(color).unwrap());
+        }
         if self.is_connected {
             style = style
                 .fg(Color::from_str(&CONFIG.theme.fg_connected_color).unwrap())
@@ -309,6 +850,8 @@ pub enum DeviceAction {
     SetPaired(bool),
     SetTrusted(bool),
     SetBlocked(bool),
+    /// Kept in the state file, bluez is not told about it
+    SetAutoConnect(bool),
     Info,
 }
 impl DeviceAction {
@@ -319,23 +862,47 @@ impl DeviceAction {
             DeviceAction::SetPaired(false) => "r".to_string(),
             DeviceAction::SetTrusted(_) => "t".to_string(),
             DeviceAction::SetBlocked(_) => "b".to_string(),
+            DeviceAction::SetAutoConnect(_) => "a".to_string(),
             DeviceAction::Info => "i".to_string(),
         }
     }
+    /// What the action did to the device, once it succeeded, nothing for the read-only ones
+    pub fn done(&self, device: &str) -> Option<String> {
+        let msg = match self {
+            DeviceAction::SetConnected(true) => format!("Connected to {device}"),
+            DeviceAction::SetConnected(false) => format!("Disconnected from {device}"),
+            DeviceAction::SetPaired(true) => format!("Paired with {device}"),
+            DeviceAction::SetPaired(false) => format!("Unpaired {device}"),
+            DeviceAction::SetTrusted(true) => format!("Trusted {device}"),
+            DeviceAction::SetTrusted(false) => format!("Untrusted {device}"),
+            DeviceAction::SetBlocked(true) => format!("Blocked {device}"),
+            DeviceAction::SetBlocked(false) => format!("Unblocked {device}"),
+            DeviceAction::SetAutoConnect(true) => format!("Auto-connecting {device}"),
+            DeviceAction::SetAutoConnect(false) => format!("No longer auto-connecting {device}"),
+            DeviceAction::Info => return None,
+        };
+        Some(msg)
+    }
+    /// Untranslated name, as kept in the action log
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeviceAction::SetConnected(true) => "Connect",
+            DeviceAction::SetConnected(false) => "Disconnect",
+            DeviceAction::SetPaired(true) => "Pair",
+            DeviceAction::SetPaired(false) => "Unpair",
+            DeviceAction::SetTrusted(true) => "Trust",
+            DeviceAction::SetTrusted(false) => "Untrust",
+            DeviceAction::SetBlocked(true) => "Block",
+            DeviceAction::SetBlocked(false) => "Unblock",
+            DeviceAction::SetAutoConnect(true) => "Auto-connect: on",
+            DeviceAction::SetAutoConnect(false) => "Auto-connect: off",
+            DeviceAction::Info => "Info",
+        }
+    }
 }
 impl Display for DeviceAction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            DeviceAction::SetConnected(true) => write!(f, "Connect"),
-            DeviceAction::SetConnected(false) => write!(f, "Disconnect"),
-            DeviceAction::SetPaired(true) => write!(f, "Pair"),
-            DeviceAction::SetPaired(false) => write!(f, "Unpair"),
-            DeviceAction::SetTrusted(true) => write!(f, "Trust"),
-            DeviceAction::SetTrusted(false) => write!(f, "Untrust"),
-            DeviceAction::SetBlocked(true) => write!(f, "Block"),
-            DeviceAction::SetBlocked(false) => write!(f, "Unblock"),
-            DeviceAction::Info => write!(f, "Info"),
-        }
+        f.write_str(tr(self.label()))
     }
 }
 impl Tabular for DeviceAction {
@@ -357,3 +924,732 @@ impl Tabular for DeviceAction {
         Some(vec![Alignment::Left, Alignment::Right])
     }
 }
+
+#[derive(Clone, Copy, Debug)]
+pub enum RetryChoice {
+    Retry,
+    Info,
+    Dismiss,
+}
+impl Display for RetryChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetryChoice::Retry => f.write_str(tr("Retry")),
+            RetryChoice::Info => f.write_str(tr("Open device info")),
+            RetryChoice::Dismiss => f.write_str(tr("Dismiss")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SetupField {
+    Theme,
+    Adapter,
+    ScanOnStartup,
+    Save,
+    Skip,
+}
+impl Display for SetupField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetupField::Theme => f.write_str(tr("Theme preset")),
+            SetupField::Adapter => f.write_str(tr("Default adapter")),
+            SetupField::ScanOnStartup => f.write_str(tr("Scan on startup")),
+            SetupField::Save => f.write_str(tr("Save and continue")),
+            SetupField::Skip => f.write_str(tr("Skip")),
+        }
+    }
+}
+
+/// How long a property row stays highlighted after its value changed
+pub const CHANGE_HIGHLIGHT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+pub struct PropertyRow {
+    pub name: String,
+    pub value: String,
+    pub changed_at: Option<Instant>,
+}
+impl PropertyRow {
+    pub fn from(prop: &bluer::DeviceProperty) -> Self {
+        use bluer::DeviceProperty as P;
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{b:02x}")).join(" ");
+        let (name, value) = match prop {
+            P::Name(v) => ("Name", v.clone()),
+            P::RemoteAddress(v) => ("RemoteAddress", v.to_string()),
+            P::AddressType(v) => ("AddressType", v.to_string()),
+            P::Icon(v) => ("Icon", v.clone()),
+            P::Class(v) => ("Class", format!("0x{v:06x}")),
+            P::Appearance(v) => ("Appearance", format!("0x{v:04x}")),
+            P::Uuids(v) => (
+                "Uuids",
+                v.iter()
+                    .map(|u| {
+                        let uuid = u.to_string();
+                        uuid_name(&uuid).map_or(uuid, str::to_string)
+                    })
+                    .sorted()
+                    .join(", "),
+            ),
+            P::Paired(v) => ("Paired", v.to_string()),
+            P::Connected(v) => ("Connected", v.to_string()),
+            P::Trusted(v) => ("Trusted", v.to_string()),
+            P::Blocked(v) => ("Blocked", v.to_string()),
+            P::WakeAllowed(v) => ("WakeAllowed", v.to_string()),
+            P::Alias(v) => ("Alias", v.clone()),
+            P::LegacyPairing(v) => ("LegacyPairing", v.to_string()),
+            P::Modalias(v) => ("Modalias", v.to_string()),
+            P::Rssi(v) => ("Rssi", format!("{v} dBm")),
+            P::TxPower(v) => ("TxPower", format!("{v} dBm")),
+            P::ManufacturerData(v) => (
+                "ManufacturerData",
+                v.iter()
+                    .sorted()
+                    .map(|(company, data)| format!("0x{company:04x}: {}", hex(data)))
+                    .join(", "),
+            ),
+            P::ServiceData(v) => (
+                "ServiceData",
+                v.iter()
+                    .sorted()
+                    .map(|(uuid, data)| format!("{uuid}: {}", hex(data)))
+                    .join(", "),
+            ),
+            P::ServicesResolved(v) => ("ServicesResolved", v.to_string()),
+            P::AdvertisingFlags(v) => ("AdvertisingFlags", hex(v)),
+            P::AdvertisingData(v) => (
+                "AdvertisingData",
+                v.iter()
+                    .sorted()
+                    .map(|(kind, data)| format!("0x{kind:02x}: {}", hex(data)))
+                    .join(", "),
+            ),
+            P::BatteryPercentage(v) => ("BatteryPercentage", format!("{v}%")),
+            // properties added by newer bluer versions
+            _ => ("Other", format!("{prop:?}")),
+        };
+        Self {
+            name: name.to_string(),
+            value,
+            changed_at: None,
+        }
+    }
+    pub fn is_highlighted(&self) -> bool {
+        self.changed_at
+            .is_some_and(|t| t.elapsed() < CHANGE_HIGHLIGHT)
+    }
+}
+impl Tabular for PropertyRow {
+    type Value = Self;
+    type ColumnValue = ();
+    fn column_values() -> Vec<Self::ColumnValue> {
+        vec![]
+    }
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![self.name.clone(), self.value.clone()]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![tr("Property").to_string(), tr("Value").to_string()])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Fill]
+    }
+    fn style(&self) -> Style {
+        let mut style = Style::default();
+        if self.is_highlighted() {
+            style = style
+                .fg(Color::from_str(&CONFIG.theme.fg_changed_color).unwrap())
+                .add_modifier(Modifier::BOLD);
+        }
+        style
+    }
+}
+
+/// How a value is written to a characteristic
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum WriteType {
+    /// Acknowledged by the device, errors come back
+    Request,
+    /// Fire and forget, what many serial-like protocols expect
+    Command,
+}
+impl WriteType {
+    pub fn op(&self) -> WriteOp {
+        match self {
+            WriteType::Request => WriteOp::Request,
+            WriteType::Command => WriteOp::Command,
+        }
+    }
+}
+impl Display for WriteType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteType::Request => f.write_str(tr("with response")),
+            WriteType::Command => f.write_str(tr("without response")),
+        }
+    }
+}
+
+/// Readings kept for the sparkline of a polled characteristic
+const GATT_SAMPLES: usize = 120;
+
+/// A characteristic of the device opened in the GATT view
+#[derive(Clone, Debug)]
+pub struct GattRow {
+    pub service: u16,
+    pub id: u16,
+    pub uuid: String,
+    pub flags: CharacteristicFlags,
+    pub value: Vec<u8>,
+    /// Numeric readings of the value taken while it is polled
+    pub samples: Vec<u64>,
+    pub is_polled: bool,
+}
+impl GattRow {
+    pub fn key(&self) -> (u16, u16) {
+        (self.service, self.id)
+    }
+    pub fn name(&self) -> String {
+        uuid_name(&self.uuid).map_or(self.uuid.clone(), str::to_string)
+    }
+    /// The write types the characteristic accepts, the acknowledged one first
+    pub fn write_types(&self) -> Vec<WriteType> {
+        [
+            (self.flags.write, WriteType::Request),
+            (self.flags.write_without_response, WriteType::Command),
+        ]
+        .into_iter()
+        .filter(|(supported, _)| *supported)
+        .map(|(_, write_type)| write_type)
+        .collect()
+    }
+    /// The value as a little endian unsigned integer, the GATT byte order
+    pub fn number(&self) -> Option<u64> {
+        (1..=8).contains(&self.value.len()).then(|| {
+            self.value
+                .iter()
+                .rev()
+                .fold(0, |n, b| n << 8 | u64::from(*b))
+        })
+    }
+    pub fn record(&mut self, value: Vec<u8>) {
+        self.value = value;
+        if self.is_polled
+            && let Some(n) = self.number()
+        {
+            self.samples.push(n);
+            if self.samples.len() > GATT_SAMPLES {
+                let _ = self.samples.remove(0);
+            }
+        }
+    }
+    fn flag_names(&self) -> String {
+        let f = &self.flags;
+        [
+            (f.read, "read"),
+            (f.write, "write"),
+            (f.write_without_response, "write-cmd"),
+            (f.notify, "notify"),
+            (f.indicate, "indicate"),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| name)
+        .join(" ")
+    }
+}
+impl Tabular for GattRow {
+    type Value = Self;
+    type ColumnValue = ();
+    fn column_values() -> Vec<Self::ColumnValue> {
+        vec![]
+    }
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        let value = self.value.iter().map(|b| format!("{b:02x}")).join(" ");
+        vec![self.name(), self.flag_names(), value]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![
+            tr("Characteristic").to_string(),
+            tr("Flags").to_string(),
+            tr("Value").to_string(),
+        ])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Length, Constraint::Fill]
+    }
+    fn style(&self) -> Style {
+        match self.is_polled {
+            true => Style::default().add_modifier(Modifier::BOLD),
+            false => Style::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UuidRow {
+    pub uuid: String,
+    pub name: String,
+}
+impl UuidRow {
+    pub fn for_device(device: &Device) -> Vec<Self> {
+        device
+            .uuids
+            .iter()
+            .map(|uuid| Self {
+                uuid: uuid.clone(),
+                name: match uuid_name(uuid) {
+                    Some(name) => name.to_string(),
+                    None if is_custom(uuid) => "Vendor specific".to_string(),
+                    None => "Unknown".to_string(),
+                },
+            })
+            .collect()
+    }
+}
+impl Tabular for UuidRow {
+    type Value = Self;
+    type ColumnValue = ();
+    fn column_values() -> Vec<Self::ColumnValue> {
+        vec![]
+    }
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![self.uuid.clone(), self.name.clone()]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![tr("UUID").to_string(), tr("Service").to_string()])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Fill]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SummaryRow {
+    pub label: String,
+    pub count: usize,
+}
+impl SummaryRow {
+    pub fn for_adapter(adapter: &Adapter, found: &HashSet<DeviceId>) -> Vec<Self> {
+        let devices = &adapter.devices;
+        let count = |f: &dyn Fn(&Device) -> bool| devices.iter().filter(|d| f(d)).count();
+        let mut rows = vec![
+            ("Devices".to_string(), devices.len()),
+            ("Connected".to_string(), count(&|d| d.is_connected)),
+            ("Paired".to_string(), count(&|d| d.is_paired)),
+            ("Unpaired".to_string(), count(&|d| !d.is_paired)),
+            ("Trusted".to_string(), count(&|d| d.is_trusted)),
+            ("Blocked".to_string(), count(&|d| d.is_blocked)),
+            (
+                "New this session".to_string(),
+                count(&|d| found.contains(&d.id)),
+            ),
+        ];
+        for category in [
+            DeviceCategory::Audio,
+            DeviceCategory::Input,
+            DeviceCategory::Phone,
+            DeviceCategory::Sensor,
+            DeviceCategory::Other,
+        ] {
+            rows.push((format!("{category:?}"), count(&|d| d.category == category)));
+        }
+        rows.into_iter()
+            .map(|(label, count)| Self { label, count })
+            .collect()
+    }
+}
+impl Tabular for SummaryRow {
+    type Value = Self;
+    type ColumnValue = ();
+    fn column_values() -> Vec<Self::ColumnValue> {
+        vec![]
+    }
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![self.label.clone(), self.count.to_string()]
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Fill]
+    }
+    fn column_alignments() -> Option<Vec<Alignment>> {
+        Some(vec![Alignment::Left, Alignment::Right])
+    }
+}
+
+/// A paired or trusted device of any adapter, listed by the quick view
+#[derive(Clone, Debug)]
+pub struct QuickRow {
+    pub adapter_id: AdapterId,
+    pub adapter: String,
+    pub device: Device,
+}
+impl QuickRow {
+    pub fn collect(adapters: &[Adapter]) -> Vec<Self> {
+        adapters
+            .iter()
+            .flat_map(|a| {
+                a.devices
+                    .iter()
+                    .filter(|d| d.is_paired || d.is_trusted)
+                    .map(|d| Self {
+                        adapter_id: a.id,
+                        adapter: a.name.clone(),
+                        device: d.clone(),
+                    })
+            })
+            .sorted_by_key(|r| (!r.device.is_connected, r.device.alias.to_lowercase()))
+            .collect()
+    }
+}
+impl Tabular for QuickRow {
+    type Value = Self;
+    type ColumnValue = ();
+    fn column_values() -> Vec<Self::ColumnValue> {
+        vec![]
+    }
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        let state = match (self.device.is_connected, self.device.battery) {
+            (true, Some(b)) => format!("Connected {b}%"),
+            (true, None) => "Connected".to_string(),
+            (false, _) => String::new(),
+        };
+        vec![self.device.alias.clone(), state, self.adapter.clone()]
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Fill, Constraint::Length, Constraint::Length]
+    }
+    fn column_alignments() -> Option<Vec<Alignment>> {
+        Some(vec![Alignment::Left, Alignment::Right, Alignment::Right])
+    }
+    fn style(&self) -> Style {
+        self.device.style()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum QuitChoice {
+    Wait,
+    Quit,
+}
+impl Display for QuitChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuitChoice::Wait => f.write_str(tr("Wait and quit when done")),
+            QuitChoice::Quit => f.write_str(tr("Quit anyway")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A code bluez asks to show, for the user to type on the device being paired
+#[derive(Clone, Debug)]
+pub struct PinCode {
+    pub device: DeviceId,
+    pub code: String,
+    /// Digits typed so far, keyboards report them during passkey entry
+    pub entered: Option<u16>,
+}
+
+/// A pairing request that waits for the user to accept or reject it
+#[derive(Debug)]
+pub struct PairingPrompt {
+    pub device: DeviceId,
+    /// The passkey the device should show as well, none for just-works
+    pub passkey: Option<u32>,
+    pub answer: oneshot::Sender<bool>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum PrivacyChoice {
+    Apply,
+    Cancel,
+}
+impl Display for PrivacyChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrivacyChoice::Apply => f.write_str(tr("Power cycle and apply")),
+            PrivacyChoice::Cancel => f.write_str(tr("Cancel")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum PairingChoice {
+    Accept,
+    Reject,
+}
+impl Display for PairingChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PairingChoice::Accept => f.write_str(tr("Accept")),
+            PairingChoice::Reject => f.write_str(tr("Reject")),
+        }
+    }
+}
+
+/// A message shown in a popup until dismissed
+#[derive(Clone, Debug)]
+pub struct Popup {
+    pub title: String,
+    pub msg: String,
+    pub severity: Severity,
+}
+impl Popup {
+    pub fn new(severity: Severity, title: impl Into<String>, msg: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            msg: msg.into(),
+            severity,
+        }
+    }
+    pub fn info(title: impl Into<String>, msg: impl Into<String>) -> Self {
+        Self::new(Severity::Info, title, msg)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ServiceChoice {
+    Start,
+    Dismiss,
+}
+impl Display for ServiceChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceChoice::Start => f.write_str(tr("Start it")),
+            ServiceChoice::Dismiss => f.write_str(tr("Dismiss")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum UnblockChoice {
+    Unblock,
+    Dismiss,
+}
+impl Display for UnblockChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnblockChoice::Unblock => f.write_str(tr("Unblock and power on")),
+            UnblockChoice::Dismiss => f.write_str(tr("Dismiss")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum StartupChoice {
+    Dismiss,
+    Mute,
+}
+impl Display for StartupChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartupChoice::Dismiss => f.write_str(tr("Dismiss")),
+            StartupChoice::Mute => f.write_str(tr("Don't show these again")),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum BatchStatus {
+    Waiting,
+    Pairing,
+    Trusting,
+    Done,
+    Failed(String),
+}
+impl BatchStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, BatchStatus::Done | BatchStatus::Failed(_))
+    }
+}
+impl Display for BatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchStatus::Waiting => f.write_str(tr("Waiting")),
+            BatchStatus::Pairing => f.write_str(tr("Pairing...")),
+            BatchStatus::Trusting => f.write_str(tr("Trusting...")),
+            BatchStatus::Done => f.write_str(tr("Paired")),
+            BatchStatus::Failed(e) => write!(f, "{}: {e}", tr("Failed")),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchRow {
+    pub id: DeviceId,
+    pub alias: String,
+    pub status: BatchStatus,
+}
+impl Tabular for BatchRow {
+    type Value = Self;
+    type ColumnValue = ();
+    fn column_values() -> Vec<Self::ColumnValue> {
+        vec![]
+    }
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![self.alias.clone(), self.status.to_string()]
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Fill]
+    }
+    fn style(&self) -> Style {
+        let mut style = Style::default();
+        if let BatchStatus::Done = self.status {
+            style = style
+                .fg(Color::from_str(&CONFIG.theme.fg_connected_color).unwrap())
+                .bg(Color::from_str(&CONFIG.theme.bg_connected_color).unwrap());
+        }
+        style
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProximityState {
+    Unknown,
+    Near,
+    Away,
+}
+impl ProximityState {
+    /// Only changes once the rssi crosses the threshold of the opposite
+    /// state, so a signal hovering around one value doesn't flap.
+    pub fn next(self, rssi: i16, leave_rssi: i16, return_rssi: i16) -> Self {
+        if rssi >= return_rssi {
+            ProximityState::Near
+        } else if rssi < leave_rssi {
+            ProximityState::Away
+        } else {
+            self
+        }
+    }
+}
+impl Display for ProximityState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProximityState::Unknown => f.write_str(tr("unknown")),
+            ProximityState::Near => f.write_str(tr("near")),
+            ProximityState::Away => f.write_str(tr("away")),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ActionOutcome {
+    Running,
+    Done,
+    Failed(String),
+}
+impl ActionOutcome {
+    fn translated(&self) -> String {
+        match self {
+            ActionOutcome::Running => tr("Running...").to_string(),
+            ActionOutcome::Done => tr("Done").to_string(),
+            ActionOutcome::Failed(e) => format!("{}: {e}", tr("Failed")),
+        }
+    }
+}
+/// Untranslated, as kept in the action log
+impl Display for ActionOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionOutcome::Running => f.write_str("Running..."),
+            ActionOutcome::Done => f.write_str("Done"),
+            ActionOutcome::Failed(e) => write!(f, "Failed: {e}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ActionRecord {
+    pub target: String,
+    pub action: String,
+    pub started: DateTime<Local>,
+    pub finished: Option<DateTime<Local>>,
+    pub outcome: ActionOutcome,
+}
+impl ActionRecord {
+    fn duration(&self) -> String {
+        self.finished.map_or(String::new(), |end| {
+            format!(
+                "{:.1}s",
+                (end - self.started).num_milliseconds() as f64 / 1000.0
+            )
+        })
+    }
+    /// The action in the interface language
+    pub fn action(&self) -> &str {
+        tr(&self.action)
+    }
+    /// Tab separated form used by the action log and exports, left untranslated
+    pub fn to_line(&self) -> String {
+        let time = format!("{} %H:%M:%S", CONFIG.theme.date_format);
+        [
+            self.started.format(&time).to_string(),
+            self.target.clone(),
+            self.action.clone(),
+            self.duration(),
+            self.outcome.to_string(),
+        ]
+        .join("\t")
+    }
+}
+impl Tabular for ActionRecord {
+    type Value = Self;
+    type ColumnValue = ();
+    fn column_values() -> Vec<Self::ColumnValue> {
+        vec![]
+    }
+    fn value(&self) -> Self::Value {
+        self.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![
+            self.started.format("%H:%M:%S").to_string(),
+            self.target.clone(),
+            self.action().to_string(),
+            self.duration(),
+            self.outcome.translated(),
+        ]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![
+            tr("Time").to_string(),
+            tr("Target").to_string(),
+            tr("Action").to_string(),
+            tr("Took").to_string(),
+            tr("Outcome").to_string(),
+        ])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![
+            Constraint::Length,
+            Constraint::Length,
+            Constraint::Length,
+            Constraint::Length,
+            Constraint::Fill,
+        ]
+    }
+}
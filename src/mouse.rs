@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use crate::globals::CONFIG;
+
+/// Tells a click that selects a row from the second one that opens it
+#[derive(Clone, Copy, Debug)]
+pub struct Clicks<T> {
+    last: Option<(T, Instant)>,
+}
+impl<T> Default for Clicks<T> {
+    fn default() -> Self {
+        Self { last: None }
+    }
+}
+impl<T: Copy + PartialEq> Clicks<T> {
+    /// Records a left click on the row of `target`, true when it completes a double click
+    pub fn double_click(&mut self, target: T) -> bool {
+        let interval = Duration::from_millis(CONFIG.double_click.into());
+        self.click_at(target, Instant::now(), interval)
+    }
+    fn click_at(&mut self, target: T, now: Instant, interval: Duration) -> bool {
+        match self.last.take() {
+            Some((last, at)) if last == target && now.duration_since(at) <= interval => true,
+            _ => {
+                self.last = Some((target, now));
+                false
+            }
+        }
+    }
+}
+
+/// Index into the table data of the row drawn at `visible` under the first shown one,
+/// the table having scrolled past `offset` rows
+pub fn table_row(visible: Option<usize>, offset: usize, len: usize) -> Option<usize> {
+    visible.map(|row| row + offset).filter(|row| *row < len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_click_on_the_same_row_opens_it() {
+        let interval = Duration::from_millis(400);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut clicks = Clicks::default();
+        assert!(!clicks.click_at(1, at(0), interval));
+        assert!(clicks.click_at(1, at(300), interval));
+        // a third click starts over
+        assert!(!clicks.click_at(1, at(350), interval));
+        assert!(!clicks.click_at(2, at(400), interval));
+        assert!(!clicks.click_at(2, at(900), interval));
+    }
+
+    #[test]
+    fn clicked_row_counts_the_scrolled_rows() {
+        assert_eq!(table_row(Some(2), 0, 10), Some(2));
+        assert_eq!(table_row(Some(2), 5, 10), Some(7));
+        assert_eq!(table_row(Some(6), 5, 10), None);
+        assert_eq!(table_row(None, 5, 10), None);
+    }
+}
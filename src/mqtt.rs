@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, ConnectionError, Event, MqttOptions, Packet, QoS};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::config::Mqtt;
+use crate::models::{Adapter, DeviceId};
+
+const RETRY_EVERY: Duration = Duration::from_secs(5);
+
+/// Connection and battery of a device
+type State = (bool, Option<u8>);
+/// Topic and payload of a message
+type Message = (String, String);
+
+/// Publishes the presence and battery of the devices, retained so the broker
+/// always holds their latest state
+pub struct Publisher {
+    client: AsyncClient,
+    errors: UnboundedReceiver<String>,
+    /// Signals each accepted connection, the broker may have lost what was sent before
+    connects: UnboundedReceiver<()>,
+    /// Connection and battery last published for each device
+    known: HashMap<DeviceId, State>,
+}
+impl Publisher {
+    pub fn start(config: &'static Mqtt) -> Option<Self> {
+        let broker = config.broker.as_ref()?;
+        let (host, port) = broker.rsplit_once(':')?;
+        let mut options = MqttOptions::new(&config.client_id, host, port.parse().ok()?);
+        if let Some(username) = &config.username {
+            let _ = options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+        let (client, mut eventloop) = AsyncClient::new(options, 64);
+        let (errors_tx, errors) = mpsc::unbounded_channel();
+        let (connects_tx, connects) = mpsc::unbounded_channel();
+        let _ = tokio::spawn(async move {
+            let mut reported = false;
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        reported = false;
+                        if connects_tx.send(()).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    // the publisher and its client are gone
+                    Err(ConnectionError::RequestsDone) => break,
+                    Err(e) => {
+                        if !reported {
+                            reported = true;
+                            let _ = errors_tx.send(format!("MQTT broker {broker}: {e}"));
+                        }
+                        tokio::time::sleep(RETRY_EVERY).await;
+                    }
+                }
+            }
+        });
+        Some(Self {
+            client,
+            errors,
+            connects,
+            known: HashMap::new(),
+        })
+    }
+    /// Publishes what changed since the last call, everything on the first one
+    /// and after each reconnection
+    pub fn publish_changes(&mut self, config: &Mqtt, adapters: &[Adapter]) {
+        let mut reconnected = false;
+        while self.connects.try_recv().is_ok() {
+            reconnected = true;
+        }
+        if reconnected {
+            self.known.clear();
+        }
+        for (device, state, messages) in changes(&self.known, config, adapters) {
+            // a device is only known once all of its messages are queued
+            if messages
+                .into_iter()
+                .all(|(topic, payload)| self.try_publish(topic, payload))
+            {
+                let _ = self.known.insert(device, state);
+            }
+        }
+    }
+    pub fn publish(&self, topic: String, payload: String) {
+        let _ = self.try_publish(topic, payload);
+    }
+    fn try_publish(&self, topic: String, payload: String) -> bool {
+        self.client
+            .try_publish(topic, QoS::AtLeastOnce, true, payload)
+            .is_ok()
+    }
+    pub fn poll_error(&mut self) -> Option<String> {
+        self.errors.try_recv().ok()
+    }
+}
+
+/// Messages for each device whose state differs from the known one, along with its new state
+fn changes(
+    known: &HashMap<DeviceId, State>,
+    config: &Mqtt,
+    adapters: &[Adapter],
+) -> Vec<(DeviceId, State, Vec<Message>)> {
+    let mut changes = vec![];
+    for adapter in adapters.iter().filter(|a| !a.is_cached) {
+        for device in &adapter.devices {
+            let topic = |template: &str| {
+                template
+                    .replace("{address}", &device.id.to_string())
+                    .replace("{name}", &topic_level(&device.alias))
+                    .replace("{adapter}", &topic_level(&adapter.name))
+            };
+            let old = known.get(&device.id);
+            let mut messages = vec![];
+            if old.is_none_or(|(connected, _)| *connected != device.is_connected) {
+                let payload = match device.is_connected {
+                    true => "home",
+                    false => "not_home",
+                };
+                messages.push((topic(&config.presence_topic), payload.to_string()));
+            }
+            if let Some(level) = device.battery
+                && old.is_none_or(|(_, battery)| *battery != Some(level))
+            {
+                messages.push((topic(&config.battery_topic), level.to_string()));
+            }
+            if !messages.is_empty() {
+                changes.push((device.id, (device.is_connected, device.battery), messages));
+            }
+        }
+    }
+    changes
+}
+
+// names may hold the characters that mqtt reserves for levels and wildcards
+fn topic_level(name: &str) -> String {
+    name.replace(['/', '+', '#'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Device, DeviceCategory};
+
+    #[test]
+    fn publishes_changes_only() {
+        let config = Mqtt {
+            broker: None,
+            client_id: "bluerat".to_string(),
+            username: None,
+            password: None,
+            presence_topic: "home/{adapter}/{name}".to_string(),
+            battery_topic: "bluerat/{address}/battery".to_string(),
+        };
+        let device = Device {
+            kind: "audio-headset".to_string(),
+            category: DeviceCategory::Audio,
+            battery: Some(80),
+            is_connected: true,
+            is_trusted: true,
+            is_paired: true,
+            ..Device::fake([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0x01], "Buds #2")
+        };
+        let mut adapter = Adapter {
+            devices: vec![device],
+            connections: 1,
+            ..Adapter::fake([0, 0, 0, 0, 0, 1], "hci0")
+        };
+        let mut known = HashMap::new();
+        let mut publish = |adapter: &Adapter| {
+            let mut messages = vec![];
+            for (device, state, sent) in changes(&known, &config, std::slice::from_ref(adapter)) {
+                let _ = known.insert(device, state);
+                messages.extend(sent);
+            }
+            messages
+        };
+        assert_eq!(
+            publish(&adapter),
+            [
+                ("home/hci0/Buds _2".to_string(), "home".to_string()),
+                (
+                    "bluerat/AA:BB:CC:DD:EE:01/battery".to_string(),
+                    "80".to_string()
+                ),
+            ]
+        );
+        assert!(publish(&adapter).is_empty());
+
+        adapter.devices[0].is_connected = false;
+        assert_eq!(
+            publish(&adapter),
+            [("home/hci0/Buds _2".to_string(), "not_home".to_string())]
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use tokio::process::Command;
+
+// bluez keeps LE privacy out of its D-Bus API, only the management interface reaches it
+
+/// Whether the adapter uses LE privacy, as `btmgmt info` reports it, `None` without btmgmt
+pub async fn privacy(adapter: &str) -> Option<bool> {
+    let output = Command::new("btmgmt")
+        .args(["--index", adapter, "info"])
+        .output()
+        .await
+        .ok()?;
+    parse_privacy(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_privacy(info: &str) -> Option<bool> {
+    let settings = info
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("current settings:"))?;
+    Some(settings.split_whitespace().any(|s| s == "privacy"))
+}
+
+/// Turns LE privacy on or off, which the kernel only allows while the adapter is powered off
+pub async fn set_privacy(adapter: &str, on: bool, powered: bool) -> Result<(), String> {
+    let mut steps = vec![["power", "off"], ["privacy", if on { "on" } else { "off" }]];
+    if powered {
+        steps.push(["power", "on"]);
+    }
+    for step in steps {
+        let output = Command::new("btmgmt")
+            .args(["--index", adapter])
+            .args(step)
+            .output()
+            .await
+            .map_err(|e| format!("btmgmt: {e}"))?;
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            return Err(stdout.lines().last().unwrap_or_default().trim().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn privacy_from_current_settings() {
+        let info = "hci0:\tPrimary controller\n\
+            \taddr 00:1A:7D:DA:71:13 version 6 manufacturer 10 class 0x0c010c\n\
+            \tsupported settings: powered connectable le advertising privacy\n\
+            \tcurrent settings: powered bondable le\n";
+        assert_eq!(parse_privacy(info), Some(false));
+        let info = info.replace("bondable le", "bondable le privacy");
+        assert_eq!(parse_privacy(&info), Some(true));
+        assert_eq!(parse_privacy("Unable to open hci9"), None);
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use tokio::process::Command;
+
+/// Radio block of an adapter, which bluez reports only as a failure to power on
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Rfkill {
+    #[default]
+    Unblocked,
+    SoftBlocked,
+    HardBlocked,
+}
+impl Display for Rfkill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rfkill::Unblocked => write!(f, "Unblocked"),
+            Rfkill::SoftBlocked => write!(f, "Blocked"),
+            Rfkill::HardBlocked => write!(f, "Hard blocked"),
+        }
+    }
+}
+
+/// The rfkill switch of the adapter as sysfs exposes it, `None` when it has none
+pub fn rfkill(adapter: &str) -> Option<(u32, Rfkill)> {
+    let dir = Path::new("/sys/class/bluetooth").join(adapter);
+    let switch = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_name().to_string_lossy().starts_with("rfkill"))?
+        .path();
+    let read = |name: &str| fs::read_to_string(switch.join(name)).ok();
+    let index = read("index")?.trim().parse().ok()?;
+    Some((index, parse_state(&read("soft")?, &read("hard")?)))
+}
+
+/// The block state of the adapter, unblocked when it has no rfkill switch
+pub fn rfkill_state(adapter: &str) -> Rfkill {
+    rfkill(adapter).map(|(_, state)| state).unwrap_or_default()
+}
+
+fn parse_state(soft: &str, hard: &str) -> Rfkill {
+    match (soft.trim(), hard.trim()) {
+        (_, "1") => Rfkill::HardBlocked,
+        ("1", _) => Rfkill::SoftBlocked,
+        _ => Rfkill::Unblocked,
+    }
+}
+
+/// Lifts the soft block of the adapter, through /dev/rfkill which logind opens to the active seat
+pub async fn unblock(adapter: &str) -> Result<(), String> {
+    let (index, _) = rfkill(adapter).ok_or(format!("{adapter} has no rfkill switch"))?;
+    let output = Command::new("rfkill")
+        .args(["unblock", &index.to_string()])
+        .output()
+        .await
+        .map_err(|e| format!("rfkill: {e}"))?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hard_block_wins() {
+        assert_eq!(parse_state("0\n", "0\n"), Rfkill::Unblocked);
+        assert_eq!(parse_state("1\n", "0\n"), Rfkill::SoftBlocked);
+        assert_eq!(parse_state("1\n", "1\n"), Rfkill::HardBlocked);
+        assert_eq!(parse_state("0\n", "1\n"), Rfkill::HardBlocked);
+    }
+}
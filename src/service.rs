@@ -0,0 +1,27 @@
+use tokio::process::Command;
+
+pub const SERVICE: &str = "bluetooth.service";
+
+/// The state `systemctl is-active` reports for bluetoothd, `None` without systemd
+pub async fn service_state() -> Option<String> {
+    let output = Command::new("systemctl")
+        .args(["is-active", SERVICE])
+        .output()
+        .await
+        .ok()?;
+    let state = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!state.is_empty()).then_some(state)
+}
+
+/// Starts bluetoothd through systemd, which asks polkit for the permission
+pub async fn start_service() -> Result<(), String> {
+    let output = Command::new("systemctl")
+        .args(["start", SERVICE])
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
@@ -0,0 +1,333 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::{fs, io};
+
+use bluer::{DiscoveryFilter, DiscoveryTransport, Uuid};
+use chrono::{Local, Utc};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::globals::{ACTION_LOG_FILE, PROJECT_NAME, STATE_FILE};
+use crate::models::{Adapter, AdapterId, DeviceId, WriteType};
+
+/// Samples older than this are dropped when recording new ones
+const BATTERY_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+/// An unchanged level is sampled again after this long, to keep the curve flat
+const BATTERY_RESAMPLE_SECS: i64 = 30 * 60;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BatterySample {
+    /// Unix timestamp in seconds
+    pub at: i64,
+    pub level: u8,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatteryHistory {
+    pub device: DeviceId,
+    pub samples: Vec<BatterySample>,
+}
+
+/// A discovery that was running on quit, with the filter it used
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedScan {
+    pub adapter: AdapterId,
+    #[serde(default)]
+    pub uuids: Vec<String>,
+    pub rssi: Option<i16>,
+    pub pathloss: Option<u16>,
+    #[serde(default)]
+    pub transport: String,
+    #[serde(default)]
+    pub duplicate_data: bool,
+    #[serde(default)]
+    pub discoverable: bool,
+    pub pattern: Option<String>,
+}
+impl SavedScan {
+    pub fn new(adapter: AdapterId, filter: &DiscoveryFilter) -> Self {
+        Self {
+            adapter,
+            uuids: filter.uuids.iter().map(|u| u.to_string()).collect(),
+            rssi: filter.rssi,
+            pathloss: filter.pathloss,
+            transport: match filter.transport {
+                DiscoveryTransport::BrEdr => "bredr",
+                DiscoveryTransport::Le => "le",
+                _ => "auto",
+            }
+            .to_string(),
+            duplicate_data: filter.duplicate_data,
+            discoverable: filter.discoverable,
+            pattern: filter.pattern.clone(),
+        }
+    }
+    pub fn filter(&self) -> DiscoveryFilter {
+        DiscoveryFilter {
+            uuids: self
+                .uuids
+                .iter()
+                .filter_map(|u| Uuid::parse_str(u).ok())
+                .collect(),
+            rssi: self.rssi,
+            pathloss: self.pathloss,
+            transport: match self.transport.as_str() {
+                "bredr" => DiscoveryTransport::BrEdr,
+                "le" => DiscoveryTransport::Le,
+                _ => DiscoveryTransport::Auto,
+            },
+            duplicate_data: self.duplicate_data,
+            discoverable: self.discoverable,
+            pattern: self.pattern.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A named payload saved for a characteristic, replayed from the GATT view
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snippet {
+    pub device: DeviceId,
+    /// Uuid of the characteristic, its handle may change between connections
+    pub characteristic: String,
+    pub name: String,
+    /// The bytes as typed, checked again when replayed
+    pub value: String,
+    pub write_type: WriteType,
+}
+
+/// Whether bluerat connects the device on startup and after it drops, overriding
+/// `auto_reconnect`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AutoConnect {
+    pub device: DeviceId,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LastSeen {
+    pub device: DeviceId,
+    /// Adapter that last saw the device, missing in older state files
+    #[serde(default)]
+    pub adapter: Option<AdapterId>,
+    /// Unix timestamp in seconds
+    pub at: i64,
+}
+
+/// What `export-state` dumps, the state with the lines of the action log
+#[derive(Serialize, Deserialize)]
+pub struct Export {
+    #[serde(flatten)]
+    pub state: State,
+    /// Missing in dumps made before the log was exported
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct State {
+    #[serde(default)]
+    pub adapters: Vec<Adapter>,
+    #[serde(default)]
+    pub battery: Vec<BatteryHistory>,
+    #[serde(default)]
+    pub scans: Vec<SavedScan>,
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+    #[serde(default)]
+    pub auto_connect: Vec<AutoConnect>,
+    #[serde(default)]
+    pub seen: Vec<LastSeen>,
+    /// Startup check problems the user asked not to be shown again
+    #[serde(default)]
+    pub muted_checks: Vec<String>,
+}
+impl State {
+    fn path() -> Option<PathBuf> {
+        Self::data_file(STATE_FILE)
+    }
+    fn data_file(name: &str) -> Option<PathBuf> {
+        ProjectDirs::from("", "", PROJECT_NAME).map(|dirs| dirs.data_dir().join(name))
+    }
+    fn create_data_file(name: &str, append: bool) -> io::Result<(PathBuf, fs::File)> {
+        let path = Self::data_file(name).ok_or(io::ErrorKind::NotFound)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&path)?;
+        Ok((path, file))
+    }
+    pub fn append_action_log(line: &str) -> io::Result<()> {
+        let (_, mut file) = Self::create_data_file(ACTION_LOG_FILE, true)?;
+        writeln!(file, "{line}")
+    }
+    /// The lines of the action log, empty when nothing was logged
+    pub fn action_log() -> Vec<String> {
+        Self::data_file(ACTION_LOG_FILE)
+            .and_then(|p| fs::read_to_string(p).ok())
+            .map_or(vec![], |s| s.lines().map(str::to_string).collect())
+    }
+    pub fn replace_action_log(lines: &[String]) -> io::Result<()> {
+        let (_, mut file) = Self::create_data_file(ACTION_LOG_FILE, false)?;
+        for line in lines {
+            writeln!(file, "{line}")?;
+        }
+        Ok(())
+    }
+    /// Writes the lines to a new timestamped file, returning its path
+    pub fn export_actions(lines: &[String]) -> io::Result<PathBuf> {
+        let name = format!("actions-{}.tsv", Local::now().format("%Y%m%d-%H%M%S"));
+        let (path, mut file) = Self::create_data_file(&name, false)?;
+        for line in lines {
+            writeln!(file, "{line}")?;
+        }
+        Ok(path)
+    }
+    pub fn exists() -> bool {
+        Self::path().is_some_and(|p| p.exists())
+    }
+    /// The saved state, an unreadable file being moved aside so saving doesn't wipe it
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match toml::from_str(&text) {
+            Ok(state) => state,
+            Err(_) => {
+                let _ = fs::rename(&path, path.with_extension("toml.unreadable"));
+                Self::default()
+            }
+        }
+    }
+    pub fn save(&self) -> io::Result<()> {
+        Self::write(&toml::to_string(self).map_err(io::Error::other)?)
+    }
+    /// Saves the state when it differs from `last`, the text written the time before
+    pub fn save_changed(&self, last: &mut String) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        if text != *last {
+            Self::write(&text)?;
+            *last = text;
+        }
+        Ok(())
+    }
+    /// Writes next to the file and renames over it, so a crash leaves the old one whole
+    fn write(text: &str) -> io::Result<()> {
+        let path = Self::path().ok_or(io::ErrorKind::NotFound)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(tmp, path)
+    }
+    pub fn record_battery(&mut self, device: DeviceId, level: u8) {
+        let now = Utc::now().timestamp();
+        let history = match self.battery.iter().position(|h| h.device == device) {
+            Some(i) => &mut self.battery[i],
+            None => {
+                self.battery.push(BatteryHistory {
+                    device,
+                    samples: vec![],
+                });
+                self.battery.last_mut().unwrap()
+            }
+        };
+        if let Some(last) = history.samples.last()
+            && last.level == level
+            && now - last.at < BATTERY_RESAMPLE_SECS
+        {
+            return;
+        }
+        history
+            .samples
+            .retain(|s| now - s.at < BATTERY_RETENTION_SECS);
+        history.samples.push(BatterySample { at: now, level });
+    }
+    pub fn battery_history(&self, device: &DeviceId) -> Vec<BatterySample> {
+        self.battery
+            .iter()
+            .find(|h| h.device == *device)
+            .map_or(vec![], |h| h.samples.clone())
+    }
+    /// Adds the snippet, or replaces in place the one of the characteristic with the same name
+    /// so the numbers of the others stay the same
+    pub fn save_snippet(&mut self, snippet: Snippet) {
+        let same = self.snippets.iter_mut().find(|s| {
+            s.device == snippet.device
+                && s.characteristic == snippet.characteristic
+                && s.name == snippet.name
+        });
+        match same {
+            Some(s) => *s = snippet,
+            None => self.snippets.push(snippet),
+        }
+    }
+    /// Removes the named snippet of the characteristic, returning whether there was one
+    pub fn remove_snippet(&mut self, device: &DeviceId, characteristic: &str, name: &str) -> bool {
+        let len = self.snippets.len();
+        self.snippets.retain(|s| {
+            s.device != *device || s.characteristic != characteristic || s.name != name
+        });
+        self.snippets.len() != len
+    }
+    pub fn device_snippets(&self, device: &DeviceId) -> Vec<Snippet> {
+        self.snippets
+            .iter()
+            .filter(|s| s.device == *device)
+            .cloned()
+            .collect()
+    }
+    pub fn record_seen(&mut self, adapter: AdapterId, device: DeviceId, at: i64) {
+        let adapter = Some(adapter);
+        match self.seen.iter_mut().find(|s| s.device == device) {
+            Some(seen) => {
+                seen.adapter = adapter;
+                seen.at = at;
+            }
+            None => self.seen.push(LastSeen {
+                device,
+                adapter,
+                at,
+            }),
+        }
+    }
+    /// When the device was last in range, as a unix timestamp in seconds
+    pub fn last_seen(&self, device: &DeviceId) -> Option<i64> {
+        self.seen.iter().find(|s| s.device == *device).map(|s| s.at)
+    }
+    /// The auto-connect choice made for the device, if any
+    pub fn auto_connect(&self, device: &DeviceId) -> Option<bool> {
+        self.auto_connect
+            .iter()
+            .find(|a| a.device == *device)
+            .map(|a| a.enabled)
+    }
+    pub fn set_auto_connect(&mut self, device: DeviceId, enabled: bool) {
+        self.auto_connect.retain(|a| a.device != device);
+        self.auto_connect.push(AutoConnect { device, enabled });
+    }
+    pub fn cached_adapters(&self) -> Vec<Adapter> {
+        let mut adapters = self.adapters.clone();
+        for a in adapters.iter_mut() {
+            a.devices.retain(|d| d.id.is_allowed());
+            a.is_cached = true;
+            a.is_on = false;
+            a.is_scanning = false;
+            a.connections = 0;
+            for d in a.devices.iter_mut() {
+                d.is_cached = true;
+                d.is_connected = false;
+            }
+        }
+        adapters
+    }
+}
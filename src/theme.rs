@@ -1,10 +1,24 @@
+use std::ops::{Deref, Range};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicI16, Ordering};
 
+use itertools::Itertools;
+use ratatui::crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEventKind,
+};
+use ratatui::layout::{Alignment, Constraint, Margin, Position, Rect};
 use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, BorderType, Borders, TableState};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Block, BorderType, Borders, Clear, Paragraph, Row, Scrollbar, ScrollbarOrientation,
+    ScrollbarState, Table, TableState,
+};
+use ratatui::Frame;
 use ratatui_helpers::stateful_table::{IndexedRow, Padding, StatefulTable, TableStyle, Tabular};
 
 use crate::globals::CONFIG;
+use crate::models::{DeviceCategory, Severity};
+use crate::mouse::table_row;
 
 pub struct StyledWidget;
 impl StyledWidget {
@@ -12,15 +26,57 @@ impl StyledWidget {
         data: Vec<T>,
         state: TableState,
         title: Option<String>,
-    ) -> StatefulTable<'a, T> {
-        StatefulTable::new(data, state, Self::table_style(), title)
+    ) -> StyledTable<'a, T> {
+        let len = data.len();
+        StyledTable::new(
+            StatefulTable::new(StripedRow::from(data), state, Self::table_style(), title),
+            len,
+        )
     }
     pub fn indexed_table<'a, T: Tabular>(
         data: Vec<T>,
         state: TableState,
         title: Option<String>,
-    ) -> StatefulTable<'a, IndexedRow<T>> {
-        StatefulTable::new(IndexedRow::from(data), state, Self::table_style(), title)
+    ) -> StyledTable<'a, IndexedRow<T>> {
+        let len = data.len();
+        StyledTable::new(
+            StatefulTable::new(
+                StripedRow::from(IndexedRow::from(data)),
+                state,
+                Self::table_style(),
+                title,
+            ),
+            len,
+        )
+    }
+    /// Indexed table whose cells are drawn with the styled byte ranges returned by
+    /// `spans` for each row
+    pub fn spanned_table<'a, T: Tabular, F>(
+        data: Vec<T>,
+        state: TableState,
+        title: Option<String>,
+        spans: F,
+    ) -> StyledTable<'a, IndexedRow<T>>
+    where
+        F: Fn(&T) -> CellSpans,
+    {
+        let rows = data
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let spans = spans(row);
+                let cells = row
+                    .content()
+                    .iter()
+                    .enumerate()
+                    .map(|(col, cell)| styled_line(cell, spans.get(col).map_or(&[], |s| s)))
+                    .collect();
+                (cells, striped(i, row.style()))
+            })
+            .collect();
+        let mut table = Self::indexed_table(data, state, title);
+        table.rows = rows;
+        table
     }
     pub fn block<'a>() -> Block<'a> {
         let mut block = Block::new();
@@ -34,6 +90,52 @@ impl StyledWidget {
         }
         block
     }
+    pub fn popup_block<'a>() -> Block<'a> {
+        let mut block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(
+                Style::default().fg(Color::from_str(&CONFIG.theme.popup_border_color).unwrap()),
+            )
+            .style(Self::popup_style());
+        if CONFIG.theme.rounded_borders {
+            block = block.border_type(BorderType::Rounded)
+        }
+        block
+    }
+    /// Popup block whose border tells how serious the message is
+    pub fn severity_block<'a>(severity: Severity) -> Block<'a> {
+        let color = match severity {
+            Severity::Info => &CONFIG.theme.popup_border_color,
+            Severity::Warning => &CONFIG.theme.fg_changed_color,
+            Severity::Error => &CONFIG.theme.fg_blocked_color,
+        };
+        Self::popup_block().border_style(Style::default().fg(Color::from_str(color).unwrap()))
+    }
+    pub fn match_style() -> Style {
+        Style::default()
+            .fg(Color::from_str(&CONFIG.theme.fg_match_color).unwrap())
+            .bg(Color::from_str(&CONFIG.theme.bg_match_color).unwrap())
+    }
+    pub fn popup_style() -> Style {
+        Style::default()
+            .fg(Color::from_str(&CONFIG.theme.popup_fg_color).unwrap())
+            .bg(Color::from_str(&CONFIG.theme.popup_bg_color).unwrap())
+    }
+    pub fn status_style() -> Option<Style> {
+        let fg = CONFIG.theme.status_fg_color.as_ref();
+        let bg = CONFIG.theme.status_bg_color.as_ref();
+        if fg.is_none() && bg.is_none() {
+            return None;
+        }
+        let mut style = Style::default();
+        if let Some(fg) = fg {
+            style = style.fg(Color::from_str(fg).unwrap());
+        }
+        if let Some(bg) = bg {
+            style = style.bg(Color::from_str(bg).unwrap());
+        }
+        Some(style)
+    }
     pub fn table_padding<'a>() -> Padding {
         let mut padding = Padding::default();
         if CONFIG.theme.borders {
@@ -59,3 +161,371 @@ impl StyledWidget {
         }
     }
 }
+
+/// Styled byte ranges of the cells of a row, by column
+pub type CellSpans = Vec<Vec<(Range<usize>, Style)>>;
+
+/// Splits the cell at the bounds of its ranges, later ranges patching the style of
+/// the earlier ones they overlap
+fn styled_line<'a>(cell: &str, spans: &[(Range<usize>, Style)]) -> Line<'a> {
+    let mut bounds = vec![0, cell.len()];
+    for (range, _) in spans {
+        bounds.extend([range.start, range.end]);
+    }
+    let bounds = bounds
+        .into_iter()
+        .filter(|b| cell.is_char_boundary(*b))
+        .sorted()
+        .dedup()
+        .collect_vec();
+    let parts = bounds.iter().tuple_windows().map(|(start, end)| {
+        let style = spans
+            .iter()
+            .filter(|(range, _)| range.start <= *start && *end <= range.end)
+            .fold(Style::default(), |style, (_, span)| style.patch(*span));
+        Span::styled(cell[*start..*end].to_string(), style)
+    });
+    Line::from(parts.collect_vec())
+}
+
+/// Table built by [`StyledWidget`], adding paging keys, mouse wheel scrolling
+/// and the optional scrollbar on top of [`StatefulTable`].
+pub struct StyledTable<'a, T: Tabular> {
+    table: StatefulTable<'a, StripedRow<T>>,
+    len: usize,
+    page: usize,
+    // styled cells and style of every row, drawn in place of the plain content
+    rows: Vec<(Vec<Line<'a>>, Style)>,
+}
+impl<'a, T: Tabular> StyledTable<'a, T> {
+    fn new(table: StatefulTable<'a, StripedRow<T>>, len: usize) -> Self {
+        Self {
+            table,
+            len,
+            page: 1,
+            rows: vec![],
+        }
+    }
+    pub fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.table.draw(f, area);
+        self.draw_rows(f, area);
+
+        let body = Self::body(area);
+        self.page = (body.height as usize).max(1);
+
+        if CONFIG.theme.scrollbars && self.len > body.height as usize {
+            let mut state =
+                ScrollbarState::new(self.len).position(self.table.state().selected().unwrap_or(0));
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(None)
+                    .end_symbol(None),
+                body,
+                &mut state,
+            );
+        }
+    }
+    /// Colors the border, or the title row without borders, of the pane receiving the keys
+    pub fn draw_focus(&self, f: &mut Frame<'_>, area: Rect) {
+        let style =
+            Style::default().fg(Color::from_str(&CONFIG.theme.focused_border_color).unwrap());
+        let buf = f.buffer_mut();
+        let top = Rect {
+            height: 1.min(area.height),
+            ..area
+        };
+        buf.set_style(top, style);
+        if !CONFIG.theme.borders || area.height < 2 {
+            return;
+        }
+        let bottom = Rect {
+            y: area.bottom() - 1,
+            ..top
+        };
+        let left = Rect {
+            width: 1.min(area.width),
+            ..area
+        };
+        let right = Rect {
+            x: area.right().saturating_sub(1),
+            ..left
+        };
+        for side in [bottom, left, right] {
+            buf.set_style(side, style);
+        }
+    }
+    /// Draws the selected row and the row count at the right of the top border
+    pub fn draw_position(&self, f: &mut Frame<'_>, area: Rect) {
+        let Some(selected) = self.table.state().selected() else {
+            return;
+        };
+        if !CONFIG.theme.borders || self.len == 0 {
+            return;
+        }
+        let text = format!(" row {}/{} ", (selected + 1).min(self.len), self.len);
+        let width = (text.len() as u16).min(area.width.saturating_sub(2));
+        let pos = Rect {
+            x: area.right().saturating_sub(width + 1),
+            width,
+            height: 1.min(area.height),
+            ..area
+        };
+        f.render_widget(Paragraph::new(text), pos);
+    }
+    // the table is still drawn first, for the block and the scroll offset of the selection,
+    // its inside is then replaced by a table of the styled cells
+    fn draw_rows(&self, f: &mut Frame<'_>, area: Rect) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let style = StyledWidget::table_style();
+        let border = if CONFIG.theme.borders { 1 } else { 0 };
+        let inner = area.inner(Margin::new(border, border));
+        let names = T::column_names();
+        let alignments = T::column_alignments().unwrap_or_default();
+        let widths = T::column_constraints()
+            .into_iter()
+            .enumerate()
+            .map(|(col, constraint)| {
+                let header = names
+                    .as_ref()
+                    .and_then(|n| n.get(col))
+                    .map_or(0, |n| n.chars().count());
+                let content = self.rows.iter().filter_map(|(cells, _)| cells.get(col));
+                constraint(content.map(Line::width).fold(header, usize::max) as u16)
+            })
+            .collect_vec();
+        let rows = self.rows.iter().map(|(cells, row_style)| {
+            let cells = cells
+                .iter()
+                .enumerate()
+                .map(|(col, cell)| match alignments.get(col) {
+                    Some(alignment) => cell.clone().alignment(*alignment),
+                    None => cell.clone(),
+                });
+            Row::new(cells).style(*row_style)
+        });
+        let mut table = Table::new(rows, widths)
+            .style(style.normal)
+            .row_highlight_style(style.highlight)
+            .column_spacing(style.column_spacing);
+        if let Some(names) = names {
+            table = table.header(Row::new(names).style(style.header));
+        }
+        let mut state = self.table.state().clone();
+        f.render_widget(Clear, inner);
+        f.render_stateful_widget(table, inner, &mut state);
+    }
+    // area of the rows, without borders and header
+    fn body(area: Rect) -> Rect {
+        let border = if CONFIG.theme.borders { 1 } else { 0 };
+        let header = if T::column_names().is_some() { 1 } else { 0 };
+        Rect {
+            y: area.y + border + header,
+            height: area.height.saturating_sub(2 * border + header),
+            ..area
+        }
+    }
+    /// Index of the row under the screen position, counting the rows scrolled out of view
+    pub fn row_at(&self, pos: Position) -> Option<usize> {
+        let visible = self.table.screen_coords_to_row_index(pos);
+        table_row(visible, self.table.state().offset(), self.len)
+    }
+    /// Selects the row under the screen position, false when there is none
+    pub fn select_at(&mut self, pos: Position) -> bool {
+        let Some(row) = self.row_at(pos) else {
+            return false;
+        };
+        let (code, steps) = self.steps_to(row);
+        self.step(code, steps);
+        true
+    }
+    pub fn update(&mut self, ev: &Event) {
+        let Some((code, steps)) = self.page_steps(ev) else {
+            self.table.update(ev);
+            return;
+        };
+        self.step(code, steps);
+    }
+    fn step(&mut self, code: KeyCode, steps: usize) {
+        let step = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        for _ in 0..steps.min(self.len) {
+            self.table.update(&step);
+        }
+    }
+    fn steps_to(&self, row: usize) -> (KeyCode, usize) {
+        let selected = self.table.state().selected().unwrap_or(0);
+        match row >= selected {
+            true => (KeyCode::Down, row - selected),
+            false => (KeyCode::Up, selected - row),
+        }
+    }
+    fn page_steps(&self, ev: &Event) -> Option<(KeyCode, usize)> {
+        match ev {
+            Event::Key(key) => match key.code {
+                KeyCode::PageDown => Some((KeyCode::Down, self.page)),
+                KeyCode::PageUp => Some((KeyCode::Up, self.page)),
+                KeyCode::End => Some((KeyCode::Down, self.len)),
+                KeyCode::Home => Some((KeyCode::Up, self.len)),
+                _ => None,
+            },
+            Event::Mouse(mouse) => match mouse.kind {
+                MouseEventKind::ScrollDown => Some((KeyCode::Down, 1)),
+                MouseEventKind::ScrollUp => Some((KeyCode::Up, 1)),
+                // select the clicked row by stepping to it, so that views reading the
+                // selection after a click get that row on a scrolled table too
+                MouseEventKind::Down(MouseButton::Left | MouseButton::Right) => {
+                    let pos = Position {
+                        x: mouse.column,
+                        y: mouse.row,
+                    };
+                    Some(self.steps_to(self.row_at(pos)?))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+impl<'a, T: Tabular> Deref for StyledTable<'a, T> {
+    type Target = StatefulTable<'a, StripedRow<T>>;
+    fn deref(&self) -> &Self::Target {
+        &self.table
+    }
+}
+
+// how far the widen/narrow column commands move a column from its fitted width
+const MAX_RESIZE: i16 = 64;
+
+/// Width bounds of table columns, computed from the content width and
+/// adjusted at runtime with the widen/narrow column commands.
+pub struct ColumnWidths<const N: usize>([AtomicI16; N]);
+impl<const N: usize> Default for ColumnWidths<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<const N: usize> ColumnWidths<N> {
+    pub const fn new() -> Self {
+        Self([const { AtomicI16::new(0) }; N])
+    }
+    pub fn fit(&self, col: usize, width: u16, min: u16, max: u16) -> u16 {
+        let delta = self.0[col].load(Ordering::Relaxed);
+        (width.clamp(min, max) as i16).saturating_add(delta).max(1) as u16
+    }
+    pub const fn columns(&self) -> usize {
+        N
+    }
+    pub fn resize(&self, col: usize, delta: i16) {
+        if let Some(w) = self.0.get(col) {
+            let _ = w.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |d| {
+                Some(d.saturating_add(delta).clamp(-MAX_RESIZE, MAX_RESIZE))
+            });
+        }
+    }
+}
+
+/// The style of the row at the index, striped unless it sets its own background
+fn striped(index: usize, style: Style) -> Style {
+    if CONFIG.theme.striped_rows && index % 2 == 1 && style.bg.is_none() {
+        return style.bg(Color::from_str(&CONFIG.theme.bg_striped_color).unwrap());
+    }
+    style
+}
+
+/// Table row that gets `theme.bg_striped_color` on every other row when
+/// `theme.striped_rows` is set, unless the row sets its own background.
+pub struct StripedRow<T>(usize, T);
+impl<T> StripedRow<T> {
+    pub fn from(v: Vec<T>) -> Vec<Self> {
+        v.into_iter()
+            .enumerate()
+            .map(|(i, t)| StripedRow(i, t))
+            .collect()
+    }
+}
+impl<T: Tabular> Tabular for StripedRow<T> {
+    type Value = T::Value;
+    type ColumnValue = T::ColumnValue;
+    fn column_values() -> Vec<Self::ColumnValue> {
+        T::column_values()
+    }
+    fn value(&self) -> Self::Value {
+        self.1.value()
+    }
+    fn content(&self) -> Vec<String> {
+        self.1.content()
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        T::column_constraints()
+    }
+    fn column_names() -> Option<Vec<String>> {
+        T::column_names()
+    }
+    fn column_alignments() -> Option<Vec<Alignment>> {
+        T::column_alignments()
+    }
+    fn style(&self) -> Style {
+        striped(self.0, self.1.style())
+    }
+}
+
+/// Glyphs shown in table cells, selected by `theme.icons`. Every glyph of a
+/// set occupies the same number of cells so columns stay aligned.
+pub struct Icons;
+impl Icons {
+    pub fn enabled() -> bool {
+        CONFIG.theme.icons != "none"
+    }
+    pub fn device(category: DeviceCategory) -> &'static str {
+        match (CONFIG.theme.icons.as_str(), category) {
+            ("ascii", DeviceCategory::Audio) => "A",
+            ("ascii", DeviceCategory::Input) => "K",
+            ("ascii", DeviceCategory::Phone) => "P",
+            ("ascii", DeviceCategory::Sensor) => "S",
+            ("ascii", DeviceCategory::Other) => "*",
+            ("unicode", DeviceCategory::Audio) => "♫",
+            ("unicode", DeviceCategory::Input) => "⌨",
+            ("unicode", DeviceCategory::Phone) => "☎",
+            ("unicode", DeviceCategory::Sensor) => "◉",
+            ("unicode", DeviceCategory::Other) => "•",
+            ("nerd", DeviceCategory::Audio) => "\u{f025}",
+            ("nerd", DeviceCategory::Input) => "\u{f11c}",
+            ("nerd", DeviceCategory::Phone) => "\u{f10b}",
+            ("nerd", DeviceCategory::Sensor) => "\u{f2db}",
+            ("nerd", DeviceCategory::Other) => "\u{f293}",
+            _ => "",
+        }
+    }
+    /// Frame of a busy indicator, advancing every tick
+    pub fn spinner(tick: u64) -> &'static str {
+        let frames: &[&str] = match CONFIG.theme.icons.as_str() {
+            "unicode" | "nerd" => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            _ => &["|", "/", "-", "\\"],
+        };
+        frames[tick as usize % frames.len()]
+    }
+    pub fn battery(percentage: u8) -> &'static str {
+        let level = (percentage.min(100) as usize + 12) / 25;
+        match CONFIG.theme.icons.as_str() {
+            "ascii" => ["[    ]", "[#   ]", "[##  ]", "[### ]", "[####]"][level],
+            "unicode" => ["▁", "▂", "▄", "▆", "█"][level],
+            "nerd" => ["\u{f244}", "\u{f243}", "\u{f242}", "\u{f241}", "\u{f240}"][level],
+            _ => "",
+        }
+    }
+    pub fn signal(rssi: i16) -> &'static str {
+        let level = match rssi {
+            ..=-90 => 0,
+            -89..=-80 => 1,
+            -79..=-70 => 2,
+            -69..=-60 => 3,
+            _ => 4,
+        };
+        match CONFIG.theme.icons.as_str() {
+            "ascii" => ["....", "|...", "||..", "|||.", "||||"][level],
+            "unicode" | "nerd" => ["    ", "▂   ", "▂▄  ", "▂▄▆ ", "▂▄▆█"][level],
+            _ => "",
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// Interval at which the event loop redraws, even without input
+pub const TICK: Duration = Duration::from_millis(100);
+
+static START: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Ticks elapsed since startup, for animation frames
+pub fn ticks() -> u64 {
+    (START.elapsed().as_millis() / TICK.as_millis()) as u64
+}
+/// Time left until the next tick, used as the input poll timeout
+pub fn until_next_tick() -> Duration {
+    let elapsed = START.elapsed().as_millis() % TICK.as_millis();
+    TICK - Duration::from_millis(elapsed as u64)
+}
+
+/// Items that become due after a delay, checked once per tick
+pub struct Timers<T> {
+    pending: Vec<(Instant, T)>,
+}
+impl<T> Default for Timers<T> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+}
+impl<T> Timers<T> {
+    pub fn after(&mut self, delay: Duration, item: T) {
+        self.pending.push((Instant::now() + delay, item));
+    }
+    pub fn take_due(&mut self) -> Vec<T> {
+        let now = Instant::now();
+        let (due, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|(at, _)| *at <= now);
+        self.pending = pending;
+        due.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timers_fire_once_when_due() {
+        let mut timers = Timers::default();
+        timers.after(Duration::ZERO, 1);
+        timers.after(Duration::from_secs(60), 2);
+        assert_eq!(timers.take_due(), vec![1]);
+        assert!(timers.take_due().is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use bluer::Address;
+
+use crate::config::{Beacon, BeaconKey};
+use crate::identity::resolves;
+
+/// A beacon that came into range or went away
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    pub name: String,
+    /// Last address the beacon advertised with, unknown if it was never seen
+    pub address: Option<Address>,
+    pub present: bool,
+}
+
+struct Watched {
+    beacon: Beacon,
+    address: Option<Address>,
+    present: Option<bool>,
+    last_seen: Instant,
+}
+
+/// Present/away state of the configured beacons, driven by their advertisements
+pub struct BeaconTracker {
+    watched: Vec<Watched>,
+}
+impl BeaconTracker {
+    pub fn new(beacons: &[Beacon], now: Instant) -> Self {
+        Self {
+            watched: beacons
+                .iter()
+                .map(|beacon| Watched {
+                    beacon: beacon.clone(),
+                    address: None,
+                    present: None,
+                    last_seen: now,
+                })
+                .collect(),
+        }
+    }
+    /// Notes an advertisement, reporting the beacons it brought back
+    pub fn seen(&mut self, address: Address, now: Instant) -> Vec<Report> {
+        let mut reports = vec![];
+        for w in self.watched.iter_mut() {
+            let matches = match &w.beacon.key {
+                BeaconKey::Address(a) => *a == address,
+                BeaconKey::Irk(irk) => resolves(irk, &address),
+            };
+            if !matches {
+                continue;
+            }
+            w.address = Some(address);
+            w.last_seen = now;
+            if w.present != Some(true) {
+                w.present = Some(true);
+                reports.push(w.report());
+            }
+        }
+        reports
+    }
+    /// Reports the beacons silent for longer than `away_after`, including the
+    /// ones never seen since the tracker started
+    pub fn expire(&mut self, away_after: Duration, now: Instant) -> Vec<Report> {
+        let mut reports = vec![];
+        for w in self.watched.iter_mut() {
+            if w.present != Some(false) && now.duration_since(w.last_seen) >= away_after {
+                w.present = Some(false);
+                reports.push(w.report());
+            }
+        }
+        reports
+    }
+}
+impl Watched {
+    fn report(&self) -> Report {
+        Report {
+            name: self.beacon.name.clone(),
+            address: self.address,
+            present: self.present == Some(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IRK: [u8; 16] = [
+        0xec, 0x02, 0x34, 0xa3, 0x57, 0xc8, 0xad, 0x05, 0x34, 0x10, 0x10, 0xa6, 0x0a, 0x39, 0x7d,
+        0x9b,
+    ];
+
+    #[test]
+    fn reports_transitions_once() {
+        let start = Instant::now();
+        let keys = Beacon {
+            name: "keys".to_string(),
+            key: BeaconKey::Irk(IRK),
+        };
+        let mut tracker = BeaconTracker::new(&[keys], start);
+        let rpa = Address::new([0x70, 0x81, 0x94, 0x0d, 0xfb, 0xaa]);
+        let away_after = Duration::from_secs(60);
+
+        let seen = tracker.seen(rpa, start + Duration::from_secs(1));
+        assert_eq!(
+            seen,
+            [Report {
+                name: "keys".to_string(),
+                address: Some(rpa),
+                present: true
+            }]
+        );
+        assert!(tracker.seen(rpa, start + Duration::from_secs(2)).is_empty());
+        assert!(tracker
+            .expire(away_after, start + Duration::from_secs(61))
+            .is_empty());
+        let away = tracker.expire(away_after, start + Duration::from_secs(62));
+        assert!(away.len() == 1 && !away[0].present);
+        assert!(tracker
+            .expire(away_after, start + Duration::from_secs(90))
+            .is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+/// Tail shared by every uuid derived from a 16 bit assigned number
+const BASE_SUFFIX: &str = "-0000-1000-8000-00805f9b34fb";
+
+/// Assigned numbers of the common services and characteristics, from the Bluetooth SIG lists
+const ASSIGNED: &[(u16, &str)] = &[
+    (0x1101, "Serial Port"),
+    (0x1103, "Dial-up Networking"),
+    (0x1105, "OBEX Object Push"),
+    (0x1106, "OBEX File Transfer"),
+    (0x1108, "Headset"),
+    (0x110a, "Audio Source"),
+    (0x110b, "Audio Sink"),
+    (0x110c, "A/V Remote Control Target"),
+    (0x110d, "Advanced Audio Distribution"),
+    (0x110e, "A/V Remote Control"),
+    (0x110f, "A/V Remote Control Controller"),
+    (0x1112, "Headset Audio Gateway"),
+    (0x1115, "PAN User"),
+    (0x1116, "Network Access Point"),
+    (0x1117, "Group Network"),
+    (0x111e, "Handsfree"),
+    (0x111f, "Handsfree Audio Gateway"),
+    (0x1124, "Human Interface Device"),
+    (0x112d, "SIM Access"),
+    (0x112f, "Phonebook Access Server"),
+    (0x1132, "Message Access Server"),
+    (0x1133, "Message Notification Server"),
+    (0x1200, "PnP Information"),
+    (0x1203, "Generic Audio"),
+    (0x1800, "Generic Access"),
+    (0x1801, "Generic Attribute"),
+    (0x1802, "Immediate Alert"),
+    (0x1803, "Link Loss"),
+    (0x1804, "Tx Power"),
+    (0x1805, "Current Time"),
+    (0x1808, "Glucose"),
+    (0x1809, "Health Thermometer"),
+    (0x180a, "Device Information"),
+    (0x180d, "Heart Rate"),
+    (0x180f, "Battery"),
+    (0x1810, "Blood Pressure"),
+    (0x1812, "Human Interface Device over GATT"),
+    (0x1813, "Scan Parameters"),
+    (0x1814, "Running Speed and Cadence"),
+    (0x1816, "Cycling Speed and Cadence"),
+    (0x1818, "Cycling Power"),
+    (0x1819, "Location and Navigation"),
+    (0x181a, "Environmental Sensing"),
+    (0x181c, "User Data"),
+    (0x181d, "Weight Scale"),
+    (0x1826, "Fitness Machine"),
+    (0x1843, "Audio Input Control"),
+    (0x1844, "Volume Control"),
+    (0x184e, "Audio Stream Control"),
+    (0x184f, "Broadcast Audio Scan"),
+    (0x1850, "Published Audio Capabilities"),
+    (0x1853, "Common Audio"),
+    (0x1854, "Hearing Access"),
+    (0x2a00, "Device Name"),
+    (0x2a01, "Appearance"),
+    (0x2a04, "Peripheral Preferred Connection Parameters"),
+    (0x2a05, "Service Changed"),
+    (0x2a19, "Battery Level"),
+    (0x2a1c, "Temperature Measurement"),
+    (0x2a23, "System ID"),
+    (0x2a24, "Model Number"),
+    (0x2a25, "Serial Number"),
+    (0x2a26, "Firmware Revision"),
+    (0x2a27, "Hardware Revision"),
+    (0x2a28, "Software Revision"),
+    (0x2a29, "Manufacturer Name"),
+    (0x2a2b, "Current Time"),
+    (0x2a37, "Heart Rate Measurement"),
+    (0x2a38, "Body Sensor Location"),
+    (0x2a4a, "HID Information"),
+    (0x2a4b, "Report Map"),
+    (0x2a4d, "Report"),
+    (0x2a50, "PnP ID"),
+    (0x2a53, "RSC Measurement"),
+    (0x2a5b, "CSC Measurement"),
+    (0x2a63, "Cycling Power Measurement"),
+    (0x2a6d, "Pressure"),
+    (0x2a6e, "Temperature"),
+    (0x2a6f, "Humidity"),
+    (0x2a9d, "Weight Measurement"),
+    (0x2aa6, "Central Address Resolution"),
+    (0xfd6f, "Exposure Notification"),
+    (0xfe2c, "Google Fast Pair"),
+    (0xfe9f, "Google"),
+    (0xfd5a, "Samsung"),
+];
+
+/// Name behind a lowercase hyphenated uuid, if it is a known assigned number
+pub fn uuid_name(uuid: &str) -> Option<&'static str> {
+    let short = uuid.strip_suffix(BASE_SUFFIX)?.strip_prefix("0000")?;
+    let short = u16::from_str_radix(short, 16).ok()?;
+    ASSIGNED
+        .iter()
+        .find(|(number, _)| *number == short)
+        .map(|(_, name)| *name)
+}
+
+/// Whether the uuid was picked by a vendor rather than derived from an assigned number
+pub fn is_custom(uuid: &str) -> bool {
+    !uuid.ends_with(BASE_SUFFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_assigned_numbers_only() {
+        assert_eq!(
+            uuid_name("0000180f-0000-1000-8000-00805f9b34fb"),
+            Some("Battery")
+        );
+        assert_eq!(uuid_name("0000ffff-0000-1000-8000-00805f9b34fb"), None);
+        assert_eq!(uuid_name("6e400001-b5a3-f393-e0a9-e50e24dcca9e"), None);
+        assert!(is_custom("6e400001-b5a3-f393-e0a9-e50e24dcca9e"));
+    }
+}
@@ -1,38 +1,70 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
 use std::vec;
 
+use chrono::Utc;
+use itertools::Itertools;
 use ratatui::crossterm::event::{Event, KeyCode, MouseButton, MouseEventKind};
-use ratatui::layout::{Constraint, Direction, Layout, Position, Rect};
-use ratatui::widgets::{Block, Borders, Paragraph, TableState};
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Margin, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Axis, Chart, Clear, Dataset, GraphType, Paragraph, Scrollbar, ScrollbarOrientation,
+    ScrollbarState, Sparkline, TableState, Wrap,
+};
 use ratatui::Frame;
 use ratatui_helpers::keymap::{KeyMap, ShortCut};
-use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
+use ratatui_helpers::stateful_table::{IndexedRow, Tabular};
 use ratatui_helpers::view::View;
 
 use crate::app::{AppRequest, ViewKind};
 use crate::bt_manager::BtManager;
-use crate::helpers::centered_rect;
+use crate::config::{Setup, THEME_PRESETS};
+use crate::filter::Filter;
+use crate::globals::CONFIG;
+use crate::helpers::{centered_rect, paste_line};
+use crate::hexdump::{Endian, HexdumpPane};
 use crate::keymaps::{
-    AdapterViewCommand, AdapterViewKeyMap, AppCommand, AppKeyMap, DeviceViewCommand,
+    bindings_for, AdapterViewCommand, AdapterViewKeyMap, AppCommand, AppKeyMap, DeviceViewCommand,
     DeviceViewKeyMap,
 };
-use crate::models::{Adapter, AdapterAction, Device, DeviceAction, DeviceId};
-use crate::theme::StyledWidget;
+use crate::models::{
+    ActionRecord, Adapter, AdapterAction, AdapterColumn, AdapterId, BatchRow, BatchStatus, Device,
+    DeviceAction, DeviceCategory, DeviceId, DeviceOrder, GattRow, PairingChoice, PinCode, Popup,
+    PrivacyChoice, PropertyRow, QuickRow, QuitChoice, RetryChoice, ServiceChoice, SetupField,
+    StartupChoice, SummaryRow, UnblockChoice, UuidRow, WriteType, ADAPTER_COLUMNS, DEVICE_COLUMNS,
+};
+use crate::mouse::Clicks;
+use crate::state::{BatterySample, Snippet};
+use crate::theme::{StyledTable, StyledWidget};
 
 pub struct AdapterView<'a> {
-    table: StatefulTable<'a, Adapter>,
+    table: StyledTable<'a, Adapter>,
     keymap: AdapterViewKeyMap,
+    clicks: Clicks<AdapterId>,
+    // position among the configured columns of the one being resized
+    column: usize,
 }
 impl AdapterView<'_> {
     pub fn new(bt: &BtManager, state: TableState) -> Self {
+        let adapters = bt.get_adapters(&Adapter::BY_NAME);
         Self {
-            table: StyledWidget::table(
-                bt.get_adapters(&Adapter::BY_NAME),
-                state,
-                Some("Adapters".into()),
-            ),
+            table: StyledWidget::table(adapters, state, Some("Adapters".into())),
             keymap: KeyMap::default(),
+            clicks: Clicks::default(),
+            column: 1,
         }
     }
+    // index into ADAPTER_COLUMNS, which doesn't follow the configured order
+    fn focused_column(&self) -> usize {
+        let columns = AdapterColumn::configured();
+        columns
+            .get(self.column)
+            .or(columns.last())
+            .map_or(0, |c| *c as usize)
+    }
 }
 impl View for AdapterView<'_> {
     type Model = BtManager;
@@ -52,7 +84,10 @@ impl View for AdapterView<'_> {
         "bluerat - adapters".to_string()
     }
     fn refresh(&mut self, model: &Self::Model) {
+        let (clicks, column) = (self.clicks, self.column);
         *self = Self::new(model, self.table.state().clone());
+        self.clicks = clicks;
+        self.column = column;
     }
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
         self.table.draw(f, area);
@@ -80,6 +115,7 @@ impl View for AdapterView<'_> {
                                 );
                             }
                         }
+                        AdapterViewCommand::ToggleScanType => return AppRequest::ToggleScanType,
                         AdapterViewCommand::OpenMenu => {
                             if let Some(adapter) = self.table.selected_value() {
                                 return AppRequest::OpenAdapterActionsViewAt(
@@ -88,6 +124,11 @@ impl View for AdapterView<'_> {
                                 );
                             }
                         }
+                        AdapterViewCommand::Summary => {
+                            if let Some(adapter) = self.table.selected_value() {
+                                return AppRequest::OpenSummaryView(adapter.id);
+                            }
+                        }
                         AdapterViewCommand::Info => {
                             if let Some(adapter) = self.table.selected_value() {
                                 return AppRequest::ExecAdapterAction(
@@ -118,6 +159,24 @@ impl View for AdapterView<'_> {
                                 );
                             }
                         }
+                        AdapterViewCommand::Repeat => {
+                            if let Some(adapter) = self.table.selected_value() {
+                                return AppRequest::RepeatAdapterAction(adapter.clone());
+                            }
+                        }
+                        AdapterViewCommand::WidenColumn => {
+                            ADAPTER_COLUMNS.resize(self.focused_column(), 2);
+                        }
+                        AdapterViewCommand::NarrowColumn => {
+                            ADAPTER_COLUMNS.resize(self.focused_column(), -2);
+                        }
+                        AdapterViewCommand::PrevColumn => {
+                            self.column = self.column.saturating_sub(1);
+                        }
+                        AdapterViewCommand::NextColumn => {
+                            let last = AdapterColumn::configured().len().saturating_sub(1);
+                            self.column = (self.column + 1).min(last);
+                        }
                     }
                 }
             }
@@ -128,19 +187,16 @@ impl View for AdapterView<'_> {
                 };
                 match ev.kind {
                     MouseEventKind::Down(MouseButton::Left) => {
-                        if let Some(row) = self.table.screen_coords_to_row_index(pos)
-                            && let Some(idx) = self.table.selected_row()
-                            && row == idx
+                        if self.table.select_at(pos)
                             && let Some(adapter) = self.table.selected_value()
+                            && self.clicks.double_click(adapter.id)
                         {
                             return AppRequest::CloseView
                                 + AppRequest::OpenDevicesView(adapter.clone());
                         }
                     }
                     MouseEventKind::Down(MouseButton::Right) => {
-                        if let Some(row) = self.table.screen_coords_to_row_index(pos)
-                            && let Some(idx) = self.table.selected_row()
-                            && row == idx
+                        if self.table.select_at(pos)
                             && let Some(adapter) = self.table.selected_value()
                         {
                             return AppRequest::OpenAdapterActionsViewAt(
@@ -160,7 +216,7 @@ impl View for AdapterView<'_> {
 
 pub struct AdapterActionsView<'a> {
     adapter: Adapter,
-    table: StatefulTable<'a, AdapterAction>,
+    table: StyledTable<'a, AdapterAction>,
     pos: Position,
     area: Rect,
 }
@@ -229,7 +285,7 @@ impl View for AdapterActionsView<'_> {
                             return AppRequest::CloseView;
                         }
 
-                        if self.table.screen_coords_to_row_index(pos).is_some()
+                        if self.table.row_at(pos).is_some()
                             && let Some(value) = self.table.selected_value()
                         {
                             return AppRequest::CloseView
@@ -245,21 +301,62 @@ impl View for AdapterActionsView<'_> {
     }
 }
 
+/// Filter listing the devices found this session that were not acknowledged
+const NEW_FILTER: &str = "state:new";
+
+/// Text typed at the bottom of the device list, taking every key until closed
+enum DevicePrompt {
+    /// Filters the rows while typing, Esc restores the filter it started from
+    Filter {
+        initial: String,
+        text: String,
+    },
+    Jump(String),
+}
+impl DevicePrompt {
+    fn text(&mut self) -> &mut String {
+        match self {
+            DevicePrompt::Filter { text, .. } | DevicePrompt::Jump(text) => text,
+        }
+    }
+    fn input<'a>(&self) -> Paragraph<'a> {
+        let (line, title) = match self {
+            DevicePrompt::Filter { text, .. } => {
+                (format!("/{text}"), "Filter (name, addr:, type:, state:)")
+            }
+            DevicePrompt::Jump(text) => (format!("g{text}"), "Jump to row number or name"),
+        };
+        Paragraph::new(line)
+            .style(StyledWidget::popup_style())
+            .block(StyledWidget::popup_block().title(title))
+    }
+}
+
 pub struct DeviceView<'a> {
     adapter: Adapter,
     adapter_info: Paragraph<'a>,
-    table: StatefulTable<'a, IndexedRow<Device>>,
+    table: StyledTable<'a, IndexedRow<Device>>,
     layout: Layout,
     keymap: DeviceViewKeyMap,
+    order: DeviceOrder,
+    filter: Option<Filter>,
+    marked: HashSet<DeviceId>,
+    clicks: Clicks<DeviceId>,
+    // the column being resized, the state column fills the rest
+    column: usize,
+    prompt: Option<DevicePrompt>,
 }
 impl DeviceView<'_> {
     pub fn new(adapter: Adapter, state: TableState) -> Self {
+        let order = CONFIG
+            .adapter(&adapter.name, &adapter.id.to_string())
+            .sort
+            .unwrap_or_default();
+        Self::with_order(adapter, state, order)
+    }
+    pub fn with_order(adapter: Adapter, state: TableState, order: DeviceOrder) -> Self {
         Self {
-            table: StyledWidget::indexed_table(
-                adapter.devices.clone(),
-                state,
-                Some("Devices".into()),
-            ),
+            table: StyledWidget::indexed_table(vec![], state, None),
             adapter_info: Paragraph::new(adapter.get_info_line())
                 .block(StyledWidget::block().title("Adapter".to_string())),
             layout: Layout::default()
@@ -267,6 +364,135 @@ impl DeviceView<'_> {
                 .constraints(vec![Constraint::Length(3), Constraint::Fill(1)]),
             adapter,
             keymap: KeyMap::default(),
+            order,
+            filter: None,
+            marked: HashSet::new(),
+            clicks: Clicks::default(),
+            // the alias is the column that usually needs room
+            column: 1,
+            prompt: None,
+        }
+        .rebuilt()
+    }
+    /// Selects the device, the rows being sorted by the view
+    pub fn with_focus(mut self, device_id: &DeviceId) -> Self {
+        self.jump(&device_id.to_string());
+        self
+    }
+    fn rebuilt(mut self) -> Self {
+        self.rebuild();
+        self
+    }
+    /// Filters the rows with the text, an empty one shows them all
+    fn set_filter(&mut self, text: &str) -> AppRequest {
+        match Filter::parse(text) {
+            Ok(filter) => self.filter = Some(filter).filter(|f| !f.is_empty()),
+            Err(e) => return AppRequest::ShowStatus(e),
+        }
+        self.rebuild();
+        AppRequest::None
+    }
+    fn update_prompt(&mut self, ev: &Event) -> AppRequest {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return AppRequest::None;
+        };
+        match ev {
+            Event::Paste(text) => prompt.text().push_str(&paste_line(text)),
+            Event::Key(ev) => match ev.code {
+                KeyCode::Enter => {
+                    if let Some(DevicePrompt::Jump(target)) = self.prompt.take() {
+                        self.jump(&target);
+                    }
+                    return AppRequest::None;
+                }
+                KeyCode::Esc => {
+                    return match self.prompt.take() {
+                        Some(DevicePrompt::Filter { initial, .. }) => self.set_filter(&initial),
+                        _ => AppRequest::None,
+                    };
+                }
+                KeyCode::Backspace => {
+                    let _ = prompt.text().pop();
+                }
+                KeyCode::Char(c) => prompt.text().push(c),
+                _ => return AppRequest::None,
+            },
+            _ => return AppRequest::None,
+        }
+        match self.prompt.as_ref() {
+            Some(DevicePrompt::Filter { text, .. }) => self.set_filter(&text.clone()),
+            _ => AppRequest::None,
+        }
+    }
+    fn rebuild(&mut self) {
+        self.table = self.styled_table(self.rows(), self.table.state().clone());
+    }
+    // flags stand out and the parts matching the filter are highlighted
+    fn styled_table<'a>(
+        &self,
+        rows: Vec<Device>,
+        state: TableState,
+    ) -> StyledTable<'a, IndexedRow<Device>> {
+        let filter = self.filter.clone();
+        StyledWidget::spanned_table(rows, state, Some(self.table_title()), move |device| {
+            let mut spans = device.cell_spans();
+            if let Some(filter) = &filter {
+                for (cell, spans) in device.content().iter().zip(spans.iter_mut()) {
+                    let style = StyledWidget::match_style();
+                    spans.extend(filter.match_ranges(cell).into_iter().map(|r| (r, style)));
+                }
+            }
+            spans
+        })
+    }
+    fn table_title(&self) -> String {
+        let mut title = "Devices".to_string();
+        if self.order != DeviceOrder::Address {
+            title = format!("{title} ({})", self.order);
+        }
+        if let Some(filter) = &self.filter {
+            title = format!("{title} [{filter}]");
+        }
+        if !self.marked.is_empty() {
+            title = format!("{title} {} marked", self.marked.len());
+        }
+        let new = self.adapter.devices.iter().filter(|d| d.is_new).count();
+        if new > 0 {
+            title = format!("{title} {new} new");
+        }
+        title
+    }
+    fn rows(&self) -> Vec<Device> {
+        self.adapter
+            .devices
+            .iter()
+            .filter(|d| self.filter.as_ref().is_none_or(|f| f.matches(d)))
+            .cloned()
+            .map(|mut d| {
+                d.is_marked = self.marked.contains(&d.id);
+                d
+            })
+            .sorted_by(self.order.sorter().0)
+            .collect()
+    }
+    /// Selects the row with this 1-based number, or the first device whose
+    /// alias or address starts with the text
+    fn jump(&mut self, target: &str) {
+        let target = target.trim();
+        let rows = self.rows();
+        let idx = match target.parse::<usize>() {
+            Ok(n) => Some(n.clamp(1, rows.len().max(1)) - 1),
+            Err(_) => {
+                let prefix = target.to_lowercase();
+                rows.iter().position(|d| {
+                    d.alias.to_lowercase().starts_with(&prefix)
+                        || d.id.to_string().to_lowercase().starts_with(&prefix)
+                })
+            }
+        };
+        if let Some(idx) = idx {
+            let state = self.table.state().clone().with_selected(idx);
+            self.table = self.styled_table(rows, state);
         }
     }
 }
@@ -275,16 +501,31 @@ impl View for DeviceView<'_> {
     type Signal = AppRequest;
     type Kind = ViewKind;
     fn kind(&self) -> ViewKind {
-        ViewKind::DeviceView
+        // typing in the prompt takes the global shortcuts too
+        match self.prompt {
+            Some(_) => ViewKind::InputView,
+            None => ViewKind::DeviceView,
+        }
     }
     fn title(&self) -> String {
         "bluerat - devices".to_string()
     }
     fn refresh(&mut self, model: &Self::Model) {
-        if let Some(adapter) = model.get_adapter(&self.adapter.id) {
-            *self = Self::new(adapter.clone(), self.table.state().clone());
-        } else if let Some(adapter) = model.get_random_adapter() {
-            *self = Self::new(adapter.clone(), self.table.state().clone());
+        if let Some(adapter) = model
+            .get_adapter(&self.adapter.id)
+            .or_else(|| model.get_random_adapter())
+        {
+            let mut marked = std::mem::take(&mut self.marked);
+            marked.retain(|id| adapter.get_device(id).is_some());
+            let (clicks, column) = (self.clicks, self.column);
+            let (filter, prompt) = (self.filter.take(), self.prompt.take());
+            *self = Self::with_order(adapter.clone(), self.table.state().clone(), self.order);
+            self.marked = marked;
+            self.clicks = clicks;
+            self.column = column;
+            self.filter = filter;
+            self.prompt = prompt;
+            self.rebuild();
         } else {
             self.table = StyledWidget::indexed_table(
                 vec![],
@@ -298,8 +539,21 @@ impl View for DeviceView<'_> {
         let layout = self.layout.split(area);
         f.render_widget(self.adapter_info.clone(), layout[0]);
         self.table.draw(f, layout[1]);
+        self.table.draw_position(f, layout[1]);
+        if let Some(prompt) = &self.prompt {
+            let input = Rect {
+                y: area.bottom().saturating_sub(3),
+                height: 3.min(area.height),
+                ..area
+            };
+            f.render_widget(Clear, input);
+            f.render_widget(prompt.input(), input);
+        }
     }
     fn update(&mut self, ev: &Event) -> AppRequest {
+        if self.prompt.is_some() {
+            return self.update_prompt(ev);
+        }
         self.table.update(ev);
         match ev {
             Event::Key(ev) => {
@@ -369,6 +623,109 @@ impl View for DeviceView<'_> {
                                 return AppRequest::MonitorDevice(self.adapter.id, device.id);
                             }
                         }
+                        DeviceViewCommand::Gatt => {
+                            if let Some(device) = self.table.selected_value() {
+                                return AppRequest::OpenGattView(self.adapter.id, device.id);
+                            }
+                        }
+                        DeviceViewCommand::BatteryHistory => {
+                            if let Some(device) = self.table.selected_value() {
+                                return AppRequest::OpenBatteryView(device.id);
+                            }
+                        }
+                        DeviceViewCommand::ToggleOrder => {
+                            self.order = self.order.next();
+                            let state = TableState::new().with_selected(0);
+                            self.table = self.styled_table(self.rows(), state);
+                        }
+                        DeviceViewCommand::ToggleMark => {
+                            if let Some(device) = self.table.selected_value() {
+                                let id = device.id;
+                                if !self.marked.remove(&id) {
+                                    let _ = self.marked.insert(id);
+                                }
+                                self.rebuild();
+                            }
+                        }
+                        DeviceViewCommand::PairMarked => {
+                            if !self.marked.is_empty() {
+                                let devices = self
+                                    .adapter
+                                    .devices
+                                    .iter()
+                                    .filter(|d| self.marked.contains(&d.id))
+                                    .map(|d| d.id)
+                                    .collect();
+                                self.marked.clear();
+                                self.rebuild();
+                                return AppRequest::BatchPair(self.adapter.id, devices);
+                            }
+                        }
+                        DeviceViewCommand::Filter => {
+                            let text = self
+                                .filter
+                                .as_ref()
+                                .map(|f| f.to_string())
+                                .unwrap_or_default();
+                            self.prompt = Some(DevicePrompt::Filter {
+                                initial: text.clone(),
+                                text,
+                            });
+                        }
+                        DeviceViewCommand::Jump => {
+                            self.prompt = Some(DevicePrompt::Jump(String::new()))
+                        }
+                        DeviceViewCommand::ConnectAddress => {
+                            return AppRequest::OpenAddressView(self.adapter.id)
+                        }
+                        DeviceViewCommand::ShowNew => {
+                            let showing_new = self
+                                .filter
+                                .as_ref()
+                                .is_some_and(|f| f.to_string() == NEW_FILTER);
+                            return match showing_new {
+                                true => {
+                                    self.set_filter("")
+                                        + AppRequest::AcknowledgeNew(self.adapter.id)
+                                }
+                                false => self.set_filter(NEW_FILTER),
+                            };
+                        }
+                        DeviceViewCommand::Acknowledge => {
+                            if let Some(device) = self.table.selected_value()
+                                && device.is_new
+                            {
+                                return AppRequest::AcknowledgeDevice(device.id);
+                            }
+                        }
+                        DeviceViewCommand::AcknowledgeAll => {
+                            return AppRequest::AcknowledgeNew(self.adapter.id)
+                        }
+                        DeviceViewCommand::PairElsewhere => {
+                            if let Some(device) = self.table.selected_value() {
+                                return AppRequest::OpenPairElsewhereView(
+                                    self.adapter.id,
+                                    device.id,
+                                );
+                            }
+                        }
+                        DeviceViewCommand::Repeat => {
+                            if let Some(device) = self.table.selected_value() {
+                                return AppRequest::RepeatDeviceAction(self.adapter.id, device.id);
+                            }
+                        }
+                        DeviceViewCommand::WidenColumn => {
+                            DEVICE_COLUMNS.resize(self.column, 2);
+                        }
+                        DeviceViewCommand::NarrowColumn => {
+                            DEVICE_COLUMNS.resize(self.column, -2);
+                        }
+                        DeviceViewCommand::PrevColumn => {
+                            self.column = self.column.saturating_sub(1);
+                        }
+                        DeviceViewCommand::NextColumn => {
+                            self.column = (self.column + 1).min(DEVICE_COLUMNS.columns() - 1);
+                        }
                     }
                 }
             }
@@ -378,10 +735,20 @@ impl View for DeviceView<'_> {
                     y: ev.row,
                 };
                 match ev.kind {
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        if self.table.select_at(pos)
+                            && let Some(device) = self.table.selected_value()
+                            && self.clicks.double_click(device.id)
+                        {
+                            return AppRequest::OpenDeviceActionsViewAt(
+                                self.adapter.clone(),
+                                device.id,
+                                (pos.x, pos.y + 1).into(),
+                            );
+                        }
+                    }
                     MouseEventKind::Down(MouseButton::Right) => {
-                        if let Some(row) = self.table.screen_coords_to_row_index(pos)
-                            && let Some(idx) = self.table.selected_row()
-                            && row == idx
+                        if self.table.select_at(pos)
                             && let Some(device) = self.table.selected_value()
                         {
                             return AppRequest::OpenDeviceActionsViewAt(
@@ -403,7 +770,7 @@ impl View for DeviceView<'_> {
 pub struct DeviceActionsView<'a> {
     adapter: Adapter,
     device_id: DeviceId,
-    table: StatefulTable<'a, DeviceAction>,
+    table: StyledTable<'a, DeviceAction>,
     pos: Position,
     area: Rect,
 }
@@ -480,7 +847,7 @@ impl View for DeviceActionsView<'_> {
                             return AppRequest::CloseView;
                         }
 
-                        if self.table.screen_coords_to_row_index(pos).is_some()
+                        if self.table.row_at(pos).is_some()
                             && let Some(value) = self.table.selected_value()
                         {
                             return AppRequest::CloseView
@@ -523,9 +890,10 @@ impl HelpViewActiveTable {
 }
 pub struct HelpView<'a> {
     active_table: HelpViewActiveTable,
-    app_table: StatefulTable<'a, ShortCut<AppCommand>>,
-    adapter_table: StatefulTable<'a, ShortCut<AdapterViewCommand>>,
-    device_table: StatefulTable<'a, ShortCut<DeviceViewCommand>>,
+    app_table: StyledTable<'a, ShortCut<AppCommand>>,
+    adapter_table: StyledTable<'a, ShortCut<AdapterViewCommand>>,
+    device_table: StyledTable<'a, ShortCut<DeviceViewCommand>>,
+    legend: Paragraph<'a>,
     layout: Layout,
 }
 impl HelpView<'_> {
@@ -546,6 +914,7 @@ impl HelpView<'_> {
                 TableState::default(),
                 Some("Shortcuts for devices".into()),
             ),
+            legend: Self::legend(),
             layout: Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints(vec![
@@ -556,6 +925,46 @@ impl HelpView<'_> {
             active_table: HelpViewActiveTable::App,
         }
     }
+    // every color used to communicate state, next to the text that carries the same state
+    fn legend<'a>() -> Paragraph<'a> {
+        let color = |c: &str| Color::from_str(c).unwrap();
+        let theme = &CONFIG.theme;
+        let mut entries = vec![
+            (
+                "Connected".to_string(),
+                Style::default()
+                    .fg(color(&theme.fg_connected_color))
+                    .bg(color(&theme.bg_connected_color)),
+            ),
+            (
+                "New device".to_string(),
+                Style::default()
+                    .fg(color(&theme.fg_new_device_color))
+                    .bg(color(&theme.bg_new_device_color)),
+            ),
+            (
+                "Blocked".to_string(),
+                Style::default().fg(color(&theme.fg_blocked_color)),
+            ),
+        ];
+        for category in [
+            DeviceCategory::Audio,
+            DeviceCategory::Input,
+            DeviceCategory::Phone,
+            DeviceCategory::Sensor,
+        ] {
+            if let Some(c) = category.color() {
+                entries.push((format!("{category:?}"), Style::default().fg(color(c))));
+            }
+        }
+        let spans = entries
+            .into_iter()
+            .flat_map(|(label, style)| [Span::styled(format!(" {label} "), style), Span::raw(" ")])
+            .collect::<Vec<_>>();
+        Paragraph::new(Line::from(spans))
+            .wrap(Wrap { trim: true })
+            .block(StyledWidget::block().title("Legend".to_string()))
+    }
 }
 impl View for HelpView<'_> {
     type Model = BtManager;
@@ -567,8 +976,8 @@ impl View for HelpView<'_> {
     fn update(&mut self, ev: &Event) -> Self::Signal {
         match ev {
             Event::Key(ev) => match ev.code {
-                KeyCode::Left => self.active_table.prev(),
-                KeyCode::Right => self.active_table.next(),
+                KeyCode::Left | KeyCode::BackTab => self.active_table.prev(),
+                KeyCode::Right | KeyCode::Tab => self.active_table.next(),
                 _ => {}
             },
             Event::Mouse(ev) => {
@@ -576,11 +985,11 @@ impl View for HelpView<'_> {
                     x: ev.column,
                     y: ev.row,
                 };
-                if self.app_table.screen_coords_to_row_index(pos).is_some() {
+                if self.app_table.row_at(pos).is_some() {
                     self.active_table = HelpViewActiveTable::App;
-                } else if self.adapter_table.screen_coords_to_row_index(pos).is_some() {
+                } else if self.adapter_table.row_at(pos).is_some() {
                     self.active_table = HelpViewActiveTable::Adapter;
-                } else if self.device_table.screen_coords_to_row_index(pos).is_some() {
+                } else if self.device_table.row_at(pos).is_some() {
                     self.active_table = HelpViewActiveTable::Device;
                 }
             }
@@ -595,39 +1004,1548 @@ impl View for HelpView<'_> {
         Self::Signal::default()
     }
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
-        let layout = self.layout.split(area);
+        let [tables, legend] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(3)])
+            .areas(area);
+        f.render_widget(&self.legend, legend);
+        let layout = self.layout.split(tables);
         self.app_table.draw(f, layout[0]);
         self.adapter_table.draw(f, layout[1]);
         self.device_table.draw(f, layout[2]);
+        match self.active_table {
+            HelpViewActiveTable::App => self.app_table.draw_focus(f, layout[0]),
+            HelpViewActiveTable::Adapter => self.adapter_table.draw_focus(f, layout[1]),
+            HelpViewActiveTable::Device => self.device_table.draw_focus(f, layout[2]),
+        }
     }
 }
 
-pub struct PopupView<'a> {
-    p: Paragraph<'a>,
+pub struct MonitorView<'a> {
+    device_id: DeviceId,
+    rows: Vec<PropertyRow>,
+    table: StyledTable<'a, PropertyRow>,
 }
-impl PopupView<'_> {
-    pub fn new(msg: String) -> Self {
+impl MonitorView<'_> {
+    pub fn new(device_id: DeviceId, rows: Vec<PropertyRow>, state: TableState) -> Self {
         Self {
-            p: Paragraph::new(msg).block(Block::default().borders(Borders::ALL)),
+            table: StyledWidget::table(rows.clone(), state, Some(format!("Monitor {device_id}"))),
+            device_id,
+            rows,
         }
     }
 }
-impl View for PopupView<'_> {
+impl View for MonitorView<'_> {
     type Model = BtManager;
     type Signal = AppRequest;
     type Kind = ViewKind;
     fn kind(&self) -> ViewKind {
-        ViewKind::NotificationView
+        ViewKind::MonitorView
     }
-    fn compute_area(&self, area: Rect) -> Rect {
-        let (width, height) = (50, 15);
-        let (width, height) = (width.min(area.width), height.min(area.height));
-        centered_rect(area, (width, height))
+    fn title(&self) -> String {
+        "bluerat - monitor".to_string()
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        *self = Self::new(
+            self.device_id,
+            model.monitored.clone(),
+            self.table.state().clone(),
+        );
     }
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
-        f.render_widget(&self.p, area);
+        // rebuild while rows are highlighted so they fade back once the highlight expires
+        if self.rows.iter().any(|r| r.changed_at.is_some()) {
+            self.rows
+                .iter_mut()
+                .filter(|r| !r.is_highlighted())
+                .for_each(|r| r.changed_at = None);
+            *self = Self::new(
+                self.device_id,
+                self.rows.clone(),
+                self.table.state().clone(),
+            );
+        }
+        self.table.draw(f, area);
     }
-    fn is_floating(&self) -> bool {
-        true
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        AppRequest::None
+    }
+}
+
+pub struct DeviceInfoView<'a> {
+    adapter_id: AdapterId,
+    device: Device,
+    /// Manufacturer and service data of the last advertisement
+    ad_data: Vec<(String, Vec<u8>)>,
+    /// Read when the view opens, `None` when not connected or not reported
+    att_mtu: Option<usize>,
+    endian: Endian,
+    table: StyledTable<'a, UuidRow>,
+}
+impl DeviceInfoView<'_> {
+    pub fn new(
+        adapter_id: AdapterId,
+        device: Device,
+        ad_data: Vec<(String, Vec<u8>)>,
+        att_mtu: Option<usize>,
+        state: TableState,
+    ) -> Self {
+        Self {
+            table: StyledWidget::table(
+                UuidRow::for_device(&device),
+                state,
+                Some("UUIDs (y to copy)".to_string()),
+            ),
+            adapter_id,
+            device,
+            ad_data,
+            att_mtu,
+            endian: Endian::default(),
+        }
+    }
+}
+impl View for DeviceInfoView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::DeviceInfoView
+    }
+    fn title(&self) -> String {
+        "bluerat - device info".to_string()
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        let device = model
+            .get_adapter(&self.adapter_id)
+            .and_then(|a| a.get_device(&self.device.id))
+            .cloned();
+        self.ad_data = model.ad_data(&self.device.id);
+        if let Some(device) = device {
+            self.device = device;
+            self.table = StyledWidget::table(
+                UuidRow::for_device(&self.device),
+                self.table.state().clone(),
+                Some("UUIDs (y to copy)".to_string()),
+            );
+        }
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let mut info = self.device.get_info();
+        if let Some(mtu) = self.att_mtu
+            && self.device.is_connected
+        {
+            // the opcode and handle take 3 bytes of every notification and write
+            info += &format!("\nATT MTU: {mtu} ({} byte values)", mtu.saturating_sub(3));
+        }
+        let height = info.lines().count() as u16 + 2;
+        let hexdump = HexdumpPane::new(self.ad_data.clone(), self.endian);
+        let hexdump_height = match hexdump.is_empty() {
+            true => 0,
+            false => hexdump.height(),
+        };
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(height),
+                Constraint::Min(0),
+                Constraint::Length(hexdump_height),
+            ])
+            .split(area);
+        let info =
+            Paragraph::new(info).block(StyledWidget::block().title(self.device.alias.clone()));
+        f.render_widget(info, layout[0]);
+        self.table.draw(f, layout[1]);
+        if !hexdump.is_empty() {
+            hexdump.draw(f, layout[2], "Advertising data");
+        }
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) if ev.code == KeyCode::Char('y') => match self.table.selected_value() {
+                Some(row) => AppRequest::CopyToClipboard(row.uuid.clone()),
+                None => AppRequest::None,
+            },
+            Event::Key(ev) if ev.code == KeyCode::Char('e') => {
+                self.endian = self.endian.toggled();
+                AppRequest::None
+            }
+            _ => AppRequest::None,
+        }
+    }
+}
+
+pub struct GattView<'a> {
+    alias: String,
+    endian: Endian,
+    /// Snippets of every characteristic of the device
+    snippets: Vec<Snippet>,
+    table: StyledTable<'a, GattRow>,
+}
+impl GattView<'_> {
+    pub fn new(
+        alias: String,
+        rows: Vec<GattRow>,
+        snippets: Vec<Snippet>,
+        state: TableState,
+        endian: Endian,
+    ) -> Self {
+        let title = format!("GATT {alias} (r to read, w to poll, enter to write)");
+        Self {
+            table: StyledWidget::table(rows, state, Some(title)),
+            alias,
+            endian,
+            snippets,
+        }
+    }
+    fn row_snippets(&self, row: &GattRow) -> Vec<&Snippet> {
+        self.snippets
+            .iter()
+            .filter(|s| s.characteristic == row.uuid)
+            .take(9)
+            .collect()
+    }
+}
+impl View for GattView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::GattView
+    }
+    fn title(&self) -> String {
+        "bluerat - gatt".to_string()
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        *self = Self::new(
+            self.alias.clone(),
+            model.gatt.clone(),
+            model.gatt_snippets(),
+            self.table.state().clone(),
+            self.endian,
+        );
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let Some(row) = self.table.selected_value().cloned() else {
+            self.table.draw(f, area);
+            return;
+        };
+        let hexdump = HexdumpPane::new(vec![(row.uuid.clone(), row.value.clone())], self.endian);
+        let plotted = row.is_polled && !row.samples.is_empty();
+        let snippets = self
+            .row_snippets(&row)
+            .iter()
+            .enumerate()
+            .map(|(i, s)| format!("{} {}  {} ({})", i + 1, s.name, s.value, s.write_type))
+            .collect_vec();
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(hexdump.height()),
+                Constraint::Length(match snippets.len() {
+                    0 => 0,
+                    n => n as u16 + 2,
+                }),
+                Constraint::Length(if plotted { 6 } else { 0 }),
+            ])
+            .split(area);
+        self.table.draw(f, layout[0]);
+        hexdump.draw(f, layout[1], &row.name());
+        if !snippets.is_empty() {
+            let block = StyledWidget::block().title("Snippets (1-9 to send)");
+            f.render_widget(Paragraph::new(snippets.join("\n")).block(block), layout[2]);
+        }
+        if plotted {
+            let last = row.samples.last().copied().unwrap_or_default();
+            let title = format!(
+                "{} every {}s, last {last}",
+                row.name(),
+                CONFIG.gatt_poll_every
+            );
+            let sparkline = Sparkline::default()
+                .block(StyledWidget::block().title(title))
+                .style(
+                    Style::default().fg(Color::from_str(&CONFIG.theme.fg_connected_color).unwrap()),
+                )
+                .data(&row.samples);
+            f.render_widget(sparkline, layout[3]);
+        }
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        let Event::Key(ev) = ev else {
+            return AppRequest::None;
+        };
+        match (ev.code, self.table.selected_value()) {
+            (KeyCode::Char('r'), Some(row)) => AppRequest::ReadCharacteristic(row.key()),
+            (KeyCode::Char('w'), Some(row)) => AppRequest::ToggleGattPoll(row.key()),
+            (KeyCode::Enter, Some(row)) => AppRequest::OpenWriteView(row.clone()),
+            (KeyCode::Char(c @ '1'..='9'), Some(row)) => {
+                let n = c as usize - '1' as usize;
+                match self.row_snippets(row).get(n) {
+                    Some(s) => AppRequest::SendSnippet(row.key(), (*s).clone()),
+                    None => AppRequest::None,
+                }
+            }
+            (KeyCode::Char('e'), _) => {
+                self.endian = self.endian.toggled();
+                AppRequest::None
+            }
+            _ => AppRequest::None,
+        }
+    }
+}
+
+pub struct BatteryView {
+    device_id: DeviceId,
+    alias: String,
+    samples: Vec<BatterySample>,
+    experimental: Option<bool>,
+}
+impl BatteryView {
+    pub fn new(device_id: DeviceId, alias: String, samples: Vec<BatterySample>) -> Self {
+        Self {
+            device_id,
+            alias,
+            samples,
+            experimental: None,
+        }
+    }
+}
+impl View for BatteryView {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::BatteryView
+    }
+    fn title(&self) -> String {
+        "bluerat - battery".to_string()
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        self.samples = model.state.battery_history(&self.device_id);
+        self.experimental = model.experimental;
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let block = StyledWidget::block().title(format!("Battery {}", self.alias));
+        let Some(first) = self.samples.first() else {
+            // older bluez only reports battery levels in experimental mode
+            let msg = match self.experimental {
+                Some(false) => {
+                    "No battery samples yet, battery levels may need bluetoothd --experimental"
+                }
+                _ => "No battery samples yet, they are recorded while connected",
+            };
+            let msg = Paragraph::new(msg).block(block);
+            f.render_widget(msg, area);
+            return;
+        };
+        // hours before now, shown in days past two days
+        let now = Utc::now().timestamp();
+        let span = (now - first.at).max(60) as f64 / 3600.0;
+        let (unit, scale) = match span > 48.0 {
+            true => ("d", 24.0),
+            false => ("h", 1.0),
+        };
+        let points = self
+            .samples
+            .iter()
+            .map(|s| ((s.at - now) as f64 / 3600.0 / scale, s.level as f64))
+            .collect_vec();
+        let min_x = -span / scale;
+        let dataset = Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::from_str(&CONFIG.theme.fg_connected_color).unwrap()))
+            .data(&points);
+        let chart = Chart::new(vec![dataset])
+            .block(block)
+            .x_axis(
+                Axis::default()
+                    .bounds([min_x, 0.0])
+                    .labels([format!("{min_x:.1}{unit}"), "now".to_string()]),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds([0.0, 100.0])
+                    .labels(["0%", "50%", "100%"]),
+            );
+        f.render_widget(chart, area);
+    }
+    fn update(&mut self, _ev: &Event) -> AppRequest {
+        AppRequest::None
+    }
+}
+
+pub struct SummaryView<'a> {
+    adapter_id: AdapterId,
+    table: StyledTable<'a, SummaryRow>,
+}
+impl SummaryView<'_> {
+    pub fn new(adapter: &Adapter, found: &HashSet<DeviceId>) -> Self {
+        Self {
+            adapter_id: adapter.id,
+            table: StyledWidget::table(
+                SummaryRow::for_adapter(adapter, found),
+                TableState::new().with_selected(0),
+                Some(format!("Summary {}", adapter.name)),
+            ),
+        }
+    }
+}
+impl View for SummaryView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::SummaryView
+    }
+    fn title(&self) -> String {
+        "bluerat - summary".to_string()
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        if let Some(adapter) = model.get_adapter(&self.adapter_id) {
+            *self = Self::new(adapter, &model.found);
+        }
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.table.draw(f, area);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        AppRequest::None
+    }
+}
+
+pub struct QuickView<'a> {
+    table: StyledTable<'a, QuickRow>,
+}
+impl QuickView<'_> {
+    pub fn new(bt: &BtManager, state: TableState) -> Self {
+        let rows = QuickRow::collect(&bt.get_adapters(&Adapter::BY_ADDRESS));
+        Self {
+            table: StyledWidget::table(
+                rows,
+                state,
+                Some("Paired devices (enter to connect/disconnect)".to_string()),
+            ),
+        }
+    }
+}
+impl View for QuickView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::QuickView
+    }
+    fn title(&self) -> String {
+        "bluerat - paired".to_string()
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        *self = Self::new(model, self.table.state().clone());
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.table.draw(f, area);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) if matches!(ev.code, KeyCode::Enter | KeyCode::Char(' ')) => {
+                match self.table.selected_value() {
+                    Some(row) => AppRequest::ExecDeviceAction(
+                        row.adapter_id,
+                        row.device.id,
+                        DeviceAction::SetConnected(!row.device.is_connected),
+                    ),
+                    None => AppRequest::None,
+                }
+            }
+            _ => AppRequest::None,
+        }
+    }
+}
+
+pub struct HistoryView<'a> {
+    table: StyledTable<'a, ActionRecord>,
+}
+impl HistoryView<'_> {
+    pub fn new(actions: &[ActionRecord], state: TableState) -> Self {
+        let rows = actions.iter().rev().cloned().collect_vec();
+        Self {
+            table: StyledWidget::table(rows, state, Some("Actions (e to export)".to_string())),
+        }
+    }
+}
+impl View for HistoryView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::HistoryView
+    }
+    fn title(&self) -> String {
+        "bluerat - actions".to_string()
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        *self = Self::new(&model.actions, self.table.state().clone());
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.table.draw(f, area);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) if ev.code == KeyCode::Char('e') => AppRequest::ExportActions,
+            _ => AppRequest::None,
+        }
+    }
+}
+
+pub struct BatchView<'a> {
+    rows: Vec<BatchRow>,
+    table: StyledTable<'a, BatchRow>,
+}
+impl BatchView<'_> {
+    pub fn new(rows: Vec<BatchRow>) -> Self {
+        let done = rows.iter().filter(|r| r.status.is_finished()).count();
+        let title = if done < rows.len() {
+            format!("Pairing {done}/{}", rows.len())
+        } else {
+            let failed = rows
+                .iter()
+                .filter(|r| matches!(r.status, BatchStatus::Failed(_)))
+                .count();
+            format!("Done: {} paired, {failed} failed", rows.len() - failed)
+        };
+        Self {
+            table: StyledWidget::table(rows.clone(), TableState::default(), Some(title)),
+            rows,
+        }
+    }
+}
+impl View for BatchView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::BatchView
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        let (width, height) = self.table.min_area();
+        centered_rect(
+            area,
+            (width.max(40).min(area.width), height.min(area.height)),
+        )
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        if !model.batch.is_empty() {
+            *self = Self::new(model.batch.clone());
+        }
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        f.render_widget(Clear, area);
+        self.table.draw(f, area);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        if let Event::Key(ev) = ev
+            && ev.code == KeyCode::Enter
+            && self.rows.iter().all(|r| r.status.is_finished())
+        {
+            return AppRequest::CloseView;
+        }
+        AppRequest::None
+    }
+}
+
+pub struct SearchView<'a> {
+    text: String,
+    input: Paragraph<'a>,
+    devices: Vec<QuickRow>,
+    table: StyledTable<'a, QuickRow>,
+}
+impl SearchView<'_> {
+    pub fn new(bt: &BtManager) -> Self {
+        let devices = bt
+            .get_adapters(&Adapter::BY_ADDRESS)
+            .into_iter()
+            .flat_map(|a| {
+                a.devices.into_iter().map(move |device| QuickRow {
+                    adapter_id: a.id,
+                    adapter: a.name.clone(),
+                    device,
+                })
+            })
+            .collect();
+        let mut view = Self {
+            text: String::new(),
+            input: Self::input(""),
+            devices,
+            table: StyledWidget::table(vec![], TableState::new(), None),
+        };
+        view.rebuild();
+        view
+    }
+    fn input<'a>(text: &str) -> Paragraph<'a> {
+        Paragraph::new(format!("/{text}"))
+            .style(StyledWidget::popup_style())
+            .block(StyledWidget::popup_block().title("Search every adapter"))
+    }
+    fn rebuild(&mut self) {
+        let rows = match Filter::parse(&self.text) {
+            Ok(filter) => self
+                .devices
+                .iter()
+                .filter(|r| filter.matches(&r.device))
+                .cloned()
+                .collect_vec(),
+            Err(_) => vec![],
+        };
+        let title = format!("{} devices (enter to open)", rows.len());
+        self.input = Self::input(&self.text);
+        self.table = StyledWidget::table(rows, TableState::new().with_selected(0), Some(title));
+    }
+}
+impl View for SearchView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::InputView
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        centered_rect(area, (80.min(area.width), (area.height * 2 / 3).max(8)))
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(3), Constraint::Fill(1)])
+            .split(area);
+        f.render_widget(Clear, area);
+        f.render_widget(&self.input, layout[0]);
+        self.table.draw(f, layout[1]);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        let key = match ev {
+            Event::Key(key) => key,
+            Event::Paste(text) => {
+                self.text.push_str(&paste_line(text));
+                self.rebuild();
+                return AppRequest::None;
+            }
+            _ => {
+                self.table.update(ev);
+                return AppRequest::None;
+            }
+        };
+        match key.code {
+            KeyCode::Esc => return AppRequest::CloseView,
+            KeyCode::Enter => {
+                return match self.table.selected_value() {
+                    Some(row) => AppRequest::CloseView + AppRequest::FocusDevice(row.device.id),
+                    None => AppRequest::None,
+                };
+            }
+            KeyCode::Backspace => {
+                let _ = self.text.pop();
+                self.rebuild();
+            }
+            KeyCode::Char(c) => {
+                self.text.push(c);
+                self.rebuild();
+            }
+            _ => self.table.update(ev),
+        }
+        AppRequest::None
+    }
+}
+
+pub struct AddressView<'a> {
+    adapter_id: AdapterId,
+    text: String,
+    input: Paragraph<'a>,
+}
+impl AddressView<'_> {
+    pub fn new(adapter_id: AdapterId) -> Self {
+        Self {
+            adapter_id,
+            input: Self::input(""),
+            text: String::new(),
+        }
+    }
+    fn input<'a>(text: &str) -> Paragraph<'a> {
+        Paragraph::new(format!(":connect {text}"))
+            .style(StyledWidget::popup_style())
+            .block(
+                StyledWidget::popup_block()
+                    .title("Connect to address, e.g. AA:BB:CC:DD:EE:FF le-random"),
+            )
+    }
+}
+impl View for AddressView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::InputView
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        Rect {
+            y: area.bottom().saturating_sub(4),
+            height: 3.min(area.height),
+            ..area
+        }
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        f.render_widget(Clear, area);
+        f.render_widget(&self.input, area);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        if let Event::Paste(text) = ev {
+            self.text.push_str(&paste_line(text));
+            self.input = Self::input(&self.text);
+        }
+        if let Event::Key(ev) = ev {
+            match ev.code {
+                KeyCode::Enter if !self.text.is_empty() => {
+                    return AppRequest::CloseView
+                        + AppRequest::ConnectAddress(self.adapter_id, self.text.clone())
+                }
+                KeyCode::Enter | KeyCode::Esc => return AppRequest::CloseView,
+                KeyCode::Backspace => {
+                    let _ = self.text.pop();
+                }
+                KeyCode::Char(c) => self.text.push(c),
+                _ => return AppRequest::None,
+            }
+            self.input = Self::input(&self.text);
+        }
+        AppRequest::None
+    }
+}
+
+pub struct WriteView<'a> {
+    row: GattRow,
+    /// The write types the characteristic supports, the chosen one first
+    write_types: Vec<WriteType>,
+    text: String,
+    input: Paragraph<'a>,
+}
+impl WriteView<'_> {
+    pub fn new(row: GattRow) -> Self {
+        let write_types = row.write_types();
+        let mut view = Self {
+            row,
+            write_types,
+            text: String::new(),
+            input: Paragraph::default(),
+        };
+        view.input = view.input();
+        view
+    }
+    fn input<'a>(&self) -> Paragraph<'a> {
+        let supported = self.write_types.iter().map(|t| t.to_string()).join(", ");
+        let title = match self.write_types.len() {
+            1 => format!("Write {} hex, {supported}", self.row.name()),
+            _ => format!(
+                "Write {} hex, {} (supports {supported}, Tab to switch)",
+                self.row.name(),
+                self.write_types[0]
+            ),
+        };
+        let title = format!("{title}, name = hex to save");
+        // a named payload reads better without the prefix
+        let text = match self.text.contains('=') {
+            true => self.text.clone(),
+            false => format!("0x{}", self.text),
+        };
+        Paragraph::new(text)
+            .style(StyledWidget::popup_style())
+            .block(StyledWidget::popup_block().title(title))
+    }
+}
+impl View for WriteView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::InputView
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        Rect {
+            y: area.bottom().saturating_sub(4),
+            height: 3.min(area.height),
+            ..area
+        }
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        f.render_widget(Clear, area);
+        f.render_widget(&self.input, area);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        if let Event::Paste(text) = ev {
+            self.text.push_str(&paste_line(text));
+            self.input = self.input();
+        }
+        if let Event::Key(ev) = ev {
+            match ev.code {
+                KeyCode::Enter if !self.text.is_empty() => {
+                    return AppRequest::CloseView
+                        + AppRequest::WriteCharacteristic(
+                            self.row.key(),
+                            self.text.clone(),
+                            self.write_types[0],
+                        );
+                }
+                KeyCode::Enter | KeyCode::Esc => return AppRequest::CloseView,
+                KeyCode::Tab => self.write_types.rotate_left(1),
+                KeyCode::Backspace => {
+                    let _ = self.text.pop();
+                }
+                KeyCode::Char(c) => self.text.push(c),
+                _ => return AppRequest::None,
+            }
+            self.input = self.input();
+        }
+        AppRequest::None
+    }
+}
+
+pub struct PopupView<'a> {
+    msg: String,
+    p: Paragraph<'a>,
+    scroll: u16,
+    max_scroll: u16,
+    page: u16,
+    area: Rect,
+}
+impl PopupView<'_> {
+    pub fn new(popup: &Popup) -> Self {
+        Self {
+            msg: popup.msg.clone(),
+            p: Paragraph::new(popup.msg.clone())
+                .style(StyledWidget::popup_style())
+                .wrap(Wrap { trim: false })
+                .block(StyledWidget::severity_block(popup.severity).title(popup.title.clone())),
+            scroll: 0,
+            max_scroll: 0,
+            page: 1,
+            area: Rect::default(),
+        }
+    }
+    // an estimate, ratatui only counts the wrapped lines behind an unstable feature
+    fn wrapped_lines(&self, width: u16) -> u16 {
+        let width = usize::from(width.max(1));
+        let lines: usize = self
+            .msg
+            .lines()
+            .map(|l| l.chars().count().div_ceil(width).max(1))
+            .sum();
+        lines.try_into().unwrap_or(u16::MAX)
+    }
+    fn scroll_by(&mut self, delta: i32) {
+        let scroll = (i32::from(self.scroll) + delta).clamp(0, i32::from(self.max_scroll));
+        self.scroll = scroll as u16;
+    }
+}
+impl View for PopupView<'_> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::NotificationView
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        let width = 60.min(area.width);
+        let lines = self.wrapped_lines(width.saturating_sub(2));
+        let height = lines
+            .saturating_add(2)
+            .clamp(5, (area.height * 2 / 3).max(5))
+            .min(area.height);
+        centered_rect(area, (width, height))
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.area = area;
+        let inner = area.inner(Margin::new(1, 1));
+        self.page = inner.height.max(1);
+        self.max_scroll = self.wrapped_lines(inner.width).saturating_sub(inner.height);
+        self.scroll = self.scroll.min(self.max_scroll);
+
+        f.render_widget(Clear, area);
+        f.render_widget(self.p.clone().scroll((self.scroll, 0)), area);
+        if self.max_scroll > 0 {
+            let mut state = ScrollbarState::new(usize::from(self.max_scroll) + 1)
+                .position(usize::from(self.scroll));
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(None)
+                    .end_symbol(None),
+                inner,
+                &mut state,
+            );
+        }
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        let page = i32::from(self.page);
+        match ev {
+            Event::Key(ev) => match ev.code {
+                KeyCode::Enter | KeyCode::Esc => return AppRequest::CloseView,
+                KeyCode::Down | KeyCode::Char('j') => self.scroll_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll_by(-1),
+                KeyCode::PageDown => self.scroll_by(page),
+                KeyCode::PageUp => self.scroll_by(-page),
+                KeyCode::Home => self.scroll = 0,
+                KeyCode::End => self.scroll = self.max_scroll,
+                _ => {}
+            },
+            Event::Mouse(ev) => match ev.kind {
+                MouseEventKind::ScrollDown => self.scroll_by(1),
+                MouseEventKind::ScrollUp => self.scroll_by(-1),
+                MouseEventKind::Down(_) => {
+                    let pos = Position {
+                        x: ev.column,
+                        y: ev.row,
+                    };
+                    if !self.area.contains(pos) {
+                        return AppRequest::CloseView;
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        AppRequest::None
+    }
+}
+
+pub struct CheatsheetView {
+    bindings: Vec<(String, String)>,
+}
+impl CheatsheetView {
+    pub fn new(kind: ViewKind) -> Self {
+        Self {
+            bindings: bindings_for(kind),
+        }
+    }
+    fn cell_width(&self) -> u16 {
+        let width = self
+            .bindings
+            .iter()
+            .map(|(keys, desc)| keys.len() + desc.len() + 1)
+            .max()
+            .unwrap_or(0);
+        width as u16 + 3
+    }
+    // bindings laid out column first, as many columns as fit in `width`
+    fn grid(&self, width: u16) -> Vec<Line<'_>> {
+        let cell = self.cell_width();
+        let cols = (width / cell).max(1) as usize;
+        let rows = self.bindings.len().div_ceil(cols);
+        (0..rows)
+            .map(|row| {
+                let spans = (0..cols)
+                    .filter_map(|col| self.bindings.get(col * rows + row))
+                    .flat_map(|(keys, desc)| {
+                        let pad = cell as usize - keys.len() - desc.len() - 1;
+                        [
+                            Span::styled(keys.clone(), StyledWidget::match_style()),
+                            Span::raw(format!(" {desc}{}", " ".repeat(pad))),
+                        ]
+                    })
+                    .collect_vec();
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+impl View for CheatsheetView {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::CheatsheetView
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        let width = area.width.saturating_sub(4).min(self.cell_width() * 3 + 2);
+        let height = self.grid(width.saturating_sub(2)).len() as u16 + 2;
+        centered_rect(area, (width, height.min(area.height)))
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let grid = Paragraph::new(self.grid(area.width.saturating_sub(2)))
+            .block(StyledWidget::popup_block().title("Keys"));
+        f.render_widget(Clear, area);
+        f.render_widget(grid, area);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        match ev {
+            Event::Key(_) | Event::Mouse(_) => AppRequest::CloseView,
+            _ => AppRequest::None,
+        }
+    }
+}
+
+/// What a confirmation popup offers, each choice turning into a request
+pub trait Choice: Clone + Display {
+    /// Kept by the popup for the requests, like the adapter to unblock
+    type Context;
+    const KIND: ViewKind;
+    /// Window title while the popup is open, empty keeps the default
+    const TITLE: &'static str = "";
+    /// Rows taken by the message, borders included
+    const MESSAGE_HEIGHT: u16 = 4;
+    /// The cells of the choice's row
+    fn cells(&self, _context: &Self::Context) -> Vec<String> {
+        vec![self.to_string()]
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Fill]
+    }
+    /// The request made by picking the choice, closing the popup included, the settings a
+    /// choice cycles being changed in the context
+    fn request(&self, context: &mut Self::Context) -> AppRequest;
+}
+impl Choice for ServiceChoice {
+    type Context = ();
+    const KIND: ViewKind = ViewKind::ServiceView;
+    fn request(&self, _: &mut ()) -> AppRequest {
+        match self {
+            ServiceChoice::Start => AppRequest::CloseView + AppRequest::StartService,
+            ServiceChoice::Dismiss => AppRequest::CloseView,
+        }
+    }
+}
+impl Choice for UnblockChoice {
+    type Context = Adapter;
+    const KIND: ViewKind = ViewKind::UnblockView;
+    fn request(&self, adapter: &mut Adapter) -> AppRequest {
+        match self {
+            UnblockChoice::Unblock => {
+                AppRequest::CloseView + AppRequest::UnblockAdapter(adapter.clone())
+            }
+            UnblockChoice::Dismiss => AppRequest::CloseView,
+        }
+    }
+}
+impl Choice for PairingChoice {
+    type Context = ();
+    const KIND: ViewKind = ViewKind::PairingPromptView;
+    // answered before closing, as closing the prompt rejects it
+    fn request(&self, _: &mut ()) -> AppRequest {
+        let accept = matches!(self, PairingChoice::Accept);
+        AppRequest::AnswerPairing(accept) + AppRequest::CloseView
+    }
+}
+/// Retrying runs the failed action again on the device
+impl Choice for RetryChoice {
+    type Context = (AdapterId, DeviceId, DeviceAction);
+    const KIND: ViewKind = ViewKind::RetryView;
+    fn request(&self, (adapter_id, device_id, action): &mut Self::Context) -> AppRequest {
+        let action = match self {
+            RetryChoice::Retry => *action,
+            RetryChoice::Info => DeviceAction::Info,
+            RetryChoice::Dismiss => return AppRequest::CloseView,
+        };
+        AppRequest::CloseView + AppRequest::ExecDeviceAction(*adapter_id, *device_id, action)
+    }
+}
+impl Choice for QuitChoice {
+    type Context = ();
+    const KIND: ViewKind = ViewKind::QuitView;
+    fn request(&self, _: &mut ()) -> AppRequest {
+        match self {
+            QuitChoice::Wait => AppRequest::CloseView + AppRequest::QuitWhenIdle,
+            QuitChoice::Quit => AppRequest::Quit,
+        }
+    }
+}
+/// Muting keeps the listed problems out of the popup on the next launches
+impl Choice for StartupChoice {
+    type Context = Vec<String>;
+    const KIND: ViewKind = ViewKind::StartupView;
+    fn request(&self, problems: &mut Vec<String>) -> AppRequest {
+        match self {
+            StartupChoice::Dismiss => AppRequest::CloseView,
+            StartupChoice::Mute => {
+                AppRequest::CloseView + AppRequest::MuteStartupChecks(problems.clone())
+            }
+        }
+    }
+}
+/// Applying power cycles the adapter to change its LE privacy
+impl Choice for PrivacyChoice {
+    type Context = (Adapter, bool);
+    const KIND: ViewKind = ViewKind::PrivacyView;
+    fn request(&self, (adapter, on): &mut (Adapter, bool)) -> AppRequest {
+        match self {
+            PrivacyChoice::Apply => {
+                AppRequest::CloseView + AppRequest::SetPrivacy(adapter.clone(), *on)
+            }
+            PrivacyChoice::Cancel => AppRequest::CloseView,
+        }
+    }
+}
+/// The adapter to pair the device on
+impl Choice for Adapter {
+    type Context = DeviceId;
+    const KIND: ViewKind = ViewKind::PairElsewhereView;
+    fn cells(&self, _: &DeviceId) -> Vec<String> {
+        Tabular::content(self)
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        <Adapter as Tabular>::column_constraints()
+    }
+    fn request(&self, device_id: &mut DeviceId) -> AppRequest {
+        AppRequest::CloseView + AppRequest::PairOnAdapter(self.id, *device_id)
+    }
+}
+/// The defaults picked on the first launch, with the adapter names to cycle through
+pub type Welcome = (Setup, Vec<String>);
+/// Picking a setting cycles its value, the popup staying open until saved or skipped
+impl Choice for SetupField {
+    type Context = Welcome;
+    const KIND: ViewKind = ViewKind::WelcomeView;
+    const TITLE: &'static str = "bluerat - welcome";
+    const MESSAGE_HEIGHT: u16 = 5;
+    fn cells(&self, (setup, _): &Welcome) -> Vec<String> {
+        let value = match self {
+            SetupField::Theme => setup.preset.clone(),
+            SetupField::Adapter => setup
+                .default_adapter
+                .clone()
+                .unwrap_or("busiest".to_string()),
+            SetupField::ScanOnStartup => match setup.scan_on_startup {
+                true => "yes".to_string(),
+                false => "no".to_string(),
+            },
+            SetupField::Save | SetupField::Skip => String::new(),
+        };
+        vec![self.to_string(), value]
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![|_| Constraint::Length(18), Constraint::Fill]
+    }
+    fn request(&self, (setup, adapters): &mut Welcome) -> AppRequest {
+        match self {
+            SetupField::Theme => {
+                let i = THEME_PRESETS
+                    .iter()
+                    .position(|p| *p == setup.preset)
+                    .map_or(0, |i| (i + 1) % THEME_PRESETS.len());
+                setup.preset = THEME_PRESETS[i].to_string();
+            }
+            // cycles through the adapters, then back to none
+            SetupField::Adapter => {
+                let next = match &setup.default_adapter {
+                    Some(name) => adapters.iter().position(|a| a == name).map(|i| i + 1),
+                    None => Some(0),
+                };
+                setup.default_adapter = next.and_then(|i| adapters.get(i)).cloned();
+            }
+            SetupField::ScanOnStartup => setup.scan_on_startup = !setup.scan_on_startup,
+            SetupField::Save => {
+                return AppRequest::CloseView + AppRequest::SaveSetup(setup.clone());
+            }
+            SetupField::Skip => return AppRequest::CloseView,
+        }
+        AppRequest::None
+    }
+}
+
+/// Row of a confirmation popup
+#[derive(Clone)]
+pub struct ChoiceRow<C> {
+    choice: C,
+    cells: Vec<String>,
+}
+impl<C: Choice> Tabular for ChoiceRow<C> {
+    type Value = C;
+    type ColumnValue = ();
+    fn column_values() -> Vec<Self::ColumnValue> {
+        vec![]
+    }
+    fn value(&self) -> Self::Value {
+        self.choice.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        self.cells.clone()
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        C::column_constraints()
+    }
+}
+
+/// Popup with a message and a few choices, a click outside closes it
+pub struct ConfirmView<'a, C: Choice> {
+    context: C::Context,
+    choices: Vec<C>,
+    msg: Paragraph<'a>,
+    table: StyledTable<'a, ChoiceRow<C>>,
+    area: Rect,
+}
+impl<C: Choice> ConfirmView<'_, C> {
+    pub fn new(title: &str, msg: String, choices: Vec<C>, context: C::Context) -> Self {
+        let mut view = Self {
+            context,
+            choices,
+            msg: Paragraph::new(msg)
+                .wrap(Wrap { trim: true })
+                .block(StyledWidget::popup_block().title(title.to_string())),
+            table: StyledWidget::table(vec![], TableState::new(), None),
+            area: Rect::default(),
+        };
+        view.rebuild(TableState::new().with_selected(0));
+        view
+    }
+    fn rebuild(&mut self, state: TableState) {
+        let rows = self
+            .choices
+            .iter()
+            .map(|choice| ChoiceRow {
+                choice: choice.clone(),
+                cells: choice.cells(&self.context),
+            })
+            .collect();
+        self.table = StyledWidget::table(rows, state, None);
+    }
+    fn choose(&mut self) -> AppRequest {
+        let Some(choice) = self.table.selected_value().cloned() else {
+            return AppRequest::None;
+        };
+        let req = choice.request(&mut self.context);
+        self.rebuild(self.table.state().clone());
+        req
+    }
+}
+impl ConfirmView<'_, SetupField> {
+    /// The first launch popup, picking the defaults written to a new config
+    pub fn welcome(bt: &BtManager) -> Self {
+        let adapters = bt
+            .get_adapters(&Adapter::BY_ADDRESS)
+            .into_iter()
+            .map(|a| a.name)
+            .collect_vec();
+        let setup = Setup {
+            preset: THEME_PRESETS[0].to_string(),
+            default_adapter: adapters.first().cloned(),
+            scan_on_startup: false,
+        };
+        let msg = "No configuration found, pick a few defaults to get started. \
+                   Enter changes the selected value.";
+        let choices = vec![
+            SetupField::Theme,
+            SetupField::Adapter,
+            SetupField::ScanOnStartup,
+            SetupField::Save,
+            SetupField::Skip,
+        ];
+        Self::new(
+            "Welcome to bluerat",
+            msg.to_string(),
+            choices,
+            (setup, adapters),
+        )
+    }
+}
+impl<C: Choice> View for ConfirmView<'_, C> {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        C::KIND
+    }
+    fn title(&self) -> String {
+        C::TITLE.to_string()
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        let (table_width, table_height) = self.table.min_area();
+        let width = table_width.max(50).min(area.width);
+        let height = table_height + C::MESSAGE_HEIGHT;
+        centered_rect(area, (width, height.min(area.height)))
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.area = area;
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(C::MESSAGE_HEIGHT),
+                Constraint::Fill(1),
+            ])
+            .split(area);
+        f.render_widget(Clear, area);
+        f.render_widget(&self.msg, layout[0]);
+        self.table.draw(f, layout[1]);
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) if ev.code == KeyCode::Enter => return self.choose(),
+            Event::Mouse(ev) => {
+                let pos = Position {
+                    x: ev.column,
+                    y: ev.row,
+                };
+                if let MouseEventKind::Down(MouseButton::Left) = ev.kind {
+                    if !self.area.contains(pos) {
+                        return AppRequest::CloseView;
+                    }
+                    if self.table.select_at(pos) {
+                        return self.choose();
+                    }
+                }
+            }
+            _ => {}
+        }
+        AppRequest::None
+    }
+}
+
+// 3x5 glyphs, so the code can be read from across the desk
+const DIGITS: [[&str; 5]; 10] = [
+    ["███", "█ █", "█ █", "█ █", "███"],
+    [" █ ", "██ ", " █ ", " █ ", "███"],
+    ["███", "  █", "███", "█  ", "███"],
+    ["███", "  █", "███", "  █", "███"],
+    ["█ █", "█ █", "███", "  █", "  █"],
+    ["███", "█  ", "███", "  █", "███"],
+    ["███", "█  ", "███", "█ █", "███"],
+    ["███", "  █", "  █", "  █", "  █"],
+    ["███", "█ █", "███", "█ █", "███"],
+    ["███", "█ █", "███", "  █", "███"],
+];
+
+pub struct PinView {
+    pin: Option<PinCode>,
+    alias: String,
+}
+impl PinView {
+    pub fn new(model: &BtManager) -> Self {
+        let mut view = Self {
+            pin: None,
+            alias: String::new(),
+        };
+        view.refresh(model);
+        view
+    }
+    fn code_lines(&self) -> Vec<Line<'static>> {
+        let Some(pin) = &self.pin else {
+            return vec![];
+        };
+        let entered = usize::from(pin.entered.unwrap_or(0));
+        let typed = |i: usize| match i < entered {
+            true => Style::default().add_modifier(Modifier::DIM),
+            false => Style::default().add_modifier(Modifier::BOLD),
+        };
+        let digits: Option<Vec<usize>> = pin
+            .code
+            .chars()
+            .map(|c| c.to_digit(10).map(|d| d as usize))
+            .collect();
+        // legacy pin codes may hold letters, which only get spaced out
+        let Some(digits) = digits else {
+            let spans = pin
+                .code
+                .chars()
+                .enumerate()
+                .map(|(i, c)| Span::styled(format!("{c} "), typed(i)))
+                .collect_vec();
+            return vec![Line::from(spans)];
+        };
+        (0..5)
+            .map(|row| {
+                let spans = digits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, d)| Span::styled(format!("{} ", DIGITS[*d][row]), typed(i)))
+                    .collect_vec();
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+impl View for PinView {
+    type Model = BtManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> ViewKind {
+        ViewKind::PinView
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn refresh(&mut self, model: &Self::Model) {
+        let Some(pin) = &model.pin_code else {
+            return;
+        };
+        self.alias = model
+            .get_device_adapter(&pin.device)
+            .and_then(|a| a.get_device(&pin.device))
+            .map_or(pin.device.to_string(), |d| d.alias.clone());
+        self.pin = Some(pin.clone());
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        let code_width = self.pin.as_ref().map_or(0, |p| p.code.chars().count() * 4);
+        let width = (code_width as u16 + 4).max(48).min(area.width);
+        let height = (self.code_lines().len() as u16 + 6).min(area.height);
+        centered_rect(area, (width, height))
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let hint = match self
+            .pin
+            .as_ref()
+            .and_then(|p| p.entered.map(|n| (n, p.code.len())))
+        {
+            Some((n, len)) if n > 0 => format!("{n} of {len} typed, X to cancel"),
+            _ => "Type it on the device, then press Enter".to_string(),
+        };
+        let lines = [Line::default()]
+            .into_iter()
+            .chain(self.code_lines())
+            .chain([Line::default(), Line::from(hint)])
+            .collect_vec();
+        let p = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .style(StyledWidget::popup_style())
+            .block(StyledWidget::popup_block().title(format!("Pair {}", self.alias)));
+        f.render_widget(Clear, area);
+        f.render_widget(p, area);
+    }
+    // closing leaves the pairing running, it is cancelled with its own key
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        match ev {
+            Event::Key(ev) if matches!(ev.code, KeyCode::Enter | KeyCode::Esc) => {
+                AppRequest::CloseView
+            }
+            _ => AppRequest::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    use super::*;
+    use crate::models::DeviceCategory;
+
+    fn mock_device(last: u8, alias: &str, kind: &str) -> Device {
+        Device {
+            kind: kind.to_string(),
+            category: DeviceCategory::decode(kind, None),
+            ..Device::fake([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, last], alias)
+        }
+    }
+    fn mock_adapter() -> Adapter {
+        let mut headphones = mock_device(0x01, "WH-1000XM4", "audio-headset");
+        headphones.battery = Some(80);
+        headphones.is_connected = true;
+        headphones.is_trusted = true;
+        let mut keyboard = mock_device(0x02, "MX Keys", "input-keyboard");
+        keyboard.is_blocked = true;
+        let mut sensor = mock_device(0x03, "Thermometer", "Unknown");
+        sensor.is_new = true;
+
+        Adapter {
+            devices: vec![headphones, keyboard, sensor],
+            is_pairable: true,
+            is_scanning: true,
+            connections: 1,
+            ..Adapter::fake([0x00, 0x11, 0x22, 0x33, 0x44, 0x55], "hci0")
+        }
+    }
+    fn render<V: View>(view: &mut V, (width, height): (u16, u16)) -> TestBackend {
+        let mut term = Terminal::new(TestBackend::new(width, height)).unwrap();
+        let _ = term
+            .draw(|f| {
+                let area = view.compute_area(f.area());
+                view.draw(f, area)
+            })
+            .unwrap();
+        term.backend().clone()
+    }
+
+    #[test]
+    fn adapter_view() {
+        let bt = BtManager::mock(vec![mock_adapter()]);
+        let mut view = AdapterView::new(&bt, TableState::new().with_selected(0));
+        insta::assert_snapshot!(render(&mut view, (80, 10)));
+    }
+
+    #[test]
+    fn device_view() {
+        let bt = BtManager::mock(vec![mock_adapter()]);
+        let adapter = bt.get_adapters(&Adapter::BY_NAME).remove(0);
+        let mut view = DeviceView::new(adapter, TableState::new().with_selected(1));
+        insta::assert_snapshot!(render(&mut view, (80, 12)));
+    }
+
+    #[test]
+    fn adapter_actions_view() {
+        let adapter = mock_adapter();
+        let actions = vec![
+            AdapterAction::SetPowered(!adapter.is_on),
+            AdapterAction::SetDiscoverable(!adapter.is_discoverable),
+            AdapterAction::SetScanning(!adapter.is_scanning),
+            AdapterAction::SetPairable(!adapter.is_pairable),
+            AdapterAction::Info,
+        ];
+        let mut view = AdapterActionsView::new(
+            adapter,
+            actions,
+            TableState::new().with_selected(0),
+            (2, 2).into(),
+        );
+        insta::assert_snapshot!(render(&mut view, (40, 12)));
+    }
+
+    #[test]
+    fn device_actions_view() {
+        let adapter = mock_adapter();
+        let device = adapter.devices[0].clone();
+        let actions = vec![
+            DeviceAction::SetConnected(!device.is_connected),
+            DeviceAction::SetTrusted(!device.is_trusted),
+            DeviceAction::SetBlocked(!device.is_blocked),
+            DeviceAction::SetPaired(!device.is_paired),
+            DeviceAction::Info,
+        ];
+        let mut view = DeviceActionsView::new(
+            adapter,
+            device.id,
+            actions,
+            TableState::new().with_selected(0),
+            (2, 2).into(),
+        );
+        insta::assert_snapshot!(render(&mut view, (40, 12)));
+    }
+
+    #[test]
+    fn help_view() {
+        let mut view = HelpView::new();
+        insta::assert_snapshot!(render(&mut view, (120, 20)));
     }
 }